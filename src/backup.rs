@@ -0,0 +1,342 @@
+use crate::datadir::DataDir;
+use crate::error::FlexiError;
+use crate::savable::{load_file, unchanged, Migration, Savable};
+use crate::settings::BackupSettings;
+use crate::storage::Storage;
+use crate::timedata::FlexMonth;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+
+// snapshot names, sorting them sorts by date
+const NAME_FORMAT: &str = "%Y-%m-%d_%H%M%S";
+
+/// Copy of every data file at a given time
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Snapshot {
+    pub taken: NaiveDateTime,
+    // content of each file by name
+    pub files: BTreeMap<String, String>,
+}
+
+impl Savable for Snapshot {
+    const MIGRATIONS: &'static [Migration] = &[unchanged];
+}
+
+pub enum Change {
+    // in the snapshot only
+    Missing,
+    // in the data directory only
+    Added,
+    // dates of the days that differ for a month, empty for other files
+    Changed(Vec<NaiveDate>),
+}
+
+/// Difference between a snapshot and the current data for one file
+pub struct FileDiff {
+    pub name: String,
+    pub change: Change,
+}
+
+impl fmt::Display for FileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.change {
+            Change::Missing => write!(f, "{}: missing now", self.name),
+            Change::Added => write!(f, "{}: not in backup", self.name),
+            Change::Changed(ref days) if days.is_empty() => write!(f, "{}: changed", self.name),
+            Change::Changed(ref days) => {
+                let days: Vec<String> =
+                    days.iter().map(|d| d.format("%d/%m").to_string()).collect();
+                write!(f, "{}: changed {}", self.name, days.join(" "))
+            }
+        }
+    }
+}
+
+fn parse_name(name: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(name, NAME_FORMAT).ok()
+}
+
+/// names of the snapshots, newest first
+pub fn list(dir: &DataDir) -> Result<Vec<String>, FlexiError> {
+    let backup_dir = dir.backup_dir();
+    let entries = match fs::read_dir(&backup_dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(FlexiError::io(&backup_dir.to_string_lossy(), e)),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                return None;
+            }
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            parse_name(&name).map(|_| name)
+        })
+        .collect();
+    names.sort_by(|a, b| b.cmp(a));
+    Ok(names)
+}
+
+/// snapshots to delete to keep the newest of the last `daily` days and `monthly` months
+pub fn to_prune(names: &[String], retention: &BackupSettings) -> Vec<String> {
+    let mut days: Vec<NaiveDate> = Vec::new();
+    let mut months: Vec<(i32, u32)> = Vec::new();
+    let mut dated: Vec<(&String, NaiveDateTime)> = names
+        .iter()
+        .filter_map(|name| parse_name(name).map(|taken| (name, taken)))
+        .collect();
+    dated.sort_by_key(|&(_, taken)| std::cmp::Reverse(taken));
+    let mut pruned = Vec::new();
+    for (name, taken) in dated {
+        let day = taken.date();
+        let month = (day.year(), day.month());
+        let mut keep = false;
+        if !days.contains(&day) && days.len() < retention.daily {
+            days.push(day);
+            keep = true;
+        }
+        if !months.contains(&month) && months.len() < retention.monthly {
+            months.push(month);
+            keep = true;
+        }
+        if !keep {
+            pruned.push(name.clone());
+        }
+    }
+    pruned
+}
+
+/// take a snapshot if none was taken `now`'s day, then apply the retention policy.
+/// Returns the name of the new snapshot
+pub fn daily(
+    storage: &dyn Storage,
+    now: NaiveDateTime,
+    retention: &BackupSettings,
+) -> Result<Option<String>, FlexiError> {
+    let dir = storage.dir();
+    let names = list(dir)?;
+    let latest = names.first().and_then(|name| parse_name(name));
+    if latest.is_some_and(|taken| taken.date() == now.date()) {
+        return Ok(None);
+    }
+    let taken = Snapshot::take(storage, now)?;
+    if taken.is_none() {
+        return Ok(None);
+    }
+    for name in to_prune(&list(dir)?, retention) {
+        let path = dir.backup(&name);
+        fs::remove_file(&path).map_err(|e| FlexiError::io(&path, e))?;
+    }
+    Ok(taken)
+}
+
+impl Snapshot {
+    /// save a snapshot of the data, nothing is saved if there is no data yet
+    pub fn take(storage: &dyn Storage, now: NaiveDateTime) -> Result<Option<String>, FlexiError> {
+        let dir = storage.dir();
+        let files = storage.files()?;
+        if files.is_empty() {
+            return Ok(None);
+        }
+        let snapshot = Snapshot { taken: now, files };
+        let backup_dir = dir.backup_dir();
+        fs::create_dir_all(&backup_dir)
+            .map_err(|e| FlexiError::io(&backup_dir.to_string_lossy(), e))?;
+        let name = snapshot.name();
+        snapshot.save_file(&dir.backup(&name))?;
+        Ok(Some(name))
+    }
+
+    pub fn load(dir: &DataDir, name: &str) -> Result<Snapshot, FlexiError> {
+        match load_file::<Snapshot>(&dir.backup(name))? {
+            Some(snapshot) => Ok(snapshot),
+            None => Err(FlexiError::Validation(format!("no backup named {}", name))),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.taken.format(NAME_FORMAT).to_string()
+    }
+
+    /// how the current data differs from the snapshot, one entry per file
+    pub fn diff(&self, storage: &dyn Storage) -> Result<Vec<FileDiff>, FlexiError> {
+        let current = storage.files()?;
+        let mut diffs = Vec::new();
+        for (name, content) in self.files.iter() {
+            let change = match current.get(name) {
+                None => Change::Missing,
+                Some(cur) if cur == content => continue,
+                Some(cur) => Change::Changed(changed_days(name, content, cur)),
+            };
+            diffs.push(FileDiff {
+                name: name.clone(),
+                change,
+            });
+        }
+        for name in current
+            .keys()
+            .filter(|name| !self.files.contains_key(*name))
+        {
+            diffs.push(FileDiff {
+                name: name.clone(),
+                change: Change::Added,
+            });
+        }
+        diffs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(diffs)
+    }
+
+    /// put back the data as it was in the snapshot, files created since are removed.
+    /// The current data is saved in a new snapshot first, its name is returned
+    pub fn restore(
+        &self,
+        storage: &dyn Storage,
+        now: NaiveDateTime,
+    ) -> Result<Option<String>, FlexiError> {
+        let saved = Snapshot::take(storage, now)?;
+        storage.replace_files(&self.files)?;
+        Ok(saved)
+    }
+}
+
+// dates of the days that differ between two versions of a month file
+fn changed_days(name: &str, old: &str, new: &str) -> Vec<NaiveDate> {
+    let (old, new) = match (FlexMonth::read(name, old), FlexMonth::read(name, new)) {
+        (Ok((old, _)), Ok((new, _))) => (old, new),
+        _ => return Vec::new(),
+    };
+    let days = |month: &FlexMonth| {
+        month
+            .weeks
+            .iter()
+            .flat_map(|w| w.days.iter())
+            .filter_map(|d| d.date.map(|date| (date, d.clone())))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let (old, new) = (days(&old), days(&new));
+    let mut dates: Vec<NaiveDate> = old
+        .iter()
+        .filter(|(date, day)| new.get(date) != Some(day))
+        .map(|(date, _)| *date)
+        .chain(new.keys().filter(|date| !old.contains_key(date)).cloned())
+        .collect();
+    dates.sort();
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+    use crate::storage::Json;
+    use std::path::Path;
+
+    fn test_dir(name: &str) -> DataDir {
+        let root = std::env::temp_dir().join(format!(
+            "rusty_flexi_{}_backup_{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        DataDir::new(root)
+    }
+
+    fn at(y: i32, m: u32, d: u32, h: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn to_prune_test() {
+        let retention = BackupSettings {
+            daily: 2,
+            monthly: 2,
+        };
+        let names: Vec<String> = [
+            at(2019, 5, 20, 9),
+            at(2019, 5, 21, 9),
+            at(2019, 5, 21, 17),
+            at(2019, 4, 2, 9),
+            at(2019, 4, 30, 9),
+            at(2019, 3, 31, 9),
+        ]
+        .iter()
+        .map(|t| t.format(NAME_FORMAT).to_string())
+        .collect();
+        // newest of the last 2 days and of the last 2 months
+        assert_eq!(
+            to_prune(&names, &retention),
+            vec![
+                "2019-05-21_090000".to_string(),
+                "2019-04-02_090000".to_string(),
+                "2019-03-31_090000".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn daily_test() {
+        let dir = test_dir("daily");
+        let json = Json::new(dir.clone());
+        let retention = BackupSettings::default();
+        assert_eq!(daily(&json, at(2019, 5, 20, 9), &retention).unwrap(), None);
+
+        let month = dir.month(2019, 5);
+        FlexMonth::new(2019, 5, &Settings::default())
+            .save_file(&month)
+            .unwrap();
+        let first = daily(&json, at(2019, 5, 20, 9), &retention).unwrap();
+        assert_eq!(first, Some("2019-05-20_090000".to_string()));
+        assert_eq!(daily(&json, at(2019, 5, 20, 17), &retention).unwrap(), None);
+        assert!(daily(&json, at(2019, 5, 21, 9), &retention)
+            .unwrap()
+            .is_some());
+        assert_eq!(list(&dir).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn diff_and_restore_test() {
+        let dir = test_dir("restore");
+        let json = Json::new(dir.clone());
+        let settings = Settings::default();
+        let mut may = FlexMonth::new(2019, 5, &settings);
+        may.save_file(&dir.month(2019, 5)).unwrap();
+        let name = Snapshot::take(&json, at(2019, 5, 20, 9)).unwrap().unwrap();
+        let snapshot = Snapshot::load(&dir, &name).unwrap();
+        assert!(snapshot.diff(&json).unwrap().is_empty());
+
+        let date = NaiveDate::from_ymd_opt(2019, 5, 14).unwrap();
+        let mut day = may.get_week_with_day(date).unwrap().0.clone();
+        day.status = crate::timedata::DayStatus::Sick;
+        may.update_day(day);
+        may.save_file(&dir.month(2019, 5)).unwrap();
+        FlexMonth::new(2019, 6, &settings)
+            .save_file(&dir.month(2019, 6))
+            .unwrap();
+        let diffs: Vec<String> = snapshot
+            .diff(&json)
+            .unwrap()
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            diffs,
+            vec!["2019_05.json: changed 14/05", "2019_06.json: not in backup"]
+        );
+
+        let saved = snapshot.restore(&json, at(2019, 5, 20, 10)).unwrap();
+        assert_eq!(saved, Some("2019-05-20_100000".to_string()));
+        assert!(snapshot.diff(&json).unwrap().is_empty());
+        assert!(!Path::new(&dir.month(2019, 6)).exists());
+        let saved = Snapshot::load(&dir, &saved.unwrap()).unwrap();
+        assert_eq!(saved.diff(&json).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,701 @@
+use crate::backup::{self, Snapshot};
+use crate::datadir::data_dir;
+use crate::export;
+use crate::settings::Settings;
+use crate::storage::{self, storage};
+use crate::timedata::*;
+use chrono::{Datelike, Local, NaiveDate, NaiveTime};
+use failure::{bail, format_err, Error};
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, Write};
+
+const USAGE: &str = "\
+usage: rusty_flexi [COMMAND] [--json] [--data-dir <dir>]
+
+Without command, the interactive view is opened.
+
+Commands:
+  in                    set beginning of today by current time
+  out                   set end of today by current time
+  status                print today, week and month statistics
+  sick <date>           mark <date> as sick day
+  holiday <from> <to>   mark working days from <from> to <to> as holidays
+  export <from> <to>    print a CSV timesheet, `--output <file>` to write it to a file
+  history <date>        print every change of <date>, newest first
+  search <text>         print the days whose note contains <text>
+  projects <period> [date]
+                        print the time worked on each project during the `week`,
+                        `month` or `year` of <date>, today by default
+  compliance <from> <to>
+                        print the working time rules broken from <from> to <to>
+  backup list           list the backups and how many files differ from the current data
+  backup diff <name>    print how the current data differs from backup <name>
+  backup restore <name> restore backup <name>, the current data is backed up first
+  convert <storage>     move the data to `json` files or a `sqlite` database, backed up first
+  help                  print this message
+
+Dates are formatted YYYY-MM-DD, `today` is accepted as well.
+--json prints the result as JSON.
+--data-dir sets where data files are stored, default to $RUSTY_FLEXI_DATA_DIR or
+$XDG_DATA_HOME/rusty_flexi.";
+
+pub enum Command {
+    In,
+    Out,
+    Status,
+    Sick(NaiveDate),
+    Holiday(NaiveDate, NaiveDate),
+    Export(NaiveDate, NaiveDate),
+    History(NaiveDate),
+    Search(String),
+    Projects(Period, NaiveDate),
+    Compliance(NaiveDate, NaiveDate),
+    BackupList,
+    BackupDiff(String),
+    BackupRestore(String),
+    Convert(storage::Kind),
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    fn parse(s: &str) -> Result<Period, Error> {
+        match s {
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            "year" => Ok(Period::Year),
+            _ => bail!("unknown period `{}`, expected week, month or year", s),
+        }
+    }
+}
+
+pub struct Cli {
+    pub command: Command,
+    pub json: bool,
+    pub output: Option<String>,
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, Error> {
+    if s == "today" {
+        Ok(Local::now().date_naive())
+    } else {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map_err(|_| format_err!("invalid date `{}`, expected YYYY-MM-DD", s))
+    }
+}
+
+fn load_day(month: &FlexMonth, date: NaiveDate) -> Result<FlexDay, Error> {
+    match month.get_week_with_day(date) {
+        Some((d, _, _)) => Ok(d.clone()),
+        None => Err(format_err!("{} not found in month file", date)),
+    }
+}
+
+/// remove `--data-dir <dir>` from `args`, return the given dir
+pub fn take_data_dir(args: &mut Vec<String>) -> Result<Option<String>, Error> {
+    match args.iter().position(|a| a == "--data-dir") {
+        Some(idx) if idx + 1 < args.len() => {
+            let dir = args.remove(idx + 1);
+            args.remove(idx);
+            Ok(Some(dir))
+        }
+        Some(_) => bail!("--data-dir needs a directory"),
+        None => Ok(None),
+    }
+}
+
+impl Cli {
+    /// parse command line arguments, program name excluded. Return None if no command is given.
+    pub fn parse(args: &[String]) -> Result<Option<Cli>, Error> {
+        let json = args.iter().any(|a| a == "--json");
+        let mut output = None;
+        let mut positional: Vec<&str> = Vec::new();
+        let mut iter = args.iter().map(|a| a.as_str());
+        while let Some(a) = iter.next() {
+            match a {
+                "--json" => {}
+                "--output" => match iter.next() {
+                    Some(path) => output = Some(path.to_string()),
+                    None => bail!("--output needs a file name"),
+                },
+                _ => positional.push(a),
+            }
+        }
+        let command = match positional.as_slice() {
+            [] => return Ok(None),
+            ["in"] => Command::In,
+            ["out"] => Command::Out,
+            ["status"] => Command::Status,
+            ["sick", date] => Command::Sick(parse_date(date)?),
+            ["holiday", from, to] => {
+                let from = parse_date(from)?;
+                let to = parse_date(to)?;
+                if to < from {
+                    bail!("holiday range ends before it begins");
+                }
+                Command::Holiday(from, to)
+            }
+            ["export", from, to] => {
+                let from = parse_date(from)?;
+                let to = parse_date(to)?;
+                if to < from {
+                    bail!("export range ends before it begins");
+                }
+                Command::Export(from, to)
+            }
+            ["history", date] => Command::History(parse_date(date)?),
+            ["projects", period] => Command::Projects(Period::parse(period)?, parse_date("today")?),
+            ["projects", period, date] => {
+                Command::Projects(Period::parse(period)?, parse_date(date)?)
+            }
+            ["compliance", from, to] => {
+                let from = parse_date(from)?;
+                let to = parse_date(to)?;
+                if to < from {
+                    bail!("compliance range ends before it begins");
+                }
+                Command::Compliance(from, to)
+            }
+            ["search", words @ ..] if !words.is_empty() => Command::Search(words.join(" ")),
+            ["backup", "list"] => Command::BackupList,
+            ["backup", "diff", name] => Command::BackupDiff(name.to_string()),
+            ["backup", "restore", name] => Command::BackupRestore(name.to_string()),
+            ["convert", kind] => match storage::Kind::parse(kind) {
+                Some(kind) => Command::Convert(kind),
+                None => bail!("unknown storage `{}`, expected json or sqlite", kind),
+            },
+            ["help"] | ["--help"] | ["-h"] => Command::Help,
+            _ => bail!("{}", USAGE),
+        };
+        Ok(Some(Cli {
+            command,
+            json,
+            output,
+        }))
+    }
+
+    pub fn run(&self, settings: &Settings) -> Result<(), Error> {
+        match self.command {
+            Command::In => self.clock(true, settings),
+            Command::Out => self.clock(false, settings),
+            Command::Status => self.status(Local::now().date_naive(), settings, &mut io::stdout()),
+            Command::Sick(date) => self.set_status(date, date, DayStatus::Sick, settings),
+            Command::Holiday(from, to) => self.set_status(from, to, DayStatus::Holiday, settings),
+            Command::Export(from, to) => match self.output {
+                Some(ref path) => export::write_csv(from, to, settings, &mut File::create(path)?),
+                None => export::write_csv(from, to, settings, &mut io::stdout()),
+            },
+            Command::History(date) => self.history(date, settings),
+            Command::Search(ref text) => self.search(text, settings),
+            Command::Projects(period, date) => self.projects(period, date, settings),
+            Command::Compliance(from, to) => self.compliance(from, to, settings),
+            Command::BackupList => self.backup_list(),
+            Command::BackupDiff(ref name) => self.backup_diff(name),
+            Command::BackupRestore(ref name) => {
+                let saved = Snapshot::load(data_dir(), name)?
+                    .restore(storage(), Local::now().naive_local())?;
+                if self.json {
+                    let out = json!({ "restored": name, "saved_as": saved });
+                    println!("{}", serde_json::to_string_pretty(&out)?);
+                } else {
+                    println!("Backup {} restored", name);
+                    if let Some(saved) = saved {
+                        println!("Previous data saved as backup {}", saved);
+                    }
+                }
+                Ok(())
+            }
+            Command::Convert(kind) => {
+                let saved = storage::convert(data_dir(), kind, Local::now().naive_local())?;
+                if self.json {
+                    let out = json!({ "converted_to": format!("{:?}", kind), "saved_as": saved });
+                    println!("{}", serde_json::to_string_pretty(&out)?);
+                } else {
+                    println!("Data converted to {:?}", kind);
+                    if let Some(saved) = saved {
+                        println!("Previous data saved as backup {}", saved);
+                    }
+                }
+                Ok(())
+            }
+            Command::Help => {
+                println!("{}", USAGE);
+                println!("\nData directory: {}", data_dir().root().display());
+                Ok(())
+            }
+        }
+    }
+
+    fn history(&self, date: NaiveDate, settings: &Settings) -> Result<(), Error> {
+        let journal = Journal::load()?;
+        let entries = journal.history(date);
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&entries)?);
+        } else if entries.is_empty() {
+            println!("No change recorded for {}", date);
+        } else {
+            for e in entries {
+                println!("{} {}", e.at.format("%Y-%m-%d %H:%M"), e.action);
+                println!("  before  {}", e.old.line(settings));
+                println!("  after   {}", e.new.line(settings));
+            }
+        }
+        Ok(())
+    }
+
+    fn search(&self, text: &str, settings: &Settings) -> Result<(), Error> {
+        let days = storage().search_notes(text)?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&days)?);
+        } else if days.is_empty() {
+            println!("No note with \"{}\"", text);
+        } else {
+            for d in days {
+                println!("{}", d.line(settings));
+                println!("  {}", d.note.unwrap_or_default());
+            }
+        }
+        Ok(())
+    }
+
+    fn projects(&self, period: Period, date: NaiveDate, settings: &Settings) -> Result<(), Error> {
+        let (title, report) = match period {
+            Period::Week => {
+                let (year, month) = grid_month_for(date);
+                let m = FlexMonth::load(year, month, settings)?;
+                let (_, w, _) = m
+                    .get_week_with_day(date)
+                    .ok_or_else(|| format_err!("{} not found in month file", date))?;
+                (
+                    format!("Week {} {}", date.iso_week().week(), date.iso_week().year()),
+                    w.projects(settings),
+                )
+            }
+            Period::Month => {
+                let m = FlexMonth::load(date.year(), date.month(), settings)?;
+                (
+                    format!("{} {}", month_to_string(m.month), m.year),
+                    m.projects(settings),
+                )
+            }
+            Period::Year => (date.year().to_string(), ProjectReport::year(date.year(), settings)?),
+        };
+        if self.json {
+            let projects: Vec<_> = report
+                .projects()
+                .into_iter()
+                .map(|(project, minutes)| json!({ "project": project, "minutes": minutes }))
+                .collect();
+            let out = json!({
+                "period": title,
+                "projects": projects,
+                "total_minutes": report.total(),
+            });
+            println!("{}", serde_json::to_string_pretty(&out)?);
+        } else {
+            println!("{}", title);
+            for line in report.lines() {
+                println!("{}", line);
+            }
+        }
+        Ok(())
+    }
+
+    fn compliance(&self, from: NaiveDate, to: NaiveDate, settings: &Settings) -> Result<(), Error> {
+        let violations = Compliance::load(settings)?.violations(from, to);
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&violations)?);
+        } else if violations.is_empty() {
+            println!("No working time rule broken");
+        } else {
+            for v in violations {
+                println!("{}", v);
+            }
+        }
+        Ok(())
+    }
+
+    fn backup_list(&self) -> Result<(), Error> {
+        let mut backups = Vec::new();
+        for name in backup::list(data_dir())? {
+            let changes = Snapshot::load(data_dir(), &name)?.diff(storage())?.len();
+            backups.push((name, changes));
+        }
+        if self.json {
+            let out: Vec<_> = backups
+                .iter()
+                .map(|(name, changes)| json!({ "name": name, "files_changed": changes }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&out)?);
+        } else if backups.is_empty() {
+            println!("No backup yet");
+        } else {
+            for (name, changes) in backups {
+                match changes {
+                    0 => println!("{}  same as current data", name),
+                    n => println!("{}  {} file(s) differ", name, n),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn backup_diff(&self, name: &str) -> Result<(), Error> {
+        let diffs = Snapshot::load(data_dir(), name)?.diff(storage())?;
+        if self.json {
+            let out: Vec<String> = diffs.iter().map(|d| d.to_string()).collect();
+            println!("{}", serde_json::to_string_pretty(&out)?);
+        } else if diffs.is_empty() {
+            println!("Same as current data");
+        } else {
+            for d in diffs {
+                println!("{}", d);
+            }
+        }
+        Ok(())
+    }
+
+    fn clock(&self, entry: bool, settings: &Settings) -> Result<(), Error> {
+        let now = Local::now().naive_local();
+        let today = now.date();
+        let (year, month) = grid_month_for(today);
+        let mut m = FlexMonth::load(year, month, settings)?;
+        let mut d = load_day(&m, today)?;
+        let old = d.clone();
+        if !d.status.is_timed(settings) {
+            bail!("{} is not a working day", today);
+        }
+        let t = if entry {
+            let t = settings.entry_time(now.time());
+            d.clock_in(t);
+            t
+        } else {
+            let t = settings.exit_time(now.time());
+            d.clock_out(t);
+            t
+        };
+        m.update_day(d.clone());
+        m.update_balance(settings);
+        m.save()?;
+        Journal::load()?.record(Action::Edit, old, d.clone(), now)?;
+        self.print_clock(entry, t, &d, settings)
+    }
+
+    fn print_clock(
+        &self,
+        entry: bool,
+        t: NaiveTime,
+        d: &FlexDay,
+        settings: &Settings,
+    ) -> Result<(), Error> {
+        let action = if entry { "in" } else { "out" };
+        if self.json {
+            let out = json!({
+                "action": action,
+                "time": t.format("%H:%M").to_string(),
+                "day": d,
+                "total_minutes": d.total_minutes(settings),
+            });
+            println!("{}", serde_json::to_string_pretty(&out)?);
+        } else {
+            println!("Clocked {} at {}", action, t.format("%H:%M"));
+            println!("{}", d.line(settings));
+        }
+        Ok(())
+    }
+
+    fn status(
+        &self,
+        today: NaiveDate,
+        settings: &Settings,
+        out: &mut dyn Write,
+    ) -> Result<(), Error> {
+        let (year, month) = grid_month_for(today);
+        let m = FlexMonth::load(year, month, settings)?;
+        let (d, w, _) = m
+            .get_week_with_day(today)
+            .ok_or_else(|| format_err!("{} not found in month file", today))?;
+        let days_off = DaysOff::load(today.year(), settings)?;
+        let account = Ledger::load(settings)?.total_until(m.year, m.month);
+        // the schedule may have changed since the start of the month
+        let week_goal = m.week_goal_for_day(today);
+        if self.json {
+            let status = json!({
+                "today": d,
+                "today_minutes": d.total_minutes(settings),
+                "week_minutes": w.total_minutes(settings),
+                "week_goal": week_goal,
+                "month_balance": m.balance,
+                "account_balance": account,
+                "holidays_left": days_off.holidays_left,
+                "leave_year": days_off.leave_year().map(|y| y.label()),
+                "sick_days": days_off.sick_days_taken(),
+            });
+            writeln!(out, "{}", serde_json::to_string_pretty(&status)?)?;
+        } else {
+            writeln!(out, "{}", d.line(settings))?;
+            writeln!(
+                out,
+                "Week:          {} / {}",
+                w.total_str(settings),
+                minutes_to_string(week_goal)
+            )?;
+            writeln!(
+                out,
+                "Balance:       {} ({} {})",
+                minutes_to_string(m.balance),
+                month_to_string(m.month),
+                m.year
+            )?;
+            writeln!(out, "Account:       {}", minutes_to_string(account))?;
+            match days_off.leave_year() {
+                Some(year) => writeln!(
+                    out,
+                    "Holidays left: {} ({}, {} carried over, {} expired)",
+                    year.left(),
+                    year.label(),
+                    year.carried,
+                    year.expired
+                ),
+                None => writeln!(out, "Holidays left: {}", days_off.holidays_left),
+            }?;
+            writeln!(
+                out,
+                "Sick days for last 12 months: {}",
+                days_off.sick_days_taken()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn set_status(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        status: DayStatus,
+        settings: &Settings,
+    ) -> Result<(), Error> {
+        let mut changed: Vec<FlexDay> = Vec::new();
+        let mut old_days: Vec<FlexDay> = Vec::new();
+        let mut month: Option<FlexMonth> = None;
+        let mut days_off: Option<(i32, DaysOff)> = None;
+        for date in NaiveDateIter::new(from, to) {
+            // months and days off are saved once all their days are edited
+            let (y, m) = grid_month_for(date);
+            if month.as_ref().map(|cur| (cur.year, cur.month)) != Some((y, m)) {
+                if let Some(cur) = month.take() {
+                    cur.save()?;
+                }
+                month = Some(FlexMonth::load(y, m, settings)?);
+            }
+            if days_off.as_ref().map(|(year, _)| *year) != Some(date.year()) {
+                if let Some((_, off)) = days_off.take() {
+                    off.save()?;
+                }
+                days_off = Some((date.year(), DaysOff::load(date.year(), settings)?));
+            }
+            let cur = month.as_mut().expect("month loaded above");
+            let (_, off) = days_off.as_mut().expect("days off loaded above");
+
+            let mut d = load_day(cur, date)?;
+            match d.status {
+                DayStatus::Weekend | DayStatus::PublicHoliday => continue,
+                s if s == status => continue,
+                _ => {}
+            }
+            let old = d.clone();
+            d.status = status;
+            off.update_days_off(old.status, &d, settings);
+            cur.update_day(d.clone());
+            cur.update_balance(settings);
+            old_days.push(old);
+            changed.push(d);
+        }
+        if let Some(cur) = month {
+            cur.save()?;
+        }
+        let holidays_left = match days_off {
+            Some((_, off)) => {
+                off.save()?;
+                off.holidays_left
+            }
+            None => 0.0,
+        };
+        let mut journal = Journal::load()?;
+        let now = Local::now().naive_local();
+        for (old, new) in old_days.into_iter().zip(changed.iter()) {
+            journal.record(Action::Edit, old, new.clone(), now)?;
+        }
+
+        if self.json {
+            let out = json!({
+                "status": status,
+                "days": changed,
+                "holidays_left": holidays_left,
+            });
+            println!("{}", serde_json::to_string_pretty(&out)?);
+        } else {
+            println!("{:?} set on {} day(s)", status, changed.len());
+            for d in &changed {
+                println!("{}", d.line(settings));
+            }
+            println!("Holidays left: {}", holidays_left);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ScheduleVersion;
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_test() {
+        assert!(Cli::parse(&args(&[])).unwrap().is_none());
+
+        let cli = Cli::parse(&args(&["status", "--json"])).unwrap().unwrap();
+        assert!(cli.json);
+        match cli.command {
+            Command::Status => {}
+            _ => panic!("should be status"),
+        }
+
+        let cli = Cli::parse(&args(&["holiday", "2019-07-01", "2019-07-05"]))
+            .unwrap()
+            .unwrap();
+        assert!(!cli.json);
+        match cli.command {
+            Command::Holiday(from, to) => {
+                assert_eq!(from, NaiveDate::from_ymd_opt(2019, 7, 1).unwrap());
+                assert_eq!(to, NaiveDate::from_ymd_opt(2019, 7, 5).unwrap());
+            }
+            _ => panic!("should be holiday"),
+        }
+
+        assert!(Cli::parse(&args(&["holiday", "2019-07-05", "2019-07-01"])).is_err());
+        assert!(Cli::parse(&args(&["sick", "yesterday"])).is_err());
+        assert!(Cli::parse(&args(&["dance"])).is_err());
+
+        let cli = Cli::parse(&args(&["backup", "restore", "2019-05-20_090000"]))
+            .unwrap()
+            .unwrap();
+        match cli.command {
+            Command::BackupRestore(ref name) => assert_eq!(name, "2019-05-20_090000"),
+            _ => panic!("should be backup restore"),
+        }
+        assert!(Cli::parse(&args(&["backup", "restore"])).is_err());
+
+        match Cli::parse(&args(&["history", "2019-05-14"]))
+            .unwrap()
+            .unwrap()
+            .command
+        {
+            Command::History(date) => {
+                assert_eq!(date, NaiveDate::from_ymd_opt(2019, 5, 14).unwrap())
+            }
+            _ => panic!("should be history"),
+        }
+
+        match Cli::parse(&args(&["search", "left", "early"]))
+            .unwrap()
+            .unwrap()
+            .command
+        {
+            Command::Search(ref text) => assert_eq!(text, "left early"),
+            _ => panic!("should be search"),
+        }
+        assert!(Cli::parse(&args(&["search"])).is_err());
+
+        match Cli::parse(&args(&["projects", "month", "2019-05-14"]))
+            .unwrap()
+            .unwrap()
+            .command
+        {
+            Command::Projects(Period::Month, date) => {
+                assert_eq!(date, NaiveDate::from_ymd_opt(2019, 5, 14).unwrap())
+            }
+            _ => panic!("should be projects"),
+        }
+        assert!(Cli::parse(&args(&["projects", "decade"])).is_err());
+        assert!(Cli::parse(&args(&["compliance", "2019-07-31", "2019-07-01"])).is_err());
+
+        match Cli::parse(&args(&["convert", "sqlite"]))
+            .unwrap()
+            .unwrap()
+            .command
+        {
+            Command::Convert(storage::Kind::Sqlite) => {}
+            _ => panic!("should be convert"),
+        }
+        assert!(Cli::parse(&args(&["convert", "xml"])).is_err());
+
+        let cli = Cli::parse(&args(&[
+            "export",
+            "2019-07-01",
+            "2019-07-31",
+            "--output",
+            "july.csv",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(cli.output, Some("july.csv".to_string()));
+        assert!(Cli::parse(&args(&["export", "2019-07-01", "2019-07-31", "--output"])).is_err());
+    }
+
+    #[test]
+    fn take_data_dir_test() {
+        let mut a = args(&["status", "--data-dir", "/tmp/flexi", "--json"]);
+        assert_eq!(
+            take_data_dir(&mut a).unwrap(),
+            Some("/tmp/flexi".to_string())
+        );
+        assert_eq!(a, args(&["status", "--json"]));
+
+        let mut a = args(&["in"]);
+        assert_eq!(take_data_dir(&mut a).unwrap(), None);
+        assert!(take_data_dir(&mut args(&["--data-dir"])).is_err());
+    }
+
+    #[test]
+    fn status_test() {
+        crate::datadir::test_data_dir("status_test");
+        let mut settings = Settings::default();
+        // 30 hours a week from the third week of May 2019
+        settings.schedule_history.push(ScheduleVersion {
+            from: NaiveDate::from_ymd_opt(2019, 5, 13).unwrap(),
+            week_sched: settings.week_sched.clone(),
+            week_goal: 30 * 60,
+        });
+        let status = |cli: &[&str], today: NaiveDate| {
+            let mut out = Vec::new();
+            Cli::parse(&args(cli))
+                .unwrap()
+                .unwrap()
+                .status(today, &settings, &mut out)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let before = NaiveDate::from_ymd_opt(2019, 5, 8).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&status(&["status", "--json"], before)).unwrap();
+        assert_eq!(json["week_goal"], 37 * 60);
+        let after = NaiveDate::from_ymd_opt(2019, 5, 21).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&status(&["status", "--json"], after)).unwrap();
+        assert_eq!(json["week_goal"], 30 * 60);
+        assert!(status(&["status"], after).contains(" / 30:00\n"));
+    }
+}
@@ -1,49 +1,74 @@
-pub use self::navigator::Direction;
-pub use self::navigator::HourField;
 pub use self::navigator::Navigator;
 
+pub mod backend;
+mod backupbrowser;
 mod editor;
+mod historybrowser;
+mod layout;
 mod navigator;
 pub mod settingseditor;
+mod yearview;
 
+use self::backend::{Attr, Backend, Input, Window};
+use self::layout::{fit, Area, Layout};
+use crate::error::FlexiError;
+use crate::savable;
 use crate::settings::Settings;
 use crate::timedata::*;
-use chrono::{Datelike, Duration, NaiveDate, Timelike, Weekday};
-use pancurses::*;
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime, Timelike, Weekday};
+use failure::Error;
 use std::collections::HashMap;
 
-pub fn init_window(window: &Window) {
-    window.keypad(true);
-    noecho();
-    cbreak();
-    start_color();
-    curs_set(0);
-    resize_term(15, 76);
-    init_pair(1, COLOR_RED, COLOR_BLACK);
+// line of the month view
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MonthLine {
+    Day(NaiveDate),
+    // total of the week starting on the date
+    Total(NaiveDate),
 }
 
 pub struct Curses<'a> {
-    pub main_win: &'a Window,
-    pub week_win: Window,
-    stat_win: Window,
+    backend: &'a dyn Backend,
+    pub main_win: &'a dyn Window,
+    // windows for the size of the terminal when last drawn
+    layout: Layout,
+    // days of the view shown, the week or the whole month
+    pub week_win: Box<dyn Window>,
+    // window of the view not shown, swapped with `week_win`
+    other_win: Box<dyn Window>,
+    pub month_view: bool,
+    month_lines: Vec<MonthLine>,
+    // first line shown in the month view, of the month `month_shown`
+    month_top: usize,
+    month_shown: (i32, u32),
+    // None when the terminal is too small
+    stat_win: Option<Box<dyn Window>>,
+    // notes of the month, if there is room left
+    notes_win: Option<Box<dyn Window>>,
     // x coord for each field:
     // Status, Start hours, Start min, End Hours, End Min, Break Hours, Break Min
     pub fields: [i32; 7],
-    option_win: Option<Window>,
-    sub_option_sched: Option<Window>,
-    sub_option_days_off: Option<Window>,
+    option_win: Option<Box<dyn Window>>,
+    sub_option_sched: Option<Box<dyn Window>>,
+    sub_option_days_off: Option<Box<dyn Window>>,
 }
 
 impl<'a> Curses<'a> {
-    pub fn new(window: &'a Window) -> Curses {
+    pub fn new(backend: &'a dyn Backend) -> Curses<'a> {
+        let window = backend.screen();
+        let layout = Layout::new(window.get_max_y(), window.get_max_x());
         Curses {
+            backend,
             main_win: window,
-            week_win: window
-                .subwin(11, 48, 2, 2)
-                .expect("Week window creation failed"),
-            stat_win: window
-                .subwin(13, 25, 1, 50)
-                .expect("Status window creation failed"),
+            layout,
+            week_win: fit(window, layout.week),
+            other_win: fit(window, layout.month),
+            month_view: false,
+            month_lines: Vec::new(),
+            month_top: 0,
+            month_shown: (0, 0),
+            stat_win: layout.stat.map(|area| fit(window, area)),
+            notes_win: layout.notes.map(|area| fit(window, area)),
             fields: [0, 16, 19, 25, 28, 33, 36],
             option_win: None,
             sub_option_days_off: None,
@@ -51,13 +76,246 @@ impl<'a> Curses<'a> {
         }
     }
 
+    /// make the windows again for the new size of the terminal, the caller draws them
+    pub fn resize(&mut self) {
+        let layout = Layout::new(self.main_win.get_max_y(), self.main_win.get_max_x());
+        let week = fit(self.main_win, layout.week);
+        let month = fit(self.main_win, layout.month);
+        if self.month_view {
+            self.week_win = month;
+            self.other_win = week;
+        } else {
+            self.week_win = week;
+            self.other_win = month;
+        }
+        self.stat_win = layout.stat.map(|area| fit(self.main_win, area));
+        self.notes_win = layout.notes.map(|area| fit(self.main_win, area));
+        self.layout = layout;
+        self.main_win.clear();
+        self.main_win.refresh();
+    }
+
+    /// switch between the week and the month of the selected day
+    pub fn toggle_month_view(&mut self) {
+        std::mem::swap(&mut self.week_win, &mut self.other_win);
+        self.month_view = !self.month_view;
+    }
+
+    // wait for a key, at most 5 seconds
     pub fn getch(&self) -> Option<Input> {
-        self.main_win.nodelay(true);
-        half_delay(50);
-        let ch = self.main_win.getch();
-        nocbreak(); // Reset the halfdelay() value
-        cbreak();
-        ch
+        self.backend.getch(Some(std::time::Duration::from_secs(5)))
+    }
+
+    pub fn wait_key(&self) -> Option<Input> {
+        self.backend.getch(None)
+    }
+
+    // one line message at the bottom of the screen
+    pub fn print_message(&self, msg: &str) {
+        let y = self.main_win.get_max_y() - 1;
+        self.main_win.mv(y, 0);
+        self.main_win.clrtoeol();
+        self.main_win.mvprintw(y, 2, msg);
+        self.main_win.refresh();
+    }
+
+    // modal error dialog, closed by any key
+    pub fn print_error(&self, msg: &str) {
+        self.print_dialog(" Error ", msg, "Press any key");
+        self.wait_key();
+    }
+
+    // modal question, true if answered with 'y'
+    pub fn ask(&self, title: &str, msg: &str) -> bool {
+        self.print_dialog(title, msg, "(y/n)");
+        loop {
+            match self.wait_key() {
+                Some(Input::Character('y')) | Some(Input::Character('Y')) => return true,
+                Some(Input::Character('n'))
+                | Some(Input::Character('N'))
+                | Some(Input::Character('\x1B'))
+                | None => return false,
+                _ => {}
+            }
+        }
+    }
+
+    // offer to restore the last good copy if `e` is a damaged file, true if restored
+    pub fn offer_recovery(&self, e: &Error) -> Result<bool, Error> {
+        let path = match e.downcast_ref::<FlexiError>() {
+            Some(FlexiError::Damaged { path, .. }) => path,
+            _ => return Ok(false),
+        };
+        let msg = format!(
+            "{} is damaged, probably cut short while written. Restore its last good copy? \
+             The damaged file is kept as {}.damaged",
+            path, path
+        );
+        if !self.ask(" Damaged file ", &msg) {
+            return Ok(false);
+        }
+        savable::restore_last_good(path)?;
+        Ok(true)
+    }
+
+    // box in the middle of the screen with `title` in red, `msg` and `footer` at the bottom
+    fn print_dialog(&self, title: &str, msg: &str, footer: &str) {
+        let width = 50;
+        let max_lines = (self.main_win.get_max_y() - 4) as usize;
+        let lines: Vec<String> = wrap(msg, width as usize - 4)
+            .into_iter()
+            .take(max_lines)
+            .collect();
+        let height = lines.len() as i32 + 4;
+        let y = (self.main_win.get_max_y() - height) / 2;
+        let x = (self.main_win.get_max_x() - width) / 2;
+        match self.main_win.subwin(height, width, y, x) {
+            Some(win) => {
+                win.clear();
+                win.draw_box();
+                win.attron(Attr::Alert);
+                win.mvprintw(0, 2, title);
+                win.attroff(Attr::Alert);
+                for (i, line) in lines.iter().enumerate() {
+                    win.mvprintw(i as i32 + 1, 2, line);
+                }
+                win.mvprintw(height - 2, width - 2 - footer.len() as i32, footer);
+                win.refresh();
+            }
+            None => self.print_message(msg),
+        }
+    }
+
+    // one line of text typed in a dialog, None if cancelled with Esc
+    pub fn read_line(&self, title: &str, initial: &str) -> Option<String> {
+        let mut text = initial.to_string();
+        loop {
+            // the end of the text when it is too long for the box
+            let shown: String = {
+                let chars: Vec<char> = text.chars().collect();
+                chars[chars.len().saturating_sub(45)..].iter().collect()
+            };
+            self.print_dialog(title, &format!("{}_", shown), "Enter: save    Esc: cancel");
+            match self.wait_key() {
+                Some(Input::Character('\n')) => return Some(text),
+                Some(Input::Character('\x1B')) | None => return None,
+                Some(Input::Character('\u{8}')) => {
+                    text.pop();
+                }
+                Some(Input::Character(c)) if !c.is_control() => text.push(c),
+                _ => {}
+            }
+        }
+    }
+
+    // rules broken, type, projects and note of the selected day, on the lines left under
+    // the week
+    pub fn print_details(&self, d: &FlexDay, violations: &[Violation], settings: &Settings) {
+        if self.month_view {
+            // the month takes the lines
+            return;
+        }
+        let win = fit(self.main_win, self.layout.details);
+        let (rows, width) = (win.get_max_y() as usize, win.get_max_x() as usize);
+        win.clear();
+        let mut lines = Vec::new();
+        if !violations.is_empty() {
+            let broken: Vec<String> = violations.iter().map(|v| v.describe()).collect();
+            lines.extend(wrap(&format!("! {}", broken.join(", ")), width));
+        }
+        let alerts = lines.len();
+        match d.status {
+            DayStatus::Worked | DayStatus::Weekend => {}
+            status => lines.push(day_type(settings, status).name),
+        }
+        let projects = d.projects(settings);
+        if projects.projects().iter().any(|(p, _)| p.is_some()) {
+            let worked: Vec<String> = projects
+                .projects()
+                .iter()
+                .map(|(p, m)| format!("{} {}", p.unwrap_or("-"), minutes_to_string(*m)))
+                .collect();
+            lines.extend(wrap(&format!("Projects: {}", worked.join(", ")), width));
+        }
+        if let Some(note) = &d.note {
+            lines.extend(wrap(&format!("Note: {}", note), width));
+        }
+        if lines.len() > rows {
+            // the whole note is in the dialog of `n`
+            lines.truncate(rows);
+            let last: String = lines[rows - 1]
+                .chars()
+                .take(width.saturating_sub(4))
+                .collect();
+            lines[rows - 1] = format!("{} ...", last);
+        }
+        for (i, line) in lines.iter().enumerate() {
+            let attr = if i < alerts { Attr::Alert } else { Attr::Dim };
+            win.attron(attr);
+            win.mvprintw(i as i32, 0, line);
+            win.attroff(attr);
+        }
+        win.refresh();
+    }
+
+    // modal list to choose from, index of the chosen item or None if cancelled with Esc
+    pub fn pick(&self, title: &str, items: &[String], selected: usize) -> Option<usize> {
+        let width = 40;
+        let rows = (items.len() as i32)
+            .min(self.main_win.get_max_y() - 6)
+            .max(1);
+        let height = rows + 4;
+        let y = (self.main_win.get_max_y() - height) / 2;
+        let x = (self.main_win.get_max_x() - width) / 2;
+        let win = self.main_win.subwin(height, width, y, x)?;
+        let mut selected = selected.min(items.len().saturating_sub(1));
+        loop {
+            // scroll to keep the selection visible
+            let first = (selected + 1).saturating_sub(rows as usize);
+            win.clear();
+            win.draw_box();
+            win.attron(Attr::Bold);
+            win.mvprintw(0, 2, title);
+            win.attroff(Attr::Bold);
+            for (i, item) in items.iter().enumerate().skip(first).take(rows as usize) {
+                if i == selected {
+                    win.attron(Attr::Reverse);
+                }
+                let label: String = item.chars().take(width as usize - 8).collect();
+                win.mvprintw((i - first) as i32 + 1, 2, &format!("{} {}", i + 1, label));
+                win.attroff(Attr::Reverse);
+            }
+            let footer = "Enter: choose    Esc: cancel";
+            win.mvprintw(height - 2, width - 2 - footer.len() as i32, footer);
+            win.refresh();
+            match self.wait_key() {
+                Some(Input::KeyUp) if selected > 0 => selected -= 1,
+                Some(Input::KeyDown) if selected + 1 < items.len() => selected += 1,
+                Some(Input::Character('\n')) if !items.is_empty() => return Some(selected),
+                Some(Input::Character(c)) if c.is_ascii_digit() => match c.to_digit(10) {
+                    Some(n) if n >= 1 && (n as usize) <= items.len() => {
+                        return Some(n as usize - 1)
+                    }
+                    _ => {}
+                },
+                Some(Input::Character('\x1B')) | None => return None,
+                _ => {}
+            }
+        }
+    }
+
+    // scrollable list of `lines`, closed with Esc
+    pub fn browse_lines(&self, title: &str, lines: &[String]) {
+        let mut first = 0;
+        loop {
+            self.print_history(title, lines, first);
+            match self.wait_key() {
+                Some(Input::KeyUp) if first > 0 => first -= 1,
+                Some(Input::KeyDown) if first + 1 < lines.len() => first += 1,
+                Some(Input::Character('\x1B')) | Some(Input::Character('q')) => return,
+                _ => {}
+            }
+        }
     }
 
     pub fn print_week_header(&self, flex_month: &FlexMonth, cur_week: i32) {
@@ -66,7 +324,7 @@ impl<'a> Curses<'a> {
         self.week_win.clrtoeol();
         self.week_win.mvprintw(
             0,
-            48 / 2 - (month_str.len() as i32 + 11) / 2,
+            self.week_win.get_max_x() / 2 - (month_str.len() as i32 + 11) / 2,
             &format!(
                 "{} {} ({}/{})",
                 month_str,
@@ -78,122 +336,272 @@ impl<'a> Curses<'a> {
     }
 
     // print week, BOLD on today's line
-    pub fn print_week(&self, week: &FlexWeek, today: &NaiveDate) {
+    pub fn print_week(&self, week: &FlexWeek, today: &NaiveDate, settings: &Settings) {
         let mut y = 2;
         self.week_win.mv(y, 0);
         for d in &week.days {
-            let day_is_today = d.date.expect("No date in day").day() == today.day();
-            if d.total_minutes() < 0 {
-                // end hour before start, print red
-                if day_is_today {
-                    self.week_win.attron(A_BOLD);
-                }
-                self.week_win.attron(COLOR_PAIR(1));
-                self.week_win.printw(&d.to_string());
-                self.week_win.attroff(COLOR_PAIR(1));
-                if day_is_today {
-                    self.week_win.attroff(A_BOLD);
-                }
-            } else if day_is_today {
-                // bold for selected day
-                self.print_selected_day(&d);
-            } else {
-                match d.status {
-                    DayStatus::Weekend | DayStatus::Sick | DayStatus::Half | DayStatus::Holiday => {
-                        self.week_win.attron(A_DIM);
-                        self.week_win.printw(&d.to_string());
-                        self.week_win.attroff(A_DIM);
-                    }
-                    _ => {
-                        self.week_win.printw(&d.to_string());
-                    }
-                };
-            }
+            self.print_day(d, d.date.expect("No date in day").day() == today.day(), settings);
             y += 1;
             self.week_win.mv(y, 0);
         }
     }
 
-    pub fn print_week_total(&self, week: &FlexWeek, below_minimum: bool) {
-        self.week_win.mv(9, 0);
-        self.week_win.printw(&format!("{:->40} ", " Total ="));
+    // every day of `month` by week, scrolled to show `selected`. The week totals are
+    // printed by `print_week_total`
+    pub fn print_month(&mut self, month: &FlexMonth, selected: NaiveDate, settings: &Settings) {
+        self.month_lines = month
+            .weeks
+            .iter()
+            .flat_map(|w| {
+                w.days
+                    .iter()
+                    .filter_map(|d| d.date)
+                    .map(MonthLine::Day)
+                    .chain(w.days[0].date.map(MonthLine::Total))
+            })
+            .collect();
+        if (month.year, month.month) != self.month_shown {
+            self.month_shown = (month.year, month.month);
+            self.month_top = 0;
+        }
+        let rows = self.month_rows();
+        let line = self
+            .month_lines
+            .iter()
+            .position(|&l| l == MonthLine::Day(selected))
+            .unwrap_or(0);
+        // the week total under Sunday comes into view with it
+        let last = match self.month_lines.get(line + 1) {
+            Some(MonthLine::Total(_)) => line + 1,
+            _ => line,
+        };
+        if line < self.month_top {
+            self.month_top = line;
+        } else if last >= self.month_top + rows {
+            self.month_top = last + 1 - rows;
+        }
+
+        let win = &self.week_win;
+        win.clear();
+        let title = format!("{} {}", month_to_string(month.month), month.year);
+        win.mvprintw(0, win.get_max_x() / 2 - title.len() as i32 / 2, &title);
+        for (i, &line) in self
+            .month_lines
+            .iter()
+            .enumerate()
+            .skip(self.month_top)
+            .take(rows)
+        {
+            if let MonthLine::Day(date) = line {
+                if let Some((d, _, _)) = month.get_week_with_day(date) {
+                    win.mv(1 + (i - self.month_top) as i32, 0);
+                    self.print_day(d, date == selected, settings);
+                }
+            }
+        }
+        win.refresh();
+    }
+
+    fn month_rows(&self) -> usize {
+        (self.week_win.get_max_y() - 1) as usize
+    }
+
+    // row of `line` in the month view, None if scrolled out
+    fn month_row(&self, line: MonthLine) -> Option<i32> {
+        let idx = self.month_lines.iter().position(|&l| l == line)?;
+        if idx >= self.month_top && idx < self.month_top + self.month_rows() {
+            Some(1 + (idx - self.month_top) as i32)
+        } else {
+            None
+        }
+    }
+
+    // line of `d` at the cursor, red if its times are wrong, bold if selected
+    fn print_day(&self, d: &FlexDay, day_is_today: bool, settings: &Settings) {
+        if d.total_minutes(settings) < 0 {
+            // end hour before start, print red
+            if day_is_today {
+                self.week_win.attron(Attr::Bold);
+            }
+            self.week_win.attron(Attr::Alert);
+            self.week_win.printw(&day_line(d, settings));
+            self.week_win.attroff(Attr::Alert);
+            if day_is_today {
+                self.week_win.attroff(Attr::Bold);
+            }
+        } else if day_is_today {
+            // bold for selected day
+            self.print_selected_day(d, settings);
+        } else {
+            match color_attr(day_type(settings, d.status).color) {
+                Some(attr) => {
+                    self.week_win.attron(attr);
+                    self.week_win.printw(&day_line(d, settings));
+                    self.week_win.attroff(attr);
+                }
+                None => {
+                    self.week_win.printw(&day_line(d, settings));
+                }
+            };
+        }
+    }
+
+    pub fn print_week_total(
+        &self,
+        week: &FlexWeek,
+        below_minimum: bool,
+        violations: &[Violation],
+        settings: &Settings,
+    ) {
+        let y = if self.month_view {
+            match week.days[0]
+                .date
+                .and_then(|monday| self.month_row(MonthLine::Total(monday)))
+            {
+                Some(y) => y,
+                None => return,
+            }
+        } else {
+            9
+        };
+        self.week_win.mv(y, 0);
+        let flag = match violations.len() {
+            0 => String::new(),
+            1 => "! 1 violation ".to_string(),
+            n => format!("! {} violations ", n),
+        };
+        // the details of the days tell which rules are broken
+        self.week_win.attron(Attr::Alert);
+        self.week_win.printw(&flag);
+        self.week_win.attroff(Attr::Alert);
+        self.week_win.printw(&format!(
+            "{:->width$} ",
+            " Total =",
+            width = 40 - flag.len()
+        ));
 
         if below_minimum {
-            self.week_win.attron(COLOR_PAIR(1));
+            self.week_win.attron(Attr::Alert);
         }
-        self.week_win.printw(&week.total_str());
+        self.week_win.printw(&week.total_str(settings));
         if below_minimum {
-            self.week_win.attroff(COLOR_PAIR(1));
+            self.week_win.attroff(Attr::Alert);
         }
         self.week_win.refresh();
     }
 
-    fn print_time(&self, time: u32, status: DayStatus) {
-        match status {
-            DayStatus::Worked | DayStatus::Half => self.week_win.printw(&format!("{:02}", time)),
-            _ => self.week_win.printw("--"),
-        };
+    fn print_time(&self, time: u32, status: DayStatus, settings: &Settings) {
+        if status.is_timed(settings) {
+            self.week_win.printw(&format!("{:02}", time));
+        } else {
+            self.week_win.printw("--");
+        }
     }
 
-    fn print_selected_day(&self, d: &FlexDay) {
-        self.week_win.attron(A_BOLD);
-        self.week_win.printw(&d.to_string());
-        self.week_win.attroff(A_BOLD);
+    fn print_selected_day(&self, d: &FlexDay, settings: &Settings) {
+        self.week_win.attron(Attr::Bold);
+        self.week_win.printw(&day_line(d, settings));
+        self.week_win.attroff(Attr::Bold);
     }
 
-    fn highlight_current_field(&self, cur_field: usize, d: &FlexDay, cur_y: i32) {
+    fn highlight_current_field(
+        &self,
+        cur_field: usize,
+        session: usize,
+        d: &FlexDay,
+        cur_y: i32,
+        settings: &Settings,
+    ) {
         // reset any previous reverse attr
         self.week_win.mv(cur_y, 0);
-        self.print_selected_day(&d);
+        self.week_win.attron(Attr::Bold);
+        self.week_win.printw(&d.session_to_string(session, settings));
+        self.week_win.attroff(Attr::Bold);
 
+        let s = &d.sessions[session];
         self.week_win.mv(cur_y, self.fields[cur_field]);
-        self.week_win.attron(A_REVERSE);
+        self.week_win.attron(Attr::Reverse);
         match cur_field {
             0 => {
                 self.week_win.printw(&d.status_str());
             }
             1 => {
-                self.print_time(d.start.hour(), d.status);
+                self.print_time(s.start.hour(), d.status, settings);
             }
             2 => {
-                self.print_time(d.start.minute(), d.status);
+                self.print_time(s.start.minute(), d.status, settings);
             }
             3 => {
-                self.print_time(d.end.hour(), d.status);
+                self.print_time(s.end.hour(), d.status, settings);
             }
             4 => {
-                self.print_time(d.end.minute(), d.status);
+                self.print_time(s.end.minute(), d.status, settings);
             }
             5 => {
-                self.print_time((d.pause / 60) as u32, d.status);
+                self.print_time((d.pause / 60) as u32, d.status, settings);
             }
             6 => {
-                self.print_time((d.pause - (d.pause / 60) * 60) as u32, d.status);
+                self.print_time((d.pause - (d.pause / 60) * 60) as u32, d.status, settings);
             }
             _ => unreachable!(),
         }
-        self.week_win.attroff(A_REVERSE);
+        self.week_win.attroff(Attr::Reverse);
     }
 
-    fn print_status_title(&self, title: &str, start_y: i32) {
-        let width = self.stat_win.get_max_x();
-        self.stat_win.attron(A_UNDERLINE);
-        self.stat_win
-            .mvprintw(start_y, width / 2 - title.len() as i32 / 2, &title);
-        self.stat_win.attroff(A_UNDERLINE);
+    fn print_status_title(&self, win: &dyn Window, title: &str, start_y: i32) {
+        let width = win.get_max_x();
+        win.attron(Attr::Underline);
+        win.mvprintw(start_y, width / 2 - title.len() as i32 / 2, title);
+        win.attroff(Attr::Underline);
     }
 
-    pub fn print_status(&self, settings: &Settings, m: &FlexMonth, off: &DaysOff) {
+    fn print_balance(&self, win: &dyn Window, y: i32, pad_x: i32, label: &str, balance: i64) {
+        let sign = if balance < 0 { "-" } else { " " };
+        win.mvprintw(y, pad_x, label);
+        if balance < 0 {
+            win.attron(Attr::Alert);
+        }
+        win.mvprintw(
+            y,
+            pad_x + 15,
+            &format!(
+                "{}{:02}:{:02}",
+                sign,
+                (balance / 60).abs(),
+                (balance - (balance / 60) * 60).abs()
+            ),
+        );
+        if balance < 0 {
+            win.attroff(Attr::Alert);
+        }
+    }
+
+    // `leave` is None unless today is worked
+    pub fn print_status(
+        &self,
+        m: &FlexMonth,
+        off: &DaysOff,
+        ledger: &Ledger,
+        leave: Option<&LeaveTimes>,
+        settings: &Settings,
+    ) {
+        let win = match &self.stat_win {
+            Some(win) => &**win,
+            None => return,
+        };
         let start_y = 1;
         let pad_x = 2;
-        self.stat_win.clear();
-        self.stat_win.draw_box(0, 0);
-        self.print_status_title(&format!("{} statistics", month_to_string(m.month)), start_y);
-        let goal = settings.week_goal * m.weeks.len() as i64;
-        let total = m.total_minute();
-        let sign = if m.balance < 0 { "-" } else { " " };
-        self.stat_win.mvprintw(
-            start_y + 2,
+        win.clear();
+        win.draw_box();
+        self.print_status_title(
+            win,
+            &format!("{} statistics", month_to_string(m.month)),
+            start_y,
+        );
+        let goal = m.total_goal();
+        let total = m.total_minute(settings);
+        win.mvprintw(
+            start_y + 1,
             pad_x,
             &format!(
                 "Target:{: >8}{:02}:{:02}",
@@ -202,8 +610,8 @@ impl<'a> Curses<'a> {
                 goal - (goal / 60) * 60
             ),
         );
-        self.stat_win.mvprintw(
-            start_y + 3,
+        win.mvprintw(
+            start_y + 2,
             pad_x,
             &format!(
                 "Total:{: >9}{:02}:{:02}",
@@ -212,101 +620,289 @@ impl<'a> Curses<'a> {
                 total - (total / 60) * 60
             ),
         );
-        self.stat_win
-            .mvprintw(start_y + 4, pad_x, &format!("Balance: "));
-        if m.balance < 0 {
-            self.stat_win.attron(COLOR_PAIR(1));
-        }
-        self.stat_win.mvprintw(
+        self.print_balance(win, start_y + 3, pad_x, "Balance: ", m.balance);
+        // running flexi account, including this month
+        self.print_balance(
+            win,
             start_y + 4,
-            pad_x + 15,
-            &format!(
-                "{}{:02}:{:02}",
-                sign,
-                (m.balance / 60).abs(),
-                (m.balance - (m.balance / 60) * 60).abs()
-            ),
+            pad_x,
+            "Account: ",
+            ledger.total_until(m.year, m.month),
         );
-        if m.balance < 0 {
-            self.stat_win.attroff(COLOR_PAIR(1));
-        }
-        self.print_status_title(&format!("Days off ({})", m.year), start_y + 6);
-        self.stat_win.mvprintw(
-            start_y + 8,
+        let leave_year = match off.leave_year() {
+            Some(leave_year) => leave_year.label(),
+            None => m.year.to_string(),
+        };
+        self.print_status_title(win, &format!("Days off ({})", leave_year), start_y + 5);
+        win.mvprintw(
+            start_y + 6,
             pad_x,
             &format!("Holidays left: {: >6}", off.holidays_left),
         );
-
-        self.stat_win.mvprintw(start_y + 9, pad_x, "Sick days for");
-        self.stat_win.mvprintw(
-            start_y + 10,
+        win.mvprintw(
+            start_y + 7,
             pad_x,
-            &format!("last 12 months: {: >5}", off.sick_days_taken()),
+            &format!("Sick (12 months): {: >3}", off.sick_days_taken()),
         );
-        self.stat_win.refresh();
+        self.print_status_title(win, "Leave today", start_y + 8);
+        let now = Local::now().naive_local().time();
+        let times = [
+            ("Day:", leave.and_then(|l| l.day)),
+            ("Week:", leave.and_then(|l| l.week)),
+            ("Account:", leave.and_then(|l| l.account)),
+        ];
+        for (i, (label, time)) in times.iter().enumerate() {
+            win.mvprintw(
+                start_y + 9 + i as i32,
+                pad_x,
+                &leave_line(label, *time, now),
+            );
+        }
+        win.refresh();
     }
 
-    fn print_settings_title(&self, option: &Window, width: i32) {
+    // notes of the days of `m`, in the space left beside or under the statistics
+    pub fn print_notes(&self, m: &FlexMonth) {
+        let win = match &self.notes_win {
+            Some(win) => win,
+            None => return,
+        };
+        win.clear();
+        win.draw_box();
+        self.print_status_title(&**win, "Notes", 1);
+        let width = (win.get_max_x() - 4).max(1) as usize;
+        let rows = (win.get_max_y() - 3).max(0) as usize;
+        let lines: Vec<String> = m
+            .weeks
+            .iter()
+            .flat_map(|w| w.days.iter())
+            .filter(|d| d.date.map(|date| date.month()) == Some(m.month))
+            .filter_map(|d| {
+                let note = d.note.as_ref()?;
+                Some(format!("{} {}", d.date?.format("%d/%m"), note))
+            })
+            .flat_map(|note| wrap(&note, width))
+            .collect();
+        if lines.is_empty() {
+            win.attron(Attr::Dim);
+            win.mvprintw(2, 2, "No note this month");
+            win.attroff(Attr::Dim);
+        }
+        for (i, line) in lines.iter().take(rows).enumerate() {
+            win.mvprintw(2 + i as i32, 2, line);
+        }
+        win.refresh();
+    }
+
+    fn print_settings_title(&self, option: &dyn Window, width: i32) {
         option.mv(1, 0);
         let title = "Settings";
-        option.mvprintw(1, (width / 2 - title.len() as i32 / 2) as i32, title);
+        option.mvprintw(1, width / 2 - title.len() as i32 / 2, title);
     }
 
     pub fn open_settings(&mut self, settings: &Settings, off: &DaysOff) {
-        let width = 60;
-        let height = 14;
-        let option = self
-            .main_win
-            .subwin(
-                height,
-                width,
-                0,
-                (self.week_win.get_max_x() + self.stat_win.get_max_x()) / 2 - width / 2,
-            )
-            .expect("Error while creating options' window");
-        option.overlay(self.main_win);
+        let (width, height) = (60, 14);
+        // centred over the days and the statistics
+        let stat_cols = self.stat_win.as_ref().map_or(0, |w| w.get_max_x());
+        let x = ((self.week_win.get_max_x() + stat_cols) / 2 - width / 2).max(0);
+        let option = fit(
+            self.main_win,
+            Area {
+                lines: height,
+                cols: width,
+                y: 0,
+                x,
+            },
+        );
+        let (width, height) = (option.get_max_x(), option.get_max_y());
         option.clear();
-        self.print_settings_title(&option, width);
+        self.print_settings_title(&*option, width);
         let beg_y = 3;
         let sub_height = height - beg_y - 1;
-        let sched = option
-            .derwin(sub_height, 28, beg_y, 2)
-            .expect("Error while creating sched option window");
-        let days_off = option
-            .derwin(sub_height, 25, beg_y, sched.get_max_x() + 4)
-            .expect("Error while creating days off option window");
+        let sched = fit(
+            &*option,
+            Area {
+                lines: sub_height,
+                cols: 28,
+                y: beg_y,
+                x: 2,
+            },
+        );
+        let days_off_x = sched.get_max_x() + 4;
+        let days_off = fit(
+            &*option,
+            Area {
+                lines: sub_height,
+                cols: 25,
+                y: beg_y,
+                x: days_off_x,
+            },
+        );
 
         self.sub_option_sched = Some(sched);
         self.sub_option_days_off = Some(days_off);
 
-        self.print_sched(&settings);
-        self.print_days_off(&off, &settings);
+        self.print_sched(settings);
+        self.print_days_off(off, settings);
 
         option.refresh();
         self.option_win = Some(option);
     }
 
+    // full screen list of the backups, `diff` describes the selected one
+    pub fn print_backups(&self, names: &[String], selected: usize, diff: &[String]) {
+        let win = self.main_win;
+        win.clear();
+        let title = "Backups";
+        win.attron(Attr::Bold);
+        win.mvprintw(1, win.get_max_x() / 2 - title.len() as i32 / 2, title);
+        win.attroff(Attr::Bold);
+        let top = 3;
+        let rows = (win.get_max_y() - top - 2) as usize;
+        // scroll to keep the selection visible
+        let first = (selected + 1).saturating_sub(rows);
+        for (i, name) in names.iter().enumerate().skip(first).take(rows) {
+            let y = top + (i - first) as i32;
+            if i == selected {
+                win.attron(Attr::Reverse);
+            }
+            win.mvprintw(y, 2, name);
+            win.attroff(Attr::Reverse);
+        }
+        let x = 24;
+        let width = (win.get_max_x() - x - 2) as usize;
+        win.mvprintw(top - 1, x, "Differences with current data:");
+        let lines = diff.iter().flat_map(|d| wrap(d, width));
+        for (i, line) in lines.take(rows).enumerate() {
+            win.mvprintw(top + i as i32, x, &line);
+        }
+        win.mvprintw(
+            win.get_max_y() - 1,
+            2,
+            "Enter: restore the selected backup    Esc: back",
+        );
+        win.refresh();
+    }
+
+    // one row of day cells per month, with the month figures and the selected day beside
+    pub fn print_year(
+        &self,
+        year: i32,
+        rows: &[yearview::MonthRow],
+        selected: NaiveDate,
+        details: &[String],
+    ) {
+        let win = self.main_win;
+        win.clear();
+        let title = format!("Year {}", year);
+        win.attron(Attr::Bold);
+        win.mvprintw(0, win.get_max_x() / 2 - title.len() as i32 / 2, &title);
+        win.attroff(Attr::Bold);
+        let (cells_x, total_x) = (6, 39);
+        let mut days = [' '; 31];
+        for &n in &[1, 5, 10, 15, 20, 25, 30] {
+            for (i, c) in n.to_string().chars().enumerate() {
+                days[n - 1 + i] = c;
+            }
+        }
+        win.mvprintw(1, cells_x, &days.iter().collect::<String>());
+        win.mvprintw(1, total_x, " Total Balance");
+        for (i, row) in rows.iter().enumerate() {
+            let y = 2 + i as i32;
+            win.mvprintw(y, 2, &month_to_string(row.month)[..3]);
+            for (day, &(c, attr)) in row.cells.iter().enumerate() {
+                let attr = if day as u32 + 1 == selected.day() && row.month == selected.month() {
+                    Some(Attr::Reverse)
+                } else {
+                    attr
+                };
+                if let Some(attr) = attr {
+                    win.attron(attr);
+                }
+                win.mvprintw(y, cells_x + day as i32, &c.to_string());
+                if let Some(attr) = attr {
+                    win.attroff(attr);
+                }
+            }
+            win.mvprintw(y, total_x, &format!("{:>6}", minutes_to_string(row.total)));
+            if row.balance < 0 {
+                win.attron(Attr::Alert);
+            }
+            win.mvprintw(
+                y,
+                total_x + 7,
+                &format!("{:>7}", minutes_to_string(row.balance)),
+            );
+            if row.balance < 0 {
+                win.attroff(Attr::Alert);
+            }
+        }
+        let x = 56;
+        for (i, line) in details.iter().enumerate() {
+            win.mvprintw(2 + i as i32, x, line);
+        }
+        win.attron(Attr::Dim);
+        for (i, line) in [
+            "+ over target",
+            "- under target",
+            "= on target",
+            ". to come",
+        ]
+        .iter()
+        .enumerate()
+        {
+            win.mvprintw(9 + i as i32, x, line);
+        }
+        win.attroff(Attr::Dim);
+        win.mvprintw(
+            win.get_max_y() - 1,
+            2,
+            "Arrows: day/month  PgUp/PgDn: year  Enter: go to week  Esc: back",
+        );
+        win.refresh();
+    }
+
+    pub fn print_history(&self, title: &str, lines: &[String], first: usize) {
+        let win = self.main_win;
+        win.clear();
+        win.attron(Attr::Bold);
+        win.mvprintw(1, win.get_max_x() / 2 - title.len() as i32 / 2, title);
+        win.attroff(Attr::Bold);
+        let top = 3;
+        let rows = (win.get_max_y() - top - 2) as usize;
+        for (i, line) in lines.iter().skip(first).take(rows).enumerate() {
+            win.mvprintw(top + i as i32, 2, line);
+        }
+        win.mvprintw(win.get_max_y() - 1, 2, "Up/Down: scroll    Esc: back");
+        win.refresh();
+    }
+
     pub fn close_setting(&mut self) {
-        self.sub_option_sched.take().unwrap().delwin();
-        self.sub_option_days_off.take().unwrap().delwin();
-        self.option_win.take().unwrap().delwin();
+        self.sub_option_sched = None;
+        self.sub_option_days_off = None;
+        self.option_win = None;
     }
 
     pub fn print_sched(&mut self, settings: &Settings) {
         let sched = self.sub_option_sched.take().unwrap();
         let mut cur_y = 0;
-        let title = "Week hours";
+        // the schedule in force today is the one edited
+        let today = Local::now().date_naive();
+        let title = match settings.schedule_start_for(&today) {
+            Some(from) => format!("Week hours since {}", from.format("%d/%m/%Y")),
+            None => "Week hours".to_string(),
+        };
         let half_width = sched.get_max_x() / 2;
-        sched.attron(A_UNDERLINE);
-        sched.mvprintw(cur_y, half_width - title.len() as i32 / 2, title);
-        sched.attroff(A_UNDERLINE);
+        sched.attron(Attr::Underline);
+        sched.mvprintw(cur_y, half_width - title.len() as i32 / 2, &title);
+        sched.attroff(Attr::Underline);
         cur_y += 2;
-        for s in &settings.week_sched.sched {
+        for s in &settings.week_sched_for(&today).sched {
             sched.mvprintw(cur_y, 0, &s.to_string());
             cur_y += 1;
         }
         cur_y += 1;
-        let target = Duration::minutes(settings.week_goal);
+        let target = Duration::minutes(settings.week_goal_for(&today));
         sched.mvprintw(
             cur_y,
             0,
@@ -321,13 +917,13 @@ impl<'a> Curses<'a> {
 
     pub fn print_days_off(&mut self, days_off: &DaysOff, settings: &Settings) {
         let off = self.sub_option_days_off.take().unwrap();
-        off.border('\u{2502}', ' ', ' ', ' ', '\u{2502}', ' ', ' ', ' ');
+        off.border(['\u{2502}', ' ', ' ', ' ', '\u{2502}', ' ', ' ', ' ']);
 
         let mut cur_y = 0;
         let title = "Days Off";
-        off.attron(A_UNDERLINE);
+        off.attron(Attr::Underline);
         off.mvprintw(cur_y, off.get_max_x() / 2 - title.len() as i32 / 2, title);
-        off.attroff(A_UNDERLINE);
+        off.attroff(Attr::Underline);
         cur_y += 2;
         off.mvprintw(
             cur_y,
@@ -363,14 +959,14 @@ impl<'a> Curses<'a> {
             .collect();
         let win = self.option_win.take().unwrap();
         win.clear();
-        win.border(
+        win.border([
             '\u{2551}', '\u{2551}', '\u{2550}', '\u{2550}', '\u{2554}', '\u{2557}', '\u{255A}',
             '\u{255D}',
-        );
+        ]);
         // reset any reverse attr
-        self.print_settings_title(&win, win.get_max_x());
-        self.print_sched(&settings);
-        self.print_days_off(&off, &settings);
+        self.print_settings_title(&*win, win.get_max_x());
+        self.print_sched(settings);
+        self.print_days_off(off, settings);
 
         // cur_idx == 5 means target week, special field management
         let y = cur_idx + 5 + if cur_idx == 5 { 1 } else { 0 };
@@ -379,18 +975,19 @@ impl<'a> Curses<'a> {
         } else {
             x_coords[&cur_field]
         };
+        let today = Local::now().date_naive();
         win.mv(y, x);
-        win.attron(A_REVERSE);
+        win.attron(Attr::Reverse);
         let value = match cur_field {
             6 => match cur_idx {
-                0 => settings.holidays_per_year as f32,
+                0 => settings.holidays_per_year,
                 1 => off.holidays_left,
                 2 => off.sick_days_taken(),
                 _ => unreachable!(),
             },
             f if f <= 5 => {
                 if cur_idx < 5 {
-                    let d = settings.week_sched.sched[cur_idx as usize];
+                    let d = settings.week_sched_for(&today).sched[cur_idx as usize];
                     match f {
                         0 => d.start.hour() as f32,
                         1 => d.start.minute() as f32,
@@ -401,7 +998,7 @@ impl<'a> Curses<'a> {
                         _ => unreachable!(),
                     }
                 } else {
-                    let t = Duration::minutes(settings.week_goal);
+                    let t = Duration::minutes(settings.week_goal_for(&today));
                     if f == 4 {
                         t.num_hours() as f32
                     } else {
@@ -417,12 +1014,19 @@ impl<'a> Curses<'a> {
         } else {
             format!("{:02}", value)
         });
-        win.attroff(A_REVERSE);
+        win.attroff(Attr::Reverse);
         win.refresh();
         self.option_win = Some(win);
     }
 
     pub fn cur_y_in_week(&self, d: &FlexDay) -> i32 {
+        if self.month_view {
+            // the selected day is always shown
+            return d
+                .date
+                .and_then(|date| self.month_row(MonthLine::Day(date)))
+                .unwrap_or(1);
+        }
         match d.weekday().expect("weekday not set, impossible") {
             Weekday::Mon => 2,
             Weekday::Tue => 3,
@@ -434,3 +1038,82 @@ impl<'a> Curses<'a> {
         }
     }
 }
+
+// label, time to leave at and how long until then, e.g. "Day:     16:50  01:23"
+fn leave_line(label: &str, time: Option<NaiveTime>, now: NaiveTime) -> String {
+    match time {
+        Some(time) => {
+            // rounded up, "now" once the minute is reached
+            let left = (time.signed_duration_since(now).num_seconds() + 59) / 60;
+            let countdown = if left > 0 {
+                minutes_to_string(left)
+            } else {
+                "now".to_string()
+            };
+            format!(
+                "{:<9}{:02}:{:02}{:>7}",
+                label,
+                time.hour(),
+                time.minute(),
+                countdown
+            )
+        }
+        None => format!("{:<9}--:--", label),
+    }
+}
+
+// attribute showing a day type color, None for normal text
+fn color_attr(color: Color) -> Option<Attr> {
+    match color {
+        Color::Normal => None,
+        Color::Dim => Some(Attr::Dim),
+        Color::Red => Some(Attr::Red),
+        Color::Green => Some(Attr::Green),
+        Color::Yellow => Some(Attr::Yellow),
+        Color::Blue => Some(Attr::Blue),
+        Color::Magenta => Some(Attr::Magenta),
+        Color::Cyan => Some(Attr::Cyan),
+    }
+}
+
+// row of the day in the week, `*` at the end when it has a note
+fn day_line(d: &FlexDay, settings: &Settings) -> String {
+    format!("{}{}", d.line(settings), if d.note.is_some() { "*" } else { " " })
+}
+
+// split `text` in lines of at most `width` chars, on spaces when possible
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let chars: Vec<char> = word.chars().collect();
+        for chunk in chars.chunks(width) {
+            if !line.is_empty() && line.chars().count() + 1 + chunk.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(chunk);
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_test() {
+        assert_eq!(
+            wrap("data/2019_03.json: invalid JSON", 20),
+            vec!["data/2019_03.json:", "invalid JSON"]
+        );
+        assert_eq!(wrap("abcdefgh ij", 4), vec!["abcd", "efgh", "ij"]);
+        assert!(wrap("", 4).is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(not(windows))]
+mod ansi;
+#[cfg(windows)]
+mod win32;
+
+use failure::Error;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    Character(char),
+    KeyUp,
+    KeyDown,
+    KeyLeft,
+    KeyRight,
+    KeyHome,
+    KeyPPage,
+    KeyNPage,
+    KeyResize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attr {
+    Bold,
+    Dim,
+    Reverse,
+    Underline,
+    // red on black, for negative values
+    Alert,
+    // foreground colors, e.g. of the day types
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+/// Drawing area, coordinates are relative to the window unless stated otherwise
+pub trait Window {
+    fn mv(&self, y: i32, x: i32);
+    /// print at cursor position, text going past the right edge is cut
+    fn printw(&self, s: &str);
+    fn mvprintw(&self, y: i32, x: i32, s: &str) {
+        self.mv(y, x);
+        self.printw(s);
+    }
+    fn attron(&self, attr: Attr);
+    fn attroff(&self, attr: Attr);
+    fn clrtoeol(&self);
+    fn clear(&self);
+    fn refresh(&self);
+    fn get_max_x(&self) -> i32;
+    fn get_max_y(&self) -> i32;
+    /// single line box around the window
+    fn draw_box(&self);
+    /// left, right, top, bottom, top left, top right, bottom left and bottom right chars
+    fn border(&self, chars: [char; 8]);
+    /// new window at screen coordinates `y`, `x`
+    fn subwin(&self, lines: i32, cols: i32, y: i32, x: i32) -> Option<Box<dyn Window>>;
+    /// new window at `y`, `x` in this window
+    fn derwin(&self, lines: i32, cols: i32, y: i32, x: i32) -> Option<Box<dyn Window>>;
+}
+
+pub trait Backend {
+    fn screen(&self) -> &dyn Window;
+    /// wait for a key, no more than `timeout` if given
+    fn getch(&self, timeout: Option<Duration>) -> Option<Input>;
+    /// give the terminal back in the state it was found
+    fn end(&self);
+}
+
+/// put the terminal back in normal mode, does nothing if it already is
+pub fn restore() {
+    #[cfg(windows)]
+    win32::restore();
+    #[cfg(not(windows))]
+    ansi::restore();
+}
+
+/// set up the terminal with the backend of the platform
+pub fn open() -> Result<Box<dyn Backend>, Error> {
+    #[cfg(windows)]
+    let backend = win32::Pancurses::open()?;
+    #[cfg(not(windows))]
+    let backend = ansi::Ansi::open()?;
+    Ok(Box::new(backend))
+}
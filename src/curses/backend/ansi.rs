@@ -0,0 +1,334 @@
+use super::{Attr, Backend, Input, Window};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{
+    Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use failure::Error;
+use std::cell::Cell;
+use std::io::{stdout, Write};
+use std::rc::Rc;
+use std::time::Duration;
+
+const ATTRS: [Attr; 11] = [
+    Attr::Bold,
+    Attr::Dim,
+    Attr::Reverse,
+    Attr::Underline,
+    Attr::Alert,
+    Attr::Red,
+    Attr::Green,
+    Attr::Yellow,
+    Attr::Blue,
+    Attr::Magenta,
+    Attr::Cyan,
+];
+
+fn attr_bit(attr: Attr) -> u16 {
+    1 << ATTRS.iter().position(|a| *a == attr).unwrap_or(0)
+}
+
+/// Window drawing straight to the terminal with ANSI escape sequences
+pub struct AnsiWindow {
+    // screen coordinates of the top left corner
+    top: i32,
+    left: i32,
+    // the screen follows the size of the terminal
+    lines: Cell<i32>,
+    cols: Cell<i32>,
+    cursor: Cell<(i32, i32)>,
+    attrs: Cell<u16>,
+    // keeps the terminal alive as long as a window is
+    _term: Rc<Term>,
+}
+
+impl AnsiWindow {
+    fn new(term: Rc<Term>, top: i32, left: i32, lines: i32, cols: i32) -> AnsiWindow {
+        AnsiWindow {
+            top,
+            left,
+            lines: Cell::new(lines),
+            cols: Cell::new(cols),
+            cursor: Cell::new((0, 0)),
+            attrs: Cell::new(0),
+            _term: term,
+        }
+    }
+
+    // the windows made before keep their size, they are made again by the caller
+    fn resize(&self, lines: i32, cols: i32) {
+        self.lines.set(lines);
+        self.cols.set(cols);
+    }
+
+    // print `s` at `y`, `x` without moving the cursor, cut to the window
+    fn put(&self, y: i32, x: i32, s: &str) {
+        if y < 0 || y >= self.lines.get() || x < 0 || x >= self.cols.get() {
+            return;
+        }
+        let text: String = s.chars().take((self.cols.get() - x) as usize).collect();
+        let mut out = stdout();
+        let _ = queue!(
+            out,
+            MoveTo((self.left + x) as u16, (self.top + y) as u16),
+            SetAttribute(Attribute::Reset)
+        );
+        let attrs = self.attrs.get();
+        for attr in ATTRS.iter().filter(|a| attrs & attr_bit(**a) != 0) {
+            let _ = match attr {
+                Attr::Bold => queue!(out, SetAttribute(Attribute::Bold)),
+                Attr::Dim => queue!(out, SetAttribute(Attribute::Dim)),
+                Attr::Reverse => queue!(out, SetAttribute(Attribute::Reverse)),
+                Attr::Underline => queue!(out, SetAttribute(Attribute::Underlined)),
+                Attr::Alert => queue!(
+                    out,
+                    SetForegroundColor(Color::DarkRed),
+                    SetBackgroundColor(Color::Black)
+                ),
+                Attr::Red => queue!(out, SetForegroundColor(Color::DarkRed)),
+                Attr::Green => queue!(out, SetForegroundColor(Color::DarkGreen)),
+                Attr::Yellow => queue!(out, SetForegroundColor(Color::DarkYellow)),
+                Attr::Blue => queue!(out, SetForegroundColor(Color::DarkBlue)),
+                Attr::Magenta => queue!(out, SetForegroundColor(Color::DarkMagenta)),
+                Attr::Cyan => queue!(out, SetForegroundColor(Color::DarkCyan)),
+            };
+        }
+        let _ = queue!(out, Print(text), SetAttribute(Attribute::Reset));
+    }
+
+    fn child(&self, lines: i32, cols: i32, top: i32, left: i32) -> Option<Box<dyn Window>> {
+        let inside = top >= self.top
+            && left >= self.left
+            && top + lines <= self.top + self.lines.get()
+            && left + cols <= self.left + self.cols.get();
+        if lines <= 0 || cols <= 0 || !inside {
+            return None;
+        }
+        Some(Box::new(AnsiWindow::new(
+            Rc::clone(&self._term),
+            top,
+            left,
+            lines,
+            cols,
+        )))
+    }
+}
+
+impl Window for AnsiWindow {
+    fn mv(&self, y: i32, x: i32) {
+        self.cursor.set((y, x));
+    }
+
+    fn printw(&self, s: &str) {
+        let (y, x) = self.cursor.get();
+        self.put(y, x, s);
+        let x = (x + s.chars().count() as i32).min(self.cols.get());
+        self.cursor.set((y, x));
+    }
+
+    fn attron(&self, attr: Attr) {
+        self.attrs.set(self.attrs.get() | attr_bit(attr));
+    }
+
+    fn attroff(&self, attr: Attr) {
+        self.attrs.set(self.attrs.get() & !attr_bit(attr));
+    }
+
+    fn clrtoeol(&self) {
+        let (y, x) = self.cursor.get();
+        let attrs = self.attrs.replace(0);
+        self.put(y, x, &" ".repeat((self.cols.get() - x).max(0) as usize));
+        self.attrs.set(attrs);
+    }
+
+    fn clear(&self) {
+        let attrs = self.attrs.replace(0);
+        let blank = " ".repeat(self.cols.get() as usize);
+        for y in 0..self.lines.get() {
+            self.put(y, 0, &blank);
+        }
+        self.attrs.set(attrs);
+        self.cursor.set((0, 0));
+    }
+
+    fn refresh(&self) {
+        let _ = stdout().flush();
+    }
+
+    fn get_max_x(&self) -> i32 {
+        self.cols.get()
+    }
+
+    fn get_max_y(&self) -> i32 {
+        self.lines.get()
+    }
+
+    fn draw_box(&self) {
+        self.border(['│', '│', '─', '─', '┌', '┐', '└', '┘']);
+    }
+
+    fn border(&self, chars: [char; 8]) {
+        let [ls, rs, ts, bs, tl, tr, bl, br] = chars;
+        let inner = (self.cols.get() - 2).max(0) as usize;
+        let line = |l: char, m: char, r: char| format!("{}{}{}", l, m.to_string().repeat(inner), r);
+        self.put(0, 0, &line(tl, ts, tr));
+        for y in 1..self.lines.get() - 1 {
+            self.put(y, 0, &ls.to_string());
+            self.put(y, self.cols.get() - 1, &rs.to_string());
+        }
+        self.put(self.lines.get() - 1, 0, &line(bl, bs, br));
+    }
+
+    fn subwin(&self, lines: i32, cols: i32, y: i32, x: i32) -> Option<Box<dyn Window>> {
+        self.child(lines, cols, y, x)
+    }
+
+    fn derwin(&self, lines: i32, cols: i32, y: i32, x: i32) -> Option<Box<dyn Window>> {
+        self.child(lines, cols, self.top + y, self.left + x)
+    }
+}
+
+pub fn restore() {
+    if terminal::is_raw_mode_enabled().unwrap_or(false) {
+        let _ = execute!(stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// raw mode and alternate screen, left on drop
+struct Term {
+    ended: Cell<bool>,
+}
+
+impl Term {
+    fn end(&self) {
+        if !self.ended.replace(true) {
+            restore();
+        }
+    }
+}
+
+impl Drop for Term {
+    fn drop(&mut self) {
+        self.end();
+    }
+}
+
+/// Backend for any ANSI terminal
+pub struct Ansi {
+    term: Rc<Term>,
+    screen: AnsiWindow,
+}
+
+impl Ansi {
+    pub fn open() -> Result<Ansi, Error> {
+        terminal::enable_raw_mode()?;
+        let term = Rc::new(Term {
+            ended: Cell::new(false),
+        });
+        execute!(stdout(), EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        let (cols, lines) = terminal::size()?;
+        let screen = AnsiWindow::new(Rc::clone(&term), 0, 0, lines as i32, cols as i32);
+        Ok(Ansi { term, screen })
+    }
+}
+
+impl Backend for Ansi {
+    fn screen(&self) -> &dyn Window {
+        &self.screen
+    }
+
+    fn getch(&self, timeout: Option<Duration>) -> Option<Input> {
+        let _ = stdout().flush();
+        if let Some(timeout) = timeout {
+            if !event::poll(timeout).unwrap_or(false) {
+                return None;
+            }
+        }
+        match event::read() {
+            Ok(Event::Key(key)) => key_to_input(key),
+            Ok(Event::Resize(cols, lines)) => {
+                self.screen.resize(lines as i32, cols as i32);
+                let _ = execute!(stdout(), Clear(ClearType::All));
+                Some(Input::KeyResize)
+            }
+            _ => None,
+        }
+    }
+
+    fn end(&self) {
+        self.term.end();
+    }
+}
+
+fn key_to_input(key: KeyEvent) -> Option<Input> {
+    if key.kind == KeyEventKind::Release {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            if c.is_ascii_alphabetic() {
+                // control codes, as a curses terminal sends them
+                Some(Input::Character(
+                    (c.to_ascii_lowercase() as u8 - b'a' + 1) as char,
+                ))
+            } else {
+                None
+            }
+        }
+        KeyCode::Char(c) => Some(Input::Character(c)),
+        KeyCode::Enter => Some(Input::Character('\n')),
+        KeyCode::Esc => Some(Input::Character('\x1B')),
+        KeyCode::Tab => Some(Input::Character('\t')),
+        KeyCode::Backspace => Some(Input::Character('\u{8}')),
+        KeyCode::Up => Some(Input::KeyUp),
+        KeyCode::Down => Some(Input::KeyDown),
+        KeyCode::Left => Some(Input::KeyLeft),
+        KeyCode::Right => Some(Input::KeyRight),
+        KeyCode::Home => Some(Input::KeyHome),
+        KeyCode::PageUp => Some(Input::KeyPPage),
+        KeyCode::PageDown => Some(Input::KeyNPage),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_to_input_test() {
+        let key = |code, modifiers| key_to_input(KeyEvent::new(code, modifiers));
+        assert_eq!(
+            key(KeyCode::Char('h'), KeyModifiers::NONE),
+            Some(Input::Character('h'))
+        );
+        assert_eq!(
+            key(KeyCode::Char('H'), KeyModifiers::SHIFT),
+            Some(Input::Character('H'))
+        );
+        assert_eq!(
+            key(KeyCode::Enter, KeyModifiers::NONE),
+            Some(Input::Character('\n'))
+        );
+        assert_eq!(
+            key(KeyCode::Esc, KeyModifiers::NONE),
+            Some(Input::Character('\x1B'))
+        );
+        assert_eq!(
+            key(KeyCode::Backspace, KeyModifiers::NONE),
+            Some(Input::Character('\u{8}'))
+        );
+        assert_eq!(
+            key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(Input::Character('\x03'))
+        );
+        assert_eq!(
+            key(KeyCode::PageDown, KeyModifiers::NONE),
+            Some(Input::KeyNPage)
+        );
+        assert_eq!(key(KeyCode::F(1), KeyModifiers::NONE), None);
+    }
+}
@@ -0,0 +1,168 @@
+use super::{Attr, Backend, Input, Window};
+use failure::Error;
+use pancurses::{chtype, COLOR_PAIR};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// curses mode is on
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        pancurses::endwin();
+    }
+}
+
+fn attr_to_chtype(attr: Attr) -> chtype {
+    match attr {
+        Attr::Bold => pancurses::A_BOLD,
+        Attr::Dim => pancurses::A_DIM,
+        Attr::Reverse => pancurses::A_REVERSE,
+        Attr::Underline => pancurses::A_UNDERLINE,
+        Attr::Alert => COLOR_PAIR(1),
+        Attr::Red => COLOR_PAIR(2),
+        Attr::Green => COLOR_PAIR(3),
+        Attr::Yellow => COLOR_PAIR(4),
+        Attr::Blue => COLOR_PAIR(5),
+        Attr::Magenta => COLOR_PAIR(6),
+        Attr::Cyan => COLOR_PAIR(7),
+    }
+}
+
+impl Window for pancurses::Window {
+    fn mv(&self, y: i32, x: i32) {
+        pancurses::Window::mv(self, y, x);
+    }
+
+    fn printw(&self, s: &str) {
+        pancurses::Window::printw(self, s);
+    }
+
+    fn attron(&self, attr: Attr) {
+        pancurses::Window::attron(self, attr_to_chtype(attr));
+    }
+
+    fn attroff(&self, attr: Attr) {
+        pancurses::Window::attroff(self, attr_to_chtype(attr));
+    }
+
+    fn clrtoeol(&self) {
+        pancurses::Window::clrtoeol(self);
+    }
+
+    fn clear(&self) {
+        pancurses::Window::clear(self);
+    }
+
+    fn refresh(&self) {
+        pancurses::Window::refresh(self);
+    }
+
+    fn get_max_x(&self) -> i32 {
+        pancurses::Window::get_max_x(self)
+    }
+
+    fn get_max_y(&self) -> i32 {
+        pancurses::Window::get_max_y(self)
+    }
+
+    fn draw_box(&self) {
+        pancurses::Window::draw_box(self, 0, 0);
+    }
+
+    fn border(&self, chars: [char; 8]) {
+        let [ls, rs, ts, bs, tl, tr, bl, br] = chars;
+        pancurses::Window::border(self, ls, rs, ts, bs, tl, tr, bl, br);
+    }
+
+    fn subwin(&self, lines: i32, cols: i32, y: i32, x: i32) -> Option<Box<dyn Window>> {
+        match pancurses::Window::subwin(self, lines, cols, y, x) {
+            Ok(win) => Some(Box::new(win)),
+            Err(_) => None,
+        }
+    }
+
+    fn derwin(&self, lines: i32, cols: i32, y: i32, x: i32) -> Option<Box<dyn Window>> {
+        match pancurses::Window::derwin(self, lines, cols, y, x) {
+            Ok(win) => Some(Box::new(win)),
+            Err(_) => None,
+        }
+    }
+}
+
+/// Backend using PDCurses
+pub struct Pancurses {
+    screen: pancurses::Window,
+}
+
+impl Pancurses {
+    pub fn open() -> Result<Pancurses, Error> {
+        let screen = pancurses::initscr();
+        ACTIVE.store(true, Ordering::SeqCst);
+        screen.keypad(true);
+        pancurses::noecho();
+        pancurses::cbreak();
+        pancurses::start_color();
+        pancurses::curs_set(0);
+        pancurses::init_pair(1, pancurses::COLOR_RED, pancurses::COLOR_BLACK);
+        let colors = [
+            pancurses::COLOR_RED,
+            pancurses::COLOR_GREEN,
+            pancurses::COLOR_YELLOW,
+            pancurses::COLOR_BLUE,
+            pancurses::COLOR_MAGENTA,
+            pancurses::COLOR_CYAN,
+        ];
+        for (idx, color) in colors.iter().enumerate() {
+            // pairs 2 to 7 of `attr_to_chtype`, default background
+            pancurses::init_pair(idx as i16 + 2, *color, -1);
+        }
+        Ok(Pancurses { screen })
+    }
+}
+
+impl Backend for Pancurses {
+    fn screen(&self) -> &dyn Window {
+        &self.screen
+    }
+
+    fn getch(&self, timeout: Option<Duration>) -> Option<Input> {
+        let input = match timeout {
+            Some(timeout) => {
+                self.screen.nodelay(true);
+                pancurses::half_delay((timeout.as_millis() / 100).max(1) as i32);
+                let input = self.screen.getch();
+                pancurses::nocbreak(); // Reset the halfdelay() value
+                pancurses::cbreak();
+                input
+            }
+            None => self.screen.getch(),
+        };
+        match input? {
+            pancurses::Input::Character(c) => Some(Input::Character(c)),
+            pancurses::Input::KeyUp => Some(Input::KeyUp),
+            pancurses::Input::KeyDown => Some(Input::KeyDown),
+            pancurses::Input::KeyLeft => Some(Input::KeyLeft),
+            pancurses::Input::KeyRight => Some(Input::KeyRight),
+            pancurses::Input::KeyHome => Some(Input::KeyHome),
+            pancurses::Input::KeyPPage => Some(Input::KeyPPage),
+            pancurses::Input::KeyNPage => Some(Input::KeyNPage),
+            pancurses::Input::KeyResize => {
+                // take the new size of the console
+                pancurses::resize_term(0, 0);
+                Some(Input::KeyResize)
+            }
+            _ => None,
+        }
+    }
+
+    fn end(&self) {
+        restore();
+    }
+}
+
+impl Drop for Pancurses {
+    fn drop(&mut self) {
+        restore();
+    }
+}
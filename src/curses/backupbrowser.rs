@@ -0,0 +1,52 @@
+use super::backend::Input;
+use super::Curses;
+use crate::backup::{self, Snapshot};
+use crate::datadir::data_dir;
+use crate::storage::storage;
+use chrono::Local;
+use failure::Error;
+
+// list the backups with their differences, return true if one was restored
+pub fn browse_backups(curses: &Curses) -> Result<bool, Error> {
+    let names = backup::list(data_dir())?;
+    if names.is_empty() {
+        curses.print_error("No backup yet, one is taken each day the program starts");
+        return Ok(false);
+    }
+    let mut selected = 0;
+    let mut diff = describe(&names[selected])?;
+    loop {
+        curses.print_backups(&names, selected, &diff);
+        match curses.wait_key() {
+            Some(Input::KeyUp) if selected > 0 => {
+                selected -= 1;
+                diff = describe(&names[selected])?;
+            }
+            Some(Input::KeyDown) if selected + 1 < names.len() => {
+                selected += 1;
+                diff = describe(&names[selected])?;
+            }
+            Some(Input::Character('\n')) => {
+                let question = format!(
+                    "Restore backup {}? The current data is backed up first",
+                    names[selected]
+                );
+                if curses.ask(" Restore ", &question) {
+                    Snapshot::load(data_dir(), &names[selected])?
+                        .restore(storage(), Local::now().naive_local())?;
+                    return Ok(true);
+                }
+            }
+            Some(Input::Character('\x1B')) | Some(Input::Character('q')) => return Ok(false),
+            _ => {}
+        }
+    }
+}
+
+fn describe(name: &str) -> Result<Vec<String>, Error> {
+    let diffs = Snapshot::load(data_dir(), name)?.diff(storage())?;
+    if diffs.is_empty() {
+        return Ok(vec!["Same as current data".to_string()]);
+    }
+    Ok(diffs.iter().map(|d| d.to_string()).collect())
+}
@@ -1,3 +1,4 @@
+use crate::settings::Settings;
 use crate::timedata::*;
 use chrono::{NaiveTime, Weekday, Duration, Timelike};
 use std::ops::{Add, Sub};
@@ -7,28 +8,35 @@ pub enum TimeField {
     Minute,
 }
 
-fn scroll_status(d: &mut FlexDay, up: bool) {
+// previous or next status in the order of the day types, weekend days can only be worked
+fn scroll_status(d: &mut FlexDay, up: bool, settings: &Settings) {
     let wd = d.weekday().expect("should have weekday");
-    d.status = if up {
-        match d.status {
-            DayStatus::Worked | DayStatus::Holiday => DayStatus::Worked,
-            DayStatus::Half => DayStatus::Holiday,
-            DayStatus::Sick => DayStatus::Half,
-            DayStatus::Weekend => DayStatus::Worked,
-        }
-    } else {
-        match d.status {
-            DayStatus::Worked => {
-                match wd {
-                    Weekday::Sat | Weekday::Sun => DayStatus::Weekend,
-                    _ => DayStatus::Holiday,
-                }
-            }
-            DayStatus::Holiday => DayStatus::Half,
-            DayStatus::Half => DayStatus::Sick,
-            DayStatus::Sick => DayStatus::Sick,
-            DayStatus::Weekend => DayStatus::Weekend,
+    let weekend_day = wd == Weekday::Sat || wd == Weekday::Sun;
+    let old_status = d.status;
+    d.status = match d.status {
+        DayStatus::Weekend if up => DayStatus::Worked,
+        DayStatus::Weekend => DayStatus::Weekend,
+        DayStatus::Worked if weekend_day && !up => DayStatus::Weekend,
+        status => {
+            let statuses = DayTypes::new(settings).statuses();
+            let idx = statuses.iter().position(|&s| s == status).unwrap_or(0);
+            let idx = if up {
+                idx.saturating_sub(1)
+            } else {
+                (idx + 1).min(statuses.len() - 1)
+            };
+            statuses[idx]
         }
+    };
+    // the working window follows the half taken off
+    let is_half = |status| {
+        matches!(
+            day_credit(settings, status),
+            Credit::MorningOff | Credit::AfternoonOff
+        )
+    };
+    if d.status != old_status && (is_half(old_status) || is_half(d.status)) {
+        d.reset_to_schedule(settings);
     }
 }
 
@@ -50,22 +58,29 @@ fn add_to_minute(time: NaiveTime, up: bool, nb: i64) -> NaiveTime {
     }
 }
 
-pub fn process_key_up_down(cur_field: usize, up: bool, mut d: &mut FlexDay) {
+pub fn process_key_up_down(
+    cur_field: usize,
+    session: usize,
+    up: bool,
+    d: &mut FlexDay,
+    settings: &Settings,
+) {
+    let s = &mut d.sessions[session];
     match cur_field {
         0 => {
-            scroll_status(&mut d, up);
+            scroll_status(d, up, settings);
         }
         1 => {
-            d.start = add_to_hour(d.start, up, 1);
+            s.start = add_to_hour(s.start, up, 1);
         }
         2 => {
-            d.start = add_to_minute(d.start, up, 1);
+            s.start = add_to_minute(s.start, up, 1);
         }
         3 => {
-            d.end = add_to_hour(d.end, up, 1);
+            s.end = add_to_hour(s.end, up, 1);
         }
         4 => {
-            d.end = add_to_minute(d.end, up, 1);
+            s.end = add_to_minute(s.end, up, 1);
         }
         5 => d.pause += if up { 60 } else { -60 },
         6 => d.pause += if up { 1 } else { -1 },
@@ -76,16 +91,14 @@ pub fn process_key_up_down(cur_field: usize, up: bool, mut d: &mut FlexDay) {
 fn edit_2nd_digit_hour(time: NaiveTime, digit: u32) -> NaiveTime {
     match time.hour() {
         1 => {
-            time.with_hour(time.hour() * 10 + digit).expect(&format!(
-                "something wrong while with_hour with {}",
-                time.hour() * 10 + digit
-            ))
+            time.with_hour(time.hour() * 10 + digit).unwrap_or_else(|| {
+                panic!("something wrong while with_hour with {}", time.hour() * 10 + digit)
+            })
         }
         2 if digit <= 3 => {
-            time.with_hour(time.hour() * 10 + digit).expect(&format!(
-                "something wrong while with_hour with {}",
-                time.hour() * 10 + digit
-            ))
+            time.with_hour(time.hour() * 10 + digit).unwrap_or_else(|| {
+                panic!("something wrong while with_hour with {}", time.hour() * 10 + digit)
+            })
         }
         _ => time,
     }
@@ -93,13 +106,9 @@ fn edit_2nd_digit_hour(time: NaiveTime, digit: u32) -> NaiveTime {
 
 fn edit_2nd_digit_minute(time: NaiveTime, digit: u32) -> NaiveTime {
     if time.minute() <= 5 {
-        time.with_minute(time.minute() * 10 + digit).expect(
-            &format!(
-                "something wrong while with_minute with {}",
-                time.minute() * 10 +
-                    digit
-            ),
-        )
+        time.with_minute(time.minute() * 10 + digit).unwrap_or_else(|| {
+            panic!("something wrong while with_minute with {}", time.minute() * 10 + digit)
+        })
     } else {
         time
     }
@@ -117,16 +126,14 @@ pub fn process_digit_input_for_time(
             Some(digit) => {
                 match field {
                     TimeField::Hour => {
-                        time.with_hour(digit).expect(&format!(
-                            "something wrong while with_hour with {}",
-                            digit
-                        ))
+                        time.with_hour(digit).unwrap_or_else(|| {
+                            panic!("something wrong while with_hour with {}", digit)
+                        })
                     }
                     TimeField::Minute => {
-                        time.with_minute(digit).expect(&format!(
-                            "something wrong while with_minute with {}",
-                            digit
-                        ))
+                        time.with_minute(digit).unwrap_or_else(|| {
+                            panic!("something wrong while with_minute with {}", digit)
+                        })
                     }
                 }
             }
@@ -134,17 +141,15 @@ pub fn process_digit_input_for_time(
                 match field {
                     TimeField::Hour => {
                         let t = time.hour() / 10;
-                        time.with_hour(t).expect(&format!(
-                            "something wrong while with_hour with {}",
-                            t
-                        ))
+                        time.with_hour(t).unwrap_or_else(|| {
+                            panic!("something wrong while with_hour with {}", t)
+                        })
                     }
                     TimeField::Minute => {
                         let t = time.minute() / 10;
-                        time.with_minute(t).expect(&format!(
-                            "something wrong while with_minute with {}",
-                            t
-                        ))
+                        time.with_minute(t).unwrap_or_else(|| {
+                            panic!("something wrong while with_minute with {}", t)
+                        })
                     }
                 }
             }
@@ -219,20 +224,27 @@ pub fn process_digit_input_for_number(nb: f32, c: char, digit_idx: i32) -> f32 {
     }
 }
 
-pub fn process_digit_input(cur_field: usize, c: char, digit_idx: i32, d: &mut FlexDay) {
+pub fn process_digit_input(
+    cur_field: usize,
+    session: usize,
+    c: char,
+    digit_idx: i32,
+    d: &mut FlexDay,
+) {
+    let s = &mut d.sessions[session];
     match cur_field {
         0 => unreachable!(),
         1 => {
-            d.start = process_digit_input_for_time(d.start, TimeField::Hour, c, digit_idx);
+            s.start = process_digit_input_for_time(s.start, TimeField::Hour, c, digit_idx);
         }
         2 => {
-            d.start = process_digit_input_for_time(d.start, TimeField::Minute, c, digit_idx);
+            s.start = process_digit_input_for_time(s.start, TimeField::Minute, c, digit_idx);
         }
         3 => {
-            d.end = process_digit_input_for_time(d.end, TimeField::Hour, c, digit_idx);
+            s.end = process_digit_input_for_time(s.end, TimeField::Hour, c, digit_idx);
         }
         4 => {
-            d.end = process_digit_input_for_time(d.end, TimeField::Minute, c, digit_idx);
+            s.end = process_digit_input_for_time(s.end, TimeField::Minute, c, digit_idx);
         }
         5 => {
             d.pause = process_digit_input_for_duration(d.pause, TimeField::Hour, c, digit_idx);
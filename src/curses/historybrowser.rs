@@ -0,0 +1,28 @@
+use super::Curses;
+use crate::settings::Settings;
+use crate::timedata::JournalEntry;
+use chrono::NaiveDate;
+
+// every change of the day at `date`, newest first
+pub fn browse_history(
+    curses: &Curses,
+    date: NaiveDate,
+    entries: &[&JournalEntry],
+    settings: &Settings,
+) {
+    let title = format!("History of {}", date.format("%a %d/%m/%Y"));
+    let mut lines = Vec::new();
+    for entry in entries {
+        lines.push(format!(
+            "{} {}",
+            entry.at.format("%d/%m/%Y %H:%M"),
+            entry.action
+        ));
+        lines.push(format!("  before  {}", entry.old.line(settings)));
+        lines.push(format!("  after   {}", entry.new.line(settings)));
+    }
+    if lines.is_empty() {
+        lines.push("No change recorded".to_string());
+    }
+    curses.browse_lines(&title, &lines);
+}
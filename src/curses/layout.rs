@@ -0,0 +1,126 @@
+use super::backend::Window;
+
+// size the main view is designed for, narrower terminals get the compact layout
+const SCREEN_LINES: i32 = 15;
+const SCREEN_COLS: i32 = 76;
+// a week of days and the statistics box
+const DAYS_COLS: i32 = 48;
+const STAT_LINES: i32 = 14;
+const STAT_COLS: i32 = 25;
+// smallest notes panel worth showing
+const NOTES_COLS: i32 = 20;
+const NOTES_LINES: i32 = 4;
+
+/// Position and size of a window, in the coordinates of its parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Area {
+    pub lines: i32,
+    pub cols: i32,
+    pub y: i32,
+    pub x: i32,
+}
+
+impl Area {
+    fn new(lines: i32, cols: i32, y: i32, x: i32) -> Area {
+        Area { lines, cols, y, x }
+    }
+}
+
+/// Windows of the main view for the size of the terminal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub week: Area,
+    // every day of the month, from the top of the screen
+    pub month: Area,
+    // rules broken, type, projects and note of the selected day, under the week
+    pub details: Area,
+    // None if the terminal is too small to fit it
+    pub stat: Option<Area>,
+    // notes of the displayed month, in the space left
+    pub notes: Option<Area>,
+}
+
+impl Layout {
+    pub fn new(lines: i32, cols: i32) -> Layout {
+        // the last line is for messages
+        let usable = lines - 1;
+        if cols < SCREEN_COLS {
+            // one column, the statistics under the week if there is room
+            let x = if cols >= DAYS_COLS + 2 { 2 } else { 0 };
+            let width = DAYS_COLS.min(cols - x);
+            // under the week and its details
+            let stat_y = SCREEN_LINES - 1;
+            let stat = if usable >= stat_y + STAT_LINES {
+                Some(Area::new(STAT_LINES, STAT_COLS.min(cols - x), stat_y, x))
+            } else {
+                None
+            };
+            return Layout {
+                week: Area::new(11, width, 2, x),
+                month: Area::new(usable, width, 0, x),
+                details: Area::new(2, width, 12, x),
+                stat,
+                notes: None,
+            };
+        }
+        let stat_x = DAYS_COLS + 2;
+        let notes = if cols - SCREEN_COLS >= NOTES_COLS {
+            Some(Area::new(usable, cols - SCREEN_COLS - 1, 0, SCREEN_COLS))
+        } else if usable - STAT_LINES >= NOTES_LINES {
+            Some(Area::new(
+                usable - STAT_LINES,
+                STAT_COLS,
+                STAT_LINES,
+                stat_x,
+            ))
+        } else {
+            None
+        };
+        Layout {
+            week: Area::new(11, DAYS_COLS, 2, 2),
+            month: Area::new(usable, DAYS_COLS, 0, 2),
+            details: Area::new((usable - 12).max(1), DAYS_COLS, 12, 2),
+            stat: Some(Area::new(STAT_LINES, STAT_COLS, 0, stat_x)),
+            notes,
+        }
+    }
+}
+
+/// window at `area` of `parent`, cut to fit in it
+pub fn fit(parent: &dyn Window, area: Area) -> Box<dyn Window> {
+    let (max_y, max_x) = (parent.get_max_y().max(1), parent.get_max_x().max(1));
+    let y = area.y.min(max_y - 1).max(0);
+    let x = area.x.min(max_x - 1).max(0);
+    let lines = area.lines.min(max_y - y).max(1);
+    let cols = area.cols.min(max_x - x).max(1);
+    parent
+        .derwin(lines, cols, y, x)
+        .expect("window should fit in its parent")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_test() {
+        let standard = Layout::new(SCREEN_LINES, SCREEN_COLS);
+        assert_eq!(standard.week, Area::new(11, 48, 2, 2));
+        assert_eq!(standard.details, Area::new(2, 48, 12, 2));
+        assert_eq!(standard.stat, Some(Area::new(14, 25, 0, 50)));
+        assert_eq!(standard.notes, None);
+
+        // notes beside the statistics, the month and the details taller
+        let large = Layout::new(40, 120);
+        assert_eq!(large.month, Area::new(39, 48, 0, 2));
+        assert_eq!(large.details.lines, 27);
+        assert_eq!(large.notes, Some(Area::new(39, 43, 0, 76)));
+        // or under them
+        assert_eq!(Layout::new(30, 80).notes, Some(Area::new(15, 25, 14, 50)));
+
+        let compact = Layout::new(24, 40);
+        assert_eq!(compact.week, Area::new(11, 40, 2, 0));
+        assert_eq!(compact.stat, None);
+        assert_eq!(Layout::new(30, 60).stat, Some(Area::new(14, 25, 14, 2)));
+    }
+}
@@ -1,17 +1,24 @@
+use super::backend::{Attr, Backend, Input};
+use super::backupbrowser;
 use super::editor;
+use super::historybrowser;
+use super::yearview;
 use super::Curses;
 use crate::curses::settingseditor;
+use crate::export;
 use crate::settings::Settings;
+use crate::storage::storage;
 use crate::timedata::*;
 use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime, Timelike, Weekday};
 use failure::Error;
-use pancurses::{Input, Window, COLOR_PAIR};
 use std::ops::{Add, Sub};
 
 pub struct Navigator<'a> {
     current_month: FlexMonth,
     current_day: NaiveDate,
-    undo_days: Vec<FlexDay>,
+    journal: Journal,
+    ledger: Ledger,
+    compliance: Compliance,
     pub days_off: DaysOff,
     pub curses: Curses<'a>,
 }
@@ -31,95 +38,186 @@ const FLD_MINUTE_START: usize = 2;
 const FLD_MINUTE_END: usize = 4;
 
 impl<'a> Navigator<'a> {
-    pub fn new(cur_day: NaiveDate, screen: &'a Window, settings: &Settings) -> Self {
+    pub fn new(
+        cur_day: NaiveDate,
+        backend: &'a dyn Backend,
+        settings: &Settings,
+    ) -> Result<Self, Error> {
         let mut nav = Navigator {
-            days_off: DaysOff::load(cur_day.year(), &settings),
-            current_month: FlexMonth::load(cur_day.year(), cur_day.month(), &settings),
+            days_off: DaysOff::load(cur_day.year(), settings)?,
+            current_month: FlexMonth::load(cur_day.year(), cur_day.month(), settings)?,
             current_day: cur_day,
-            curses: Curses::new(&screen),
-            undo_days: vec![],
+            curses: Curses::new(backend),
+            journal: Journal::load()?,
+            ledger: Ledger::load(settings)?,
+            compliance: Compliance::load(settings)?,
         };
         nav.days_off.roll_sick_days();
-        nav
+        Ok(nav)
     }
 
-    pub fn main_loop(&mut self, window: &Window, mut settings: &mut Settings) -> Result<(), Error> {
+    pub fn main_loop(&mut self, settings: &mut Settings) -> Result<(), Error> {
         let mut done = false;
         while !done {
-            match window.getch() {
-                Some(c) => {
-                    match c {
-                        Input::Character('q') | Input::Character('\x1B') => done = true,
-                        Input::KeyUp => {
-                            self.select_prev_day(&settings);
-                        }
-                        Input::KeyDown => {
-                            self.select_next_day(&settings);
-                        }
-                        Input::KeyLeft => {
-                            self.select_prev_week(&settings);
-                        }
-                        Input::KeyRight => {
-                            self.select_next_week(&settings);
-                        }
-                        Input::KeyPPage => {
-                            self.change_month(Direction::Previous, &settings);
-                        }
-                        Input::KeyNPage => {
-                            self.change_month(Direction::Next, &settings);
-                        }
-                        Input::Character('\n') => {
-                            self.store_undo(self.get_current_day().clone(), &settings);
-                            self.edit_day(&settings)?;
-                        }
-                        Input::Character(c) if c == 'h' || c == 's' => {
-                            self.change_status(c, &settings)?;
-                        }
-                        Input::Character('o') => {
-                            settingseditor::edit_settings(
-                                &mut self.curses,
-                                &mut settings,
-                                &mut self.days_off,
-                            )?;
-                        }
-                        Input::Character('u') => {
-                            self.undo(&settings)?;
-                        }
-                        Input::KeyHome => {
-                            let today = chrono::Local::today().naive_local();
-                            self.select_day(today, &settings);
-                        }
-                        Input::Character(c) if c == 'b' || c == 'e' => {
-                            let today = chrono::Local::today().naive_local();
-                            self.select_day(today, &settings);
-                            self.store_undo(self.get_current_day().clone(), &settings);
-                            let t = chrono::Local::now().naive_local().time();
-                            let t = NaiveTime::from_hms(t.hour(), t.minute(), 0); // clear seconds
-                            let (t, field) = if c == 'b' {
-                                (
-                                    t.sub(Duration::minutes(settings.offsets.entry)),
-                                    HourField::Begin,
-                                )
-                            } else {
-                                (
-                                    t.add(Duration::minutes(settings.offsets.exit)),
-                                    HourField::End,
-                                )
-                            };
-                            self.change_time(t, field, &settings)?;
-                            self.edit_day(&settings)?;
-                        }
-                        _ => {
-                            println!("unknown: {:?}", c);
-                        }
-                    }
-                }
-                None => {}
+            match self.curses.getch() {
+                Some(c) => match self.process_key(c, settings) {
+                    Ok(quit) => done = quit,
+                    Err(e) => self.report_error(&e, settings),
+                },
+                // no key for a while, count down to the leave times
+                None => self.print_status(settings),
             }
         }
         Ok(())
     }
 
+    // month statistics, days off and when today's work can end
+    fn print_status(&self, settings: &Settings) {
+        let today = Local::now().date_naive();
+        let (year, month) = grid_month_for(today);
+        let leave = if self.current_month.get_week_with_day(today).is_some() {
+            let account = self
+                .ledger
+                .total_until(self.current_month.year, self.current_month.month);
+            LeaveTimes::new(&self.current_month, today, account, settings)
+        } else {
+            // today's month is saved, or new and without anything worked yet
+            FlexMonth::load(year, month, settings).ok().and_then(|m| {
+                LeaveTimes::new(&m, today, self.ledger.total_until(year, month), settings)
+            })
+        };
+        self.curses.print_status(
+            &self.current_month,
+            &self.days_off,
+            &self.ledger,
+            leave.as_ref(),
+            settings,
+        );
+    }
+
+    // act on a key of the main view, return true to quit
+    fn process_key(&mut self, c: Input, settings: &mut Settings) -> Result<bool, Error> {
+        match c {
+            Input::Character('q') | Input::Character('\x1B') => return Ok(true),
+            Input::KeyUp => {
+                self.select_prev_day(settings)?;
+            }
+            Input::KeyDown => {
+                self.select_next_day(settings)?;
+            }
+            Input::KeyLeft => {
+                self.select_prev_week(settings)?;
+            }
+            Input::KeyRight => {
+                self.select_next_week(settings)?;
+            }
+            Input::KeyPPage => {
+                self.change_month(Direction::Previous, settings)?;
+            }
+            Input::KeyNPage => {
+                self.change_month(Direction::Next, settings)?;
+            }
+            Input::Character('\n') => {
+                let old = self.get_current_day().clone();
+                let new = self.edit_day(settings)?;
+                self.record_edit(old, new)?;
+            }
+            Input::Character(c) if c == 'h' || c == 's' => {
+                self.change_status(c, settings)?;
+            }
+            Input::Character('o') => {
+                settingseditor::edit_settings(&mut self.curses, settings, &mut self.days_off)?;
+            }
+            Input::Character('u') => {
+                self.walk_journal(Action::Undo, settings)?;
+            }
+            Input::Character('U') => {
+                self.walk_journal(Action::Redo, settings)?;
+            }
+            Input::Character('l') => {
+                let date = self.current_day;
+                historybrowser::browse_history(
+                    &self.curses,
+                    date,
+                    &self.journal.history(date),
+                    settings,
+                );
+                self.init(settings)?;
+            }
+            Input::Character('p') => {
+                self.tag_day(settings)?;
+            }
+            Input::Character('P') => {
+                self.show_projects(settings)?;
+            }
+            Input::Character('c') => {
+                self.show_compliance(settings)?;
+            }
+            Input::Character('f') => {
+                self.fix_unclocked(settings)?;
+            }
+            Input::Character('y') => {
+                self.show_year(settings)?;
+            }
+            Input::Character('m') => {
+                self.curses.toggle_month_view();
+                self.init(settings)?;
+            }
+            Input::KeyResize => {
+                self.curses.resize();
+                self.init(settings)?;
+            }
+            Input::Character('n') => {
+                self.edit_note(settings)?;
+            }
+            Input::Character('/') => {
+                self.search_note(settings)?;
+            }
+            Input::Character('x') => {
+                self.export_month(settings);
+            }
+            Input::Character('r') => {
+                if backupbrowser::browse_backups(&self.curses)? {
+                    self.reload(settings)?;
+                } else {
+                    self.init(settings)?;
+                }
+            }
+            Input::KeyHome => {
+                let today = chrono::Local::now().date_naive();
+                self.select_day(today, settings)?;
+            }
+            Input::Character(c) if c == 'b' || c == 'e' => {
+                let today = chrono::Local::now().date_naive();
+                self.select_day(today, settings)?;
+                let old = self.get_current_day().clone();
+                let now = chrono::Local::now().naive_local().time();
+                let (t, field) = if c == 'b' {
+                    (settings.entry_time(now), HourField::Begin)
+                } else {
+                    (settings.exit_time(now), HourField::End)
+                };
+                self.change_time(t, field, settings)?;
+                let new = self.edit_day(settings)?;
+                self.record_edit(old, new)?;
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    // show the error then redraw what the dialog covered
+    fn report_error(&mut self, e: &Error, settings: &Settings) {
+        match self.curses.offer_recovery(e) {
+            Ok(true) => {}
+            Ok(false) => self.curses.print_error(&e.to_string()),
+            Err(e) => self.curses.print_error(&e.to_string()),
+        }
+        if let Err(e) = self.init(settings) {
+            self.curses.print_message(&e.to_string());
+        }
+    }
+
     pub fn get_current_day(&self) -> &FlexDay {
         let day_and_week = self.current_month.get_week_with_day(self.current_day);
         match day_and_week {
@@ -128,12 +226,25 @@ impl<'a> Navigator<'a> {
         }
     }
 
-    pub fn init(&mut self, settings: &Settings) {
+    // read every file again, e.g. after a backup is restored
+    fn reload(&mut self, settings: &mut Settings) -> Result<(), Error> {
+        *settings = Settings::load()?.unwrap_or_default();
+        self.days_off = DaysOff::load(self.current_day.year(), settings)?;
+        self.days_off.roll_sick_days();
+        let (year, month) = (self.current_month.year, self.current_month.month);
+        self.current_month = FlexMonth::load(year, month, settings)?;
+        self.ledger = Ledger::load(settings)?;
+        self.compliance = Compliance::load(settings)?;
+        self.journal = Journal::load()?;
+        self.init(settings)
+    }
+
+    pub fn init(&mut self, settings: &Settings) -> Result<(), Error> {
         self.curses.main_win.clear();
         let date = self.current_day;
-        self.current_day = self.select_day(date, &settings);
-        self.curses
-            .print_status(&settings, &self.current_month, &self.days_off);
+        self.current_day = self.select_day(date, settings)?;
+        self.print_status(settings);
+        Ok(())
     }
 
     fn first_day_of_month_at_current_weekday(&self) -> NaiveDate {
@@ -149,120 +260,157 @@ impl<'a> Navigator<'a> {
         .expect("change_month: should have date")
     }
 
+    // rules broken in `week`, with the weeks before it for the average
+    fn week_violations(&self, week: &FlexWeek) -> Vec<Violation> {
+        match (week.days[0].date, week.days[6].date) {
+            (Some(monday), Some(sunday)) => self.compliance.violations(monday, sunday),
+            _ => Vec::new(),
+        }
+    }
+
     fn select_day_in_month(
-        &self,
+        &mut self,
         date: NaiveDate,
         month: &FlexMonth,
         settings: &Settings,
     ) -> Option<NaiveDate> {
         let day_and_week = month.get_week_with_day(date);
+        if day_and_week.is_some() {
+            self.curses.print_notes(month);
+        }
         match day_and_week {
-            Some((_, w, week_nb)) => {
-                self.curses.print_week_header(&month, week_nb);
-                self.curses.print_week(&w, &date);
-                self.curses
-                    .print_week_total(&w, w.total_minutes() < settings.week_goal);
+            Some(_) if self.curses.month_view => {
+                self.curses.print_month(month, date, settings);
+                for (idx, w) in month.weeks.iter().enumerate() {
+                    let violations = self.week_violations(w);
+                    self.curses.print_week_total(
+                        w,
+                        w.total_minutes(settings) < month.week_goal(idx),
+                        &violations,
+                        settings,
+                    );
+                }
+                Some(date)
+            }
+            Some((d, w, week_nb)) => {
+                let violations = self.week_violations(w);
+                let of_day: Vec<Violation> = violations
+                    .iter()
+                    .filter(|v| v.date == date)
+                    .cloned()
+                    .collect();
+                self.curses.print_week_header(month, week_nb);
+                self.curses.print_week(w, &date, settings);
+                self.curses.print_details(d, &of_day, settings);
+                let goal = month.week_goal(week_nb as usize - 1);
+                self.curses.print_week_total(
+                    w,
+                    w.total_minutes(settings) < goal,
+                    &violations,
+                    settings,
+                );
                 Some(date)
             }
             None => None,
         }
     }
 
-    pub fn select_day(&mut self, date: NaiveDate, settings: &Settings) -> NaiveDate {
-        let cur_month = self.current_month.clone();
-        match self.select_day_in_month(date, &cur_month, &settings) {
-            Some(date) => {
-                self.current_day = date;
-                date
-            }
-            None => {
-                let (year, month) = if date.month() < cur_month.month {
-                    prev_month(cur_month.year, cur_month.month)
-                } else {
-                    next_month(cur_month.year, cur_month.month)
-                };
-                self.current_month = FlexMonth::load(year, month, &settings);
-                self.select_day(date, &settings)
-            }
+    pub fn select_day(&mut self, date: NaiveDate, settings: &Settings) -> Result<NaiveDate, Error> {
+        if self.current_month.get_week_with_day(date).is_none() {
+            // may be months or years away
+            let (year, month) = grid_month_for(date);
+            self.current_month = FlexMonth::load(year, month, settings)?;
         }
+        let cur_month = self.current_month.clone();
+        let date = self
+            .select_day_in_month(date, &cur_month, settings)
+            .expect("the grid month should hold the date");
+        self.current_day = date;
+        Ok(date)
     }
 
-    pub fn select_prev_day(&mut self, settings: &Settings) {
+    pub fn select_prev_day(&mut self, settings: &Settings) -> Result<(), Error> {
         let old = self.current_day;
-        self.current_day = self.current_day.pred();
+        self.current_day = self.current_day.pred_opt().unwrap();
         if old == find_first_monday_of_grid(self.current_month.year, self.current_month.month) {
-            self.change_month(Direction::Previous, &settings)
+            self.change_month(Direction::Previous, settings)
         } else {
             let date = self.current_day;
-            self.select_day(date, &settings);
+            self.select_day(date, settings)?;
+            Ok(())
         }
     }
 
-    pub fn select_next_day(&mut self, settings: &Settings) {
+    pub fn select_next_day(&mut self, settings: &Settings) -> Result<(), Error> {
         let old = self.current_day;
-        self.current_day = self.current_day.succ();
+        self.current_day = self.current_day.succ_opt().unwrap();
         if old == find_last_sunday_for(self.current_month.year, self.current_month.month) {
-            self.change_month(Direction::Next, &settings)
+            self.change_month(Direction::Next, settings)
         } else {
             let date = self.current_day;
-            self.select_day(date, &settings);
+            self.select_day(date, settings)?;
+            Ok(())
         }
     }
 
-    pub fn select_prev_week(&mut self, settings: &Settings) {
+    pub fn select_prev_week(&mut self, settings: &Settings) -> Result<(), Error> {
         self.current_day = self.current_day.sub(Duration::days(7));
         if self.current_day
             < find_first_monday_of_grid(self.current_month.year, self.current_month.month)
         {
-            self.change_month(Direction::Previous, &settings)
+            self.change_month(Direction::Previous, settings)
         } else {
             let date = self.current_day;
-            self.select_day(date, &settings);
+            self.select_day(date, settings)?;
+            Ok(())
         }
     }
 
-    pub fn select_next_week(&mut self, settings: &Settings) {
+    pub fn select_next_week(&mut self, settings: &Settings) -> Result<(), Error> {
         self.current_day = self.current_day.add(Duration::days(7));
         if self.current_day
             > find_last_sunday_for(self.current_month.year, self.current_month.month)
         {
-            self.change_month(Direction::Next, &settings)
+            self.change_month(Direction::Next, settings)
         } else {
             let date = self.current_day;
-            self.select_day(date, &settings);
+            self.select_day(date, settings)?;
+            Ok(())
         }
     }
 
-    pub fn change_month(&mut self, direction: Direction, settings: &Settings) {
+    pub fn change_month(&mut self, direction: Direction, settings: &Settings) -> Result<(), Error> {
         let next = direction == Direction::Next;
         let (y, m) = if next {
             next_month(self.current_month.year, self.current_month.month)
         } else {
             prev_month(self.current_month.year, self.current_month.month)
         };
-        self.current_month = FlexMonth::load(y, m, &settings);
+        self.current_month = FlexMonth::load(y, m, settings)?;
         let date = if next {
             self.first_day_of_month_at_current_weekday()
         } else {
             self.last_day_of_month_at_current_weekday()
         };
-        self.current_day = self.select_day(date, &settings);
-        self.curses
-            .print_status(&settings, &self.current_month, &self.days_off);
+        self.current_day = self.select_day(date, settings)?;
+        self.print_status(settings);
+        Ok(())
     }
 
-    pub fn edit_day(&mut self, settings: &Settings) -> Result<(), Error> {
+    // edit the selected day, return it as edited
+    pub fn edit_day(&mut self, settings: &mut Settings) -> Result<FlexDay, Error> {
         let mut d = self.get_current_day().clone();
         let selected_day = d.date.expect("edit_day: must have date");
         let now = Local::now().naive_utc();
         let today = now.date();
-        let now = NaiveTime::from_hms(now.time().hour(), now.time().minute(), 0);
+        let now = NaiveTime::from_hms_opt(now.time().hour(), now.time().minute(), 0).unwrap();
         let cur_y = self.curses.cur_y_in_week(&d);
+        let last_session = d.sessions.len() - 1;
         let mut cur_field: usize = match d.status {
-            DayStatus::Weekend | DayStatus::Sick | DayStatus::Holiday => 0,
+            status if !status.is_timed(settings) => 0,
             _ => {
                 if selected_day == today {
-                    if now < NaiveTime::from_hms(12, 00, 00) {
+                    if now < NaiveTime::from_hms_opt(12, 00, 00).unwrap() {
                         FLD_MINUTE_START
                     } else {
                         FLD_MINUTE_END
@@ -275,7 +423,15 @@ impl<'a> Navigator<'a> {
             }
         };
 
-        self.curses.highlight_current_field(cur_field, &d, cur_y);
+        // work on the last session unless we are heading for the first start
+        let mut cur_session = if cur_field == FLD_MINUTE_START && selected_day != today {
+            0
+        } else {
+            last_session
+        };
+
+        self.curses
+            .highlight_current_field(cur_field, cur_session, &d, cur_y, settings);
         self.curses.week_win.refresh();
 
         let mut done = false;
@@ -283,8 +439,8 @@ impl<'a> Navigator<'a> {
         let mut digit_idx = 0;
         while !done {
             let old_status = d.status;
-            match self.curses.getch() {
-                Some(c) => match c {
+            if let Some(c) = self.curses.getch() {
+                match c {
                     Input::Character('\x1B') | Input::Character('\n') => done = true,
                     Input::KeyHome => {
                         done = true;
@@ -294,52 +450,146 @@ impl<'a> Navigator<'a> {
                         digit_idx = 0;
                         if cur_field < self.curses.fields.len() - 1 {
                             cur_field += 1;
-                            self.curses.highlight_current_field(cur_field, &d, cur_y);
+                            self.curses.highlight_current_field(
+                                cur_field,
+                                cur_session,
+                                &d,
+                                cur_y,
+                                settings,
+                            );
                         }
                     }
                     Input::KeyLeft => {
                         digit_idx = 0;
                         if cur_field > 0 {
                             cur_field -= 1;
-                            self.curses.highlight_current_field(cur_field, &d, cur_y);
+                            self.curses.highlight_current_field(
+                                cur_field,
+                                cur_session,
+                                &d,
+                                cur_y,
+                                settings,
+                            );
                         }
                     }
                     Input::KeyUp | Input::KeyDown => {
                         digit_idx = 0;
-                        editor::process_key_up_down(cur_field, c == Input::KeyUp, &mut d);
-                        self.curses.highlight_current_field(cur_field, &d, cur_y);
-                    }
-                    Input::Character(c) if (c >= '0' && c <= '9') || c == '\u{8}' => {
+                        let before = d.clone();
+                        editor::process_key_up_down(
+                            cur_field,
+                            cur_session,
+                            c == Input::KeyUp,
+                            &mut d,
+                            settings,
+                        );
+                        // the status field resets the times to the schedule on half days
                         if cur_field > 0 {
-                            editor::process_digit_input(cur_field, c, digit_idx, &mut d);
-                            digit_idx = (digit_idx + 1) % 2;
-                            self.curses.highlight_current_field(cur_field, &d, cur_y);
+                            d.record_changes(&before);
                         }
+                        // half days have a single session
+                        cur_session = cur_session.min(d.sessions.len() - 1);
+                        self.curses.highlight_current_field(
+                            cur_field,
+                            cur_session,
+                            &d,
+                            cur_y,
+                            settings,
+                        );
                     }
-                    _ => println!("unknown: {:?}", c),
-                },
-                None => {}
+                    Input::Character(c)
+                        if (c.is_ascii_digit() || c == '\u{8}') && cur_field > 0 =>
+                    {
+                        let before = d.clone();
+                        editor::process_digit_input(cur_field, cur_session, c, digit_idx, &mut d);
+                        d.record_changes(&before);
+                        digit_idx = (digit_idx + 1) % 2;
+                        self.curses.highlight_current_field(
+                            cur_field,
+                            cur_session,
+                            &d,
+                            cur_y,
+                            settings,
+                        );
+                    }
+                    Input::Character('\t') => {
+                        digit_idx = 0;
+                        cur_session = (cur_session + 1) % d.sessions.len();
+                        self.curses.highlight_current_field(
+                            cur_field,
+                            cur_session,
+                            &d,
+                            cur_y,
+                            settings,
+                        );
+                    }
+                    Input::Character('+') => {
+                        digit_idx = 0;
+                        cur_session = d.add_session();
+                        self.curses.highlight_current_field(
+                            cur_field,
+                            cur_session,
+                            &d,
+                            cur_y,
+                            settings,
+                        );
+                    }
+                    Input::Character('-') => {
+                        digit_idx = 0;
+                        d.remove_session(cur_session);
+                        if cur_session >= d.sessions.len() {
+                            cur_session = d.sessions.len() - 1;
+                        }
+                        self.curses.highlight_current_field(
+                            cur_field,
+                            cur_session,
+                            &d,
+                            cur_y,
+                            settings,
+                        );
+                    }
+                    Input::Character('p') => {
+                        digit_idx = 0;
+                        let current = d.sessions[cur_session].project.clone();
+                        if let Some(project) = self.choose_project(current.as_deref(), settings)? {
+                            d.sessions[cur_session].project = project;
+                        }
+                        // redraw what the list covered
+                        self.update_display_post_edit(old_status, d.clone(), settings)?;
+                        self.init(settings)?;
+                        self.curses.highlight_current_field(
+                            cur_field,
+                            cur_session,
+                            &d,
+                            cur_y,
+                            settings,
+                        );
+                        self.curses.week_win.refresh();
+                    }
+                    _ => {}
+                }
             }
-            self.update_display_post_edit(old_status, d, &settings)?;
+            self.update_display_post_edit(old_status, d.clone(), settings)?;
         }
         // remove any reverse attr
         let cur_day = self.current_day;
         self.select_day(
             if go_to_today {
-                Local::today().naive_local()
+                Local::now().date_naive()
             } else {
                 cur_day
             },
-            &settings,
-        );
-        Ok(())
+            settings,
+        )?;
+        Ok(d)
     }
 
     pub fn change_status(&mut self, c: char, settings: &Settings) -> Result<(), Error> {
-        let mut d = self.get_current_day().clone();
+        let old = self.get_current_day().clone();
+        let mut d = old.clone();
         let old_status = d.status;
         match d.weekday().expect("must have weekday") {
             Weekday::Sat | Weekday::Sun => {}
+            _ if d.status == DayStatus::Weekend => {} // day off in the schedule
             _ => {
                 d.status = match c {
                     'h' => {
@@ -358,7 +608,8 @@ impl<'a> Navigator<'a> {
                     }
                     _ => d.status,
                 };
-                self.update_display_post_direct_edit(old_status, d, &settings)?;
+                self.update_display_post_direct_edit(old_status, d.clone(), settings)?;
+                self.record_edit(old, d)?;
             }
         }
         Ok(())
@@ -370,16 +621,21 @@ impl<'a> Navigator<'a> {
         d: FlexDay,
         settings: &Settings,
     ) -> Result<(), Error> {
-        self.update_display_post_edit(old_status, d, &settings)?;
+        self.update_display_post_edit(old_status, d.clone(), settings)?;
         self.curses.week_win.mv(self.curses.cur_y_in_week(&d), 0);
-        if d.total_minutes() < 0 {
-            self.curses.week_win.attron(COLOR_PAIR(1));
+        if d.total_minutes(settings) < 0 {
+            self.curses.week_win.attron(Attr::Alert);
         }
-        self.curses.print_selected_day(&d);
-        if d.total_minutes() < 0 {
-            self.curses.week_win.attroff(COLOR_PAIR(1));
+        self.curses.print_selected_day(&d, settings);
+        if d.total_minutes(settings) < 0 {
+            self.curses.week_win.attroff(Attr::Alert);
         }
         self.curses.week_win.refresh();
+        let violations = match d.date {
+            Some(date) => self.compliance.violations(date, date),
+            None => Vec::new(),
+        };
+        self.curses.print_details(&d, &violations, settings);
         Ok(())
     }
 
@@ -390,15 +646,13 @@ impl<'a> Navigator<'a> {
         settings: &Settings,
     ) -> Result<(), Error> {
         let mut d = self.get_current_day().clone();
-        match d.status {
-            DayStatus::Worked | DayStatus::Half => {
-                match field {
-                    HourField::Begin => d.start = time,
-                    HourField::End => d.end = time,
-                }
-                self.update_display_post_direct_edit(d.status, d, &settings)?;
+        if d.status.is_timed(settings) {
+            match field {
+                HourField::Begin => d.clock_in(time),
+                HourField::End => d.clock_out(time),
             }
-            _ => {}
+            let status = d.status;
+            self.update_display_post_direct_edit(status, d, settings)?;
         }
         Ok(())
     }
@@ -409,38 +663,370 @@ impl<'a> Navigator<'a> {
         d: FlexDay,
         settings: &Settings,
     ) -> Result<(), Error> {
-        self.days_off.update_days_off(old_status, d);
+        self.days_off.update_days_off(old_status, &d, settings);
         let week = self
             .current_month
             .update_day(d)
             .expect("Should find a week");
-        self.current_month.update_balance();
-        self.current_month.save();
+        self.current_month.update_balance(settings);
+        self.current_month.save()?;
+        self.ledger.update(&self.current_month);
+        self.compliance.update(&self.current_month);
         self.days_off.save()?;
-        self.curses
-            .print_status(&settings, &self.current_month, &self.days_off);
-        self.curses
-            .print_week_total(&week, week.total_minutes() < settings.week_goal);
+        self.print_status(settings);
+        let goal = self.current_month.week_goal_for_day(self.current_day);
+        let violations = self.week_violations(&week);
+        self.curses.print_week_total(
+            &week,
+            week.total_minutes(settings) < goal,
+            &violations,
+            settings,
+        );
         Ok(())
     }
 
-    fn store_undo(&mut self, day: FlexDay, settings: &Settings) {
-        while self.undo_days.len() >= settings.max_undo {
-            self.undo_days.remove(0);
+    fn export_month(&self, settings: &Settings) {
+        let (year, month) = (self.current_month.year, self.current_month.month);
+        let msg = match export::export_month(year, month, settings) {
+            Ok(path) => format!("Exported to {}", path),
+            Err(e) => format!("Export failed: {}", e),
+        };
+        self.curses.print_message(&msg);
+    }
+
+    // project from the list of the settings, Some(None) to remove it, None if cancelled.
+    // A new project typed in is added to the settings
+    fn choose_project(
+        &mut self,
+        current: Option<&str>,
+        settings: &mut Settings,
+    ) -> Result<Option<Option<String>>, Error> {
+        let mut items = vec!["(no project)".to_string()];
+        items.extend(settings.projects.iter().cloned());
+        items.push("New project...".to_string());
+        let selected = current
+            .and_then(|c| settings.projects.iter().position(|p| p == c))
+            .map_or(0, |idx| idx + 1);
+        let choice = match self.curses.pick(" Project ", &items, selected) {
+            Some(choice) => choice,
+            None => return Ok(None),
+        };
+        if choice == 0 {
+            return Ok(Some(None));
+        }
+        if let Some(project) = settings.projects.get(choice - 1) {
+            return Ok(Some(Some(project.clone())));
         }
-        self.undo_days.push(day);
+        match self.curses.read_line(" New project ", "") {
+            Some(name) if !name.trim().is_empty() => {
+                let name = name.trim().to_string();
+                if !settings.projects.contains(&name) {
+                    settings.projects.push(name.clone());
+                    settings.save()?;
+                }
+                Ok(Some(Some(name)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // tag the last session of the selected day, the one being worked
+    fn tag_day(&mut self, settings: &mut Settings) -> Result<(), Error> {
+        let old = self.get_current_day().clone();
+        if !old.status.is_timed(settings) {
+            return Ok(());
+        }
+        let last = old.sessions.len() - 1;
+        let current = old.sessions[last].project.clone();
+        if let Some(project) = self.choose_project(current.as_deref(), settings)? {
+            let mut d = old.clone();
+            d.sessions[last].project = project;
+            self.update_display_post_direct_edit(d.status, d.clone(), settings)?;
+            self.record_edit(old, d)?;
+        }
+        self.init(settings)
+    }
+
+    // time of each project in the week, month and year of the selected day
+    fn show_projects(&mut self, settings: &Settings) -> Result<(), Error> {
+        let date = self.current_day;
+        let m = &self.current_month;
+        let (_, week, _) = m
+            .get_week_with_day(date)
+            .expect("selected day should be in the month");
+        let mut lines = vec![format!("Week {}", date.iso_week().week())];
+        lines.extend(week.projects(settings).lines());
+        lines.push(String::new());
+        lines.push(format!("{} {}", month_to_string(m.month), m.year));
+        lines.extend(m.projects(settings).lines());
+        lines.push(String::new());
+        lines.push(date.year().to_string());
+        lines.extend(ProjectReport::year(date.year(), settings)?.lines());
+        self.curses.browse_lines("Projects", &lines);
+        self.init(settings)
     }
 
-    fn undo(&mut self, settings: &Settings) -> Result<(), Error> {
-        if !self.undo_days.is_empty() {
-            let day_to_restore = self.undo_days.pop().unwrap();
-            self.select_day(
-                day_to_restore.date.expect("undo day should have a date"),
-                &settings,
-            );
-            let old_status = self.get_current_day().status;
-            self.update_display_post_direct_edit(old_status, day_to_restore, &settings)?;
+    // year overview, Enter on a day shows its week
+    fn show_year(&mut self, settings: &Settings) -> Result<(), Error> {
+        if let Some(date) = yearview::browse_year(&self.curses, self.current_day, settings)? {
+            // may be months away, select_day only steps to the next month
+            let (year, month) = grid_month_for(date);
+            self.current_month = FlexMonth::load(year, month, settings)?;
+            self.current_day = date;
+        }
+        self.init(settings)
+    }
+
+    // rules broken during the displayed month
+    fn show_compliance(&mut self, settings: &Settings) -> Result<(), Error> {
+        let m = &self.current_month;
+        let first = m.weeks[0].days[0].date;
+        let last = m.weeks[m.weeks.len() - 1].days[6].date;
+        let mut lines: Vec<String> = match (first, last) {
+            (Some(first), Some(last)) => self
+                .compliance
+                .violations(first, last)
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+        if lines.is_empty() {
+            lines.push("No working time rule broken".to_string());
+        }
+        let title = format!("Compliance {} {}", month_to_string(m.month), m.year);
+        self.curses.browse_lines(&title, &lines);
+        self.init(settings)
+    }
+
+    // type the note of the selected day, an empty one removes it
+    fn edit_note(&mut self, settings: &Settings) -> Result<(), Error> {
+        let old = self.get_current_day().clone();
+        let title = format!(" Note of {} ", self.current_day.format("%a %d/%m/%Y"));
+        let text = self
+            .curses
+            .read_line(&title, old.note.as_deref().unwrap_or(""));
+        if let Some(text) = text {
+            let mut d = old.clone();
+            d.set_note(&text);
+            self.update_display_post_direct_edit(d.status, d.clone(), settings)?;
+            self.record_edit(old, d)?;
+        }
+        self.init(settings)
+    }
+
+    // select the next day after the current one whose note has the typed text
+    fn search_note(&mut self, settings: &Settings) -> Result<(), Error> {
+        let text = match self.curses.read_line(" Search notes ", "") {
+            Some(text) if !text.trim().is_empty() => text,
+            _ => return self.init(settings),
+        };
+        let dates: Vec<NaiveDate> = storage()
+            .search_notes(text.trim())?
+            .into_iter()
+            .filter_map(|d| d.date)
+            .collect();
+        // wrap around to the first one
+        let found = dates
+            .iter()
+            .find(|&&date| date > self.current_day)
+            .or_else(|| dates.first());
+        match found {
+            Some(&date) => {
+                // a weekend on the 1st is in the grid of the month before
+                let (year, month) = grid_month_for(date);
+                self.current_month = FlexMonth::load(year, month, settings)?;
+                self.current_day = date;
+                self.init(settings)
+            }
+            None => {
+                self.init(settings)?;
+                self.curses
+                    .print_message(&format!("No note with \"{}\"", text.trim()));
+                Ok(())
+            }
+        }
+    }
+
+    /// tell how many past days were never clocked, they are fixed with `f`
+    pub fn warn_unclocked(&self, settings: &Settings) -> Result<(), Error> {
+        let count = storage()
+            .unclocked_days(Local::now().date_naive(), settings)?
+            .len();
+        if count > 0 {
+            self.curses.print_message(&format!(
+                "{} past day{} not clocked, press f to fix",
+                count,
+                if count > 1 { "s" } else { "" }
+            ));
         }
         Ok(())
     }
+
+    // step through the past days never clocked, oldest first, to edit them or keep their times
+    fn fix_unclocked(&mut self, settings: &mut Settings) -> Result<(), Error> {
+        let days = storage().unclocked_days(Local::now().date_naive(), settings)?;
+        if days.is_empty() {
+            self.curses.print_message("Every past day is clocked");
+            return Ok(());
+        }
+        for (idx, day) in days.iter().enumerate() {
+            let date = day.date.expect("saved day should have a date");
+            // may be months away, select_day only steps to the next month
+            let (year, month) = grid_month_for(date);
+            self.current_month = FlexMonth::load(year, month, settings)?;
+            self.current_day = date;
+            self.init(settings)?;
+            self.curses.print_message(&format!(
+                "Not clocked {}/{}  Enter: edit  k: keep times  Space: skip  Esc: stop",
+                idx + 1,
+                days.len()
+            ));
+            let old = self.get_current_day().clone();
+            let mut d = match self.curses.wait_key() {
+                Some(Input::Character('\n')) => {
+                    self.edit_day(settings)?;
+                    // Home in edit mode may have left the day
+                    self.select_day(date, settings)?;
+                    self.get_current_day().clone()
+                }
+                Some(Input::Character('k')) => old.clone(),
+                Some(Input::Character('\x1B')) | Some(Input::Character('q')) => break,
+                _ => continue,
+            };
+            // reviewed, the times are right now
+            d.start_recorded = true;
+            d.end_recorded = true;
+            self.update_display_post_direct_edit(d.status, d.clone(), settings)?;
+            self.record_edit(old, d)?;
+        }
+        self.init(settings)?;
+        self.warn_unclocked(settings)
+    }
+
+    fn record_edit(&mut self, old: FlexDay, new: FlexDay) -> Result<(), Error> {
+        let now = Local::now().naive_local();
+        self.journal.record(Action::Edit, old, new, now)?;
+        Ok(())
+    }
+
+    // undo or redo the last edit, days off follow the status put back
+    fn walk_journal(&mut self, action: Action, settings: &Settings) -> Result<(), Error> {
+        let day = match action {
+            Action::Redo => self.journal.to_redo(),
+            _ => self.journal.to_undo(),
+        };
+        if let Some(day) = day.cloned() {
+            self.select_day(day.date.expect("journal day should have a date"), settings)?;
+            let current = self.get_current_day().clone();
+            self.update_display_post_direct_edit(current.status, day.clone(), settings)?;
+            self.journal
+                .record(action, current, day, Local::now().naive_local())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curses::backend::Window;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    // draws nowhere
+    struct NullWindow {
+        lines: i32,
+        cols: i32,
+    }
+
+    impl Window for NullWindow {
+        fn mv(&self, _y: i32, _x: i32) {}
+        fn printw(&self, _s: &str) {}
+        fn attron(&self, _attr: Attr) {}
+        fn attroff(&self, _attr: Attr) {}
+        fn clrtoeol(&self) {}
+        fn clear(&self) {}
+        fn refresh(&self) {}
+        fn get_max_x(&self) -> i32 {
+            self.cols
+        }
+        fn get_max_y(&self) -> i32 {
+            self.lines
+        }
+        fn draw_box(&self) {}
+        fn border(&self, _chars: [char; 8]) {}
+        fn subwin(&self, lines: i32, cols: i32, _y: i32, _x: i32) -> Option<Box<dyn Window>> {
+            Some(Box::new(NullWindow { lines, cols }))
+        }
+        fn derwin(&self, lines: i32, cols: i32, _y: i32, _x: i32) -> Option<Box<dyn Window>> {
+            Some(Box::new(NullWindow { lines, cols }))
+        }
+    }
+
+    // hands out `keys` one by one, then none
+    struct NullBackend {
+        screen: NullWindow,
+        keys: RefCell<VecDeque<Input>>,
+    }
+
+    impl NullBackend {
+        fn new(keys: &str) -> NullBackend {
+            NullBackend {
+                screen: NullWindow {
+                    lines: 15,
+                    cols: 76,
+                },
+                keys: RefCell::new(keys.chars().map(Input::Character).collect()),
+            }
+        }
+    }
+
+    impl Backend for NullBackend {
+        fn screen(&self) -> &dyn Window {
+            &self.screen
+        }
+        fn getch(&self, _timeout: Option<std::time::Duration>) -> Option<Input> {
+            self.keys.borrow_mut().pop_front()
+        }
+        fn end(&self) {}
+    }
+
+    #[test]
+    fn undo_across_years_test() {
+        crate::datadir::test_data_dir("undo_across_years_test");
+        let settings = Settings::default();
+        let backend = NullBackend::new("");
+        let december = NaiveDate::from_ymd_opt(2019, 12, 10).unwrap();
+        let mut nav = Navigator::new(december, &backend, &settings).unwrap();
+        nav.init(&settings).unwrap();
+        nav.change_status('s', &settings).unwrap();
+        assert_eq!(nav.get_current_day().status, DayStatus::Sick);
+
+        nav.select_day(NaiveDate::from_ymd_opt(2020, 1, 15).unwrap(), &settings)
+            .unwrap();
+        nav.walk_journal(Action::Undo, &settings).unwrap();
+        assert_eq!(nav.current_day, december);
+        assert_eq!(nav.get_current_day().status, DayStatus::Worked);
+    }
+
+    #[test]
+    fn search_weekend_note_test() {
+        crate::datadir::test_data_dir("search_weekend_note_test");
+        let settings = Settings::default();
+        // on-call Saturday, in the grid of March
+        let saturday = NaiveDate::from_ymd_opt(2017, 4, 1).unwrap();
+        let mut march = FlexMonth::new(2017, 3, &settings);
+        let mut day = march.get_week_with_day(saturday).unwrap().0.clone();
+        day.set_note("on-call");
+        march.update_day(day);
+        storage().save_month(&march).unwrap();
+
+        let backend = NullBackend::new("on-call\n");
+        let start = NaiveDate::from_ymd_opt(2017, 2, 14).unwrap();
+        let mut nav = Navigator::new(start, &backend, &settings).unwrap();
+        nav.search_note(&settings).unwrap();
+        assert_eq!(nav.current_day, saturday);
+        assert_eq!(nav.get_current_day().note, Some("on-call".to_string()));
+    }
 }
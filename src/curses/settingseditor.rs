@@ -1,21 +1,22 @@
+use super::backend::Input;
 use super::editor;
 use super::editor::TimeField;
 use super::Curses;
 use crate::settings::Settings;
 use crate::timedata::*;
+use chrono::Local;
 use failure::Error;
-use pancurses::Input;
 
 pub fn edit_settings<'a>(
-  mut curses: &mut Curses<'a>,
-  mut settings: &mut Settings,
-  mut days_off: &mut DaysOff,
+  curses: &mut Curses<'a>,
+  settings: &mut Settings,
+  days_off: &mut DaysOff,
 ) -> Result<(), Error> {
-  curses.open_settings(&settings, &days_off);
+  curses.open_settings(settings, days_off);
   let mut cur_idx = 0;
   let mut cur_field = 0;
   let mut done = false;
-  select_option(&mut curses, cur_idx, cur_field, &settings, &days_off);
+  select_option(curses, cur_idx, cur_field, settings, days_off);
   let mut digit_idx = 0;
   while !done {
     match curses.getch() {
@@ -41,7 +42,7 @@ pub fn edit_settings<'a>(
             } else {
               cur_idx = (cur_idx + 1) % 3;
             }
-            select_option(&mut curses, cur_idx, cur_field, &settings, &days_off)
+            select_option(curses, cur_idx, cur_field, settings, days_off)
           }
           Input::KeyLeft => {
             digit_idx = 0;
@@ -53,49 +54,41 @@ pub fn edit_settings<'a>(
                 cur_idx = 2;
               }
             }
-            select_option(&mut curses, cur_idx, cur_field, &settings, &days_off)
+            select_option(curses, cur_idx, cur_field, settings, days_off)
           }
           Input::KeyRight => {
             digit_idx = 0;
             cur_field = (cur_field + 1) % 7;
-            if cur_field > 5 {
-              if cur_idx > 2 {
-                cur_idx = 2;
-              }
+            if cur_field > 5 && cur_idx > 2 {
+              cur_idx = 2;
             }
-            select_option(&mut curses, cur_idx, cur_field, &settings, &days_off)
+            select_option(curses, cur_idx, cur_field, settings, days_off)
           }
-          Input::Character(c) if c >= '0' && c <= '9' => {
+          Input::Character(c) if c.is_ascii_digit() => {
             manage_option_edition(
               cur_idx,
               cur_field,
               c,
               digit_idx,
-              &mut settings,
-              &mut days_off,
+              settings,
+              days_off,
             );
             digit_idx = (digit_idx + 1) % 2;
-            select_option(&mut curses, cur_idx, cur_field, &settings, &days_off)
+            select_option(curses, cur_idx, cur_field, settings, days_off)
           }
           _ => {}
         };
         cur_field = if cur_idx == 5 {
-          if cur_field < 4 {
-            4
-          } else if cur_field > 5 {
-            5
-          } else {
-            cur_field
-          }
+          cur_field.clamp(4, 5)
         } else {
           cur_field
         };
-        select_option(&mut curses, cur_idx, cur_field, &settings, &days_off)
+        select_option(curses, cur_idx, cur_field, settings, days_off)
       }
       None => {}
     }
   }
-  settings.save();
+  settings.save()?;
   days_off.save()?;
   curses.close_setting();
   // self.init(&settings);
@@ -109,7 +102,7 @@ fn select_option<'a>(
   settings: &Settings,
   days_off: &DaysOff,
 ) {
-  curses.highlight_option(cur_idx, cur_field, &settings, &days_off)
+  curses.highlight_option(cur_idx, cur_field, settings, days_off)
 }
 
 fn manage_option_edition(
@@ -120,10 +113,13 @@ fn manage_option_edition(
   settings: &mut Settings,
   days_off: &mut DaysOff,
 ) {
+  // the schedule in force today is the one edited
+  let today = Local::now().date_naive();
   if cur_idx < 5 {
     match cur_field {
       sched_field if sched_field <= 5 => {
-        let mut d = settings.week_sched.sched[cur_idx as usize];
+        let (week_sched, _) = settings.schedule_for_mut(&today);
+        let mut d = week_sched.sched[cur_idx as usize];
         match sched_field {
           0 => {
             d.start = editor::process_digit_input_for_time(d.start, TimeField::Hour, c, digit_idx)
@@ -143,41 +139,31 @@ fn manage_option_edition(
           }
           _ => unreachable!(),
         };
-        settings.week_sched.sched[cur_idx as usize] = d;
+        week_sched.sched[cur_idx as usize] = d;
       }
-      6 => match cur_idx {
-        0 => {
+      // holidays left and sick days are computed from the days taken
+      6 => {
+        if cur_idx == 0 {
           settings.holidays_per_year =
             editor::process_digit_input_for_number(settings.holidays_per_year, c, digit_idx);
+          days_off.refresh(settings);
         }
-        1 => {
-          days_off.holidays_left =
-            editor::process_digit_input_for_number(days_off.holidays_left, c, digit_idx);
-        }
-        _ => unreachable!(),
-      },
+      }
       _ => unreachable!(),
     }
   } else {
+    let (_, week_goal) = settings.schedule_for_mut(&today);
     match cur_field {
       4 => {
-        settings.week_goal = editor::process_digit_input_for_duration(
-          settings.week_goal,
-          TimeField::Hour,
-          c,
-          digit_idx,
-        )
+        *week_goal =
+          editor::process_digit_input_for_duration(*week_goal, TimeField::Hour, c, digit_idx)
       }
       5 => {
-        settings.week_goal = editor::process_digit_input_for_duration(
-          settings.week_goal,
-          TimeField::Minute,
-          c,
-          digit_idx,
-        )
+        *week_goal =
+          editor::process_digit_input_for_duration(*week_goal, TimeField::Minute, c, digit_idx)
       }
       _ => unreachable!(),
     }
-    settings.holiday_duration = settings.week_goal / 5;
+    settings.holiday_duration = settings.week_goal_for(&today) / 5;
   }
 }
@@ -0,0 +1,232 @@
+use super::backend::{Attr, Input};
+use super::{color_attr, Curses};
+use crate::settings::Settings;
+use crate::timedata::*;
+use chrono::{Datelike, Local, NaiveDate};
+use failure::Error;
+use std::collections::BTreeMap;
+
+// one calendar month of the year view
+pub struct MonthRow {
+    pub month: u32,
+    // one cell per day of the month
+    pub cells: Vec<(char, Option<Attr>)>,
+    pub total: i64,
+    pub balance: i64,
+}
+
+// grid months of `year` and the ones around it holding its first and last days
+struct Year {
+    year: i32,
+    months: BTreeMap<(i32, u32), FlexMonth>,
+}
+
+impl Year {
+    // months never saved get the public holidays in memory only, looking never writes
+    fn load(year: i32, settings: &Settings) -> Result<Year, Error> {
+        let calendar = HolidayCalendar::load(settings)?;
+        let mut months = BTreeMap::new();
+        let (mut y, mut m) = prev_month(year, 1);
+        while (y, m) <= (year + 1, 1) {
+            let (mut month, saved) = FlexMonth::load_with_flag(y, m, settings)?;
+            if !saved {
+                month.apply_calendar(&calendar, settings);
+            }
+            months.insert((y, m), month);
+            let (next_y, next_m) = next_month(y, m);
+            y = next_y;
+            m = next_m;
+        }
+        Ok(Year { year, months })
+    }
+
+    fn day(&self, date: NaiveDate) -> Option<&FlexDay> {
+        self.months
+            .get(&grid_month_for(date))
+            .and_then(|m| m.get_week_with_day(date))
+            .map(|(d, _, _)| d)
+    }
+
+    fn rows(&self, today: NaiveDate, settings: &Settings) -> Vec<MonthRow> {
+        (1..=12)
+            .map(|month| {
+                let m = &self.months[&(self.year, month)];
+                let cells = month_days(self.year, month)
+                    .map(|date| match self.day(date) {
+                        Some(d) => cell(d, today, settings),
+                        None => (' ', None),
+                    })
+                    .collect();
+                MonthRow {
+                    month,
+                    cells,
+                    total: m.total_minute(settings),
+                    balance: m.balance,
+                }
+            })
+            .collect()
+    }
+
+    // date, type, times and over/under-time of the selected day
+    fn details(&self, date: NaiveDate, settings: &Settings) -> Vec<String> {
+        let mut lines = vec![date.format("%a %d/%m/%Y").to_string()];
+        if let Some(d) = self.day(date) {
+            lines.push(day_type(settings, d.status).name);
+            if d.status.is_timed(settings) {
+                let diff = d.total_minutes(settings) - settings.target_minutes_for(&date);
+                lines.push(format!(
+                    "{} -> {}",
+                    d.start().format("%H:%M"),
+                    d.end().format("%H:%M")
+                ));
+                lines.push(format!(
+                    "Total {:>8}",
+                    minutes_to_string(d.total_minutes(settings))
+                ));
+                lines.push(format!(
+                    "Diff {:>9}",
+                    format!(
+                        "{}{}",
+                        if diff > 0 { "+" } else { "" },
+                        minutes_to_string(diff)
+                    )
+                ));
+            }
+        }
+        lines
+    }
+}
+
+fn month_days(year: i32, month: u32) -> impl Iterator<Item = NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    let (next_y, next_m) = next_month(year, month);
+    NaiveDateIter::new(
+        first,
+        NaiveDate::from_ymd_opt(next_y, next_m, 1)
+            .unwrap()
+            .pred_opt()
+            .unwrap(),
+    )
+}
+
+// over/under-time of the past worked days, code and colour of the other types
+fn cell(d: &FlexDay, today: NaiveDate, settings: &Settings) -> (char, Option<Attr>) {
+    let date = d.date.expect("day should have a date");
+    match d.status {
+        DayStatus::Weekend => (' ', None),
+        status if !status.is_timed(settings) => {
+            (status.code(), color_attr(day_type(settings, status).color))
+        }
+        _ if date > today => ('.', Some(Attr::Dim)),
+        _ if d.total_minutes(settings) < 0 => ('!', Some(Attr::Alert)),
+        _ => match d.total_minutes(settings) - settings.target_minutes_for(&date) {
+            diff if diff > 0 => ('+', Some(Attr::Green)),
+            diff if diff < 0 => ('-', Some(Attr::Red)),
+            _ => ('=', None),
+        },
+    }
+}
+
+// same day in the month `months` away, the last one of shorter months
+fn add_months(date: NaiveDate, months: i32) -> NaiveDate {
+    let index = date.year() * 12 + date.month0() as i32 + months;
+    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+    let last = month_days(year, month)
+        .last()
+        .expect("month should have days");
+    last.with_day(date.day()).unwrap_or(last)
+}
+
+// twelve months of the year of `date`, return the day chosen to go to its week
+pub fn browse_year(
+    curses: &Curses,
+    date: NaiveDate,
+    settings: &Settings,
+) -> Result<Option<NaiveDate>, Error> {
+    let today = Local::now().date_naive();
+    let mut selected = date;
+    let mut year = Year::load(date.year(), settings)?;
+    loop {
+        if selected.year() != year.year {
+            year = Year::load(selected.year(), settings)?;
+        }
+        curses.print_year(
+            year.year,
+            &year.rows(today, settings),
+            selected,
+            &year.details(selected, settings),
+        );
+        selected = match curses.wait_key() {
+            Some(Input::KeyLeft) => selected.pred_opt().unwrap(),
+            Some(Input::KeyRight) => selected.succ_opt().unwrap(),
+            Some(Input::KeyUp) => add_months(selected, -1),
+            Some(Input::KeyDown) => add_months(selected, 1),
+            Some(Input::KeyPPage) => add_months(selected, -12),
+            Some(Input::KeyNPage) => add_months(selected, 12),
+            Some(Input::KeyHome) => today,
+            Some(Input::Character('\n')) => return Ok(Some(selected)),
+            Some(Input::Character('\x1B')) | Some(Input::Character('q')) => return Ok(None),
+            _ => selected,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_months_test() {
+        let date = NaiveDate::from_ymd_opt(2019, 1, 31).unwrap();
+        assert_eq!(
+            add_months(date, 1),
+            NaiveDate::from_ymd_opt(2019, 2, 28).unwrap()
+        );
+        assert_eq!(
+            add_months(date, -1),
+            NaiveDate::from_ymd_opt(2018, 12, 31).unwrap()
+        );
+        assert_eq!(
+            add_months(date, 13),
+            NaiveDate::from_ymd_opt(2020, 2, 29).unwrap()
+        );
+    }
+
+    #[test]
+    fn cell_test() {
+        let settings = Settings::default();
+        let month = FlexMonth::new(2017, 5, &settings);
+        let today = NaiveDate::from_ymd_opt(2017, 5, 10).unwrap();
+        let (d, _, _) = month
+            .get_week_with_day(NaiveDate::from_ymd_opt(2017, 5, 2).unwrap())
+            .unwrap();
+        assert_eq!(cell(d, today, &settings), ('=', None));
+        let mut longer = d.clone();
+        longer.clock_out(chrono::NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+        assert_eq!(cell(&longer, today, &settings), ('+', Some(Attr::Green)));
+        let mut holiday = d.clone();
+        holiday.status = DayStatus::Holiday;
+        assert_eq!(cell(&holiday, today, &settings).0, 'H');
+        let (later, _, _) = month.get_week_with_day(today.succ_opt().unwrap()).unwrap();
+        assert_eq!(cell(later, today, &settings), ('.', Some(Attr::Dim)));
+    }
+
+    #[test]
+    fn load_test() {
+        crate::datadir::test_data_dir("load_test");
+        let mut settings = Settings::default();
+        settings.public_holidays.country = "fr".to_string();
+        let year = Year::load(1995, &settings).unwrap();
+        let labour_day = NaiveDate::from_ymd_opt(1995, 5, 1).unwrap();
+        assert_eq!(
+            year.day(labour_day).unwrap().status,
+            DayStatus::PublicHoliday
+        );
+        for month in 1..=12 {
+            assert!(crate::storage::storage()
+                .load_month(1995, month)
+                .unwrap()
+                .is_none());
+        }
+    }
+}
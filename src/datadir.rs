@@ -0,0 +1,290 @@
+use glob::Pattern;
+#[cfg(test)]
+use std::cell::Cell;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const ENV_VAR: &str = "RUSTY_FLEXI_DATA_DIR";
+const APP_NAME: &str = "rusty_flexi";
+// where data lived before the location was configurable
+const LEGACY_DIR: &str = "./data";
+
+static DATA_DIR: OnceLock<DataDir> = OnceLock::new();
+
+/// A file of the JSON layout, also used to exchange data between storages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFile {
+    Settings,
+    Month(i32, u32),
+    DaysOff(i32),
+    SickDays,
+    // written by hand, see `HolidayCalendar`
+    Closures,
+    // one JSON entry per line, see `Journal`
+    Journal,
+}
+
+impl DataFile {
+    pub fn name(&self) -> String {
+        match self {
+            DataFile::Settings => "settings.json".to_string(),
+            DataFile::Month(year, month) => format!("{}_{:02}.json", year, month),
+            DataFile::DaysOff(year) => format!("{}_daysoff.json", year),
+            DataFile::SickDays => "sickdays.json".to_string(),
+            DataFile::Closures => "closures.json".to_string(),
+            DataFile::Journal => "journal.jsonl".to_string(),
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<DataFile> {
+        match name {
+            "settings.json" => return Some(DataFile::Settings),
+            "sickdays.json" => return Some(DataFile::SickDays),
+            "closures.json" => return Some(DataFile::Closures),
+            "journal.jsonl" => return Some(DataFile::Journal),
+            _ => {}
+        }
+        let stem = name.strip_suffix(".json")?;
+        let year = stem.get(0..4)?.parse().ok()?;
+        match stem.get(4..)? {
+            "_daysoff" => Some(DataFile::DaysOff(year)),
+            month if month.len() == 3 && month.starts_with('_') => match month[1..].parse() {
+                Ok(month) if (1..=12).contains(&month) => Some(DataFile::Month(year, month)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Location of every data file
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    pub fn new<P: Into<PathBuf>>(root: P) -> DataDir {
+        DataDir { root: root.into() }
+    }
+
+    /// `--data-dir` flag first, then the environment variable, then the platform data dir
+    pub fn resolve(flag: Option<&str>) -> DataDir {
+        DataDir::new(resolve_root(
+            flag,
+            env::var_os(ENV_VAR),
+            platform_dir(),
+            Path::new(LEGACY_DIR).join("settings.json").exists(),
+        ))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn create(&self) -> io::Result<()> {
+        if !self.root.exists() {
+            fs::create_dir_all(&self.root)?;
+        }
+        Ok(())
+    }
+
+    pub fn file(&self, file: DataFile) -> String {
+        self.root.join(file.name()).to_string_lossy().into_owned()
+    }
+
+    pub fn settings(&self) -> String {
+        self.file(DataFile::Settings)
+    }
+
+    pub fn month(&self, year: i32, month: u32) -> String {
+        self.file(DataFile::Month(year, month))
+    }
+
+    /// glob pattern matching every month file
+    pub fn months_pattern(&self) -> String {
+        let root = Pattern::escape(&self.root.to_string_lossy());
+        Path::new(&root)
+            .join("[0-9][0-9][0-9][0-9]_[0-9][0-9].json")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn days_off(&self, year: i32) -> String {
+        self.file(DataFile::DaysOff(year))
+    }
+
+    pub fn sick_days(&self) -> String {
+        self.file(DataFile::SickDays)
+    }
+
+    pub fn closures(&self) -> String {
+        self.file(DataFile::Closures)
+    }
+
+    pub fn journal(&self) -> String {
+        self.file(DataFile::Journal)
+    }
+
+    /// SQLite database, data is stored there instead of the JSON files when it exists
+    pub fn database(&self) -> String {
+        self.root
+            .join("rusty_flexi.db")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn export_dir(&self) -> PathBuf {
+        self.root.join("export")
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        self.root.join("backups")
+    }
+
+    pub fn backup(&self, name: &str) -> String {
+        self.backup_dir()
+            .join(format!("{}.json", name))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// set the data dir used by the whole program, must be called before any file access
+pub fn init(dir: DataDir) {
+    let _ = DATA_DIR.set(dir);
+}
+
+#[cfg(not(test))]
+pub fn data_dir() -> &'static DataDir {
+    DATA_DIR.get_or_init(|| DataDir::resolve(None))
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_DATA_DIR: Cell<Option<&'static DataDir>> = const { Cell::new(None) };
+}
+
+// each test has a data dir of its own, see `test_data_dir`
+#[cfg(test)]
+pub fn data_dir() -> &'static DataDir {
+    TEST_DATA_DIR
+        .with(|dir| dir.get())
+        .expect("test should call datadir::test_data_dir first")
+}
+
+/// use an empty data dir of its own for the rest of the test `test`, leaving the data of the
+/// other tests and of the working tree alone
+#[cfg(test)]
+pub fn test_data_dir(test: &str) -> &'static DataDir {
+    let root = env::temp_dir().join(format!("rusty_flexi_{}_{}_data", std::process::id(), test));
+    let _ = fs::remove_dir_all(&root);
+    let dir: &'static DataDir = Box::leak(Box::new(DataDir::new(root)));
+    dir.create().unwrap();
+    TEST_DATA_DIR.with(|d| d.set(Some(dir)));
+    dir
+}
+
+fn resolve_root(
+    flag: Option<&str>,
+    env_dir: Option<OsString>,
+    platform_dir: Option<PathBuf>,
+    legacy_exists: bool,
+) -> PathBuf {
+    if let Some(dir) = flag {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = env_dir.filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    match platform_dir {
+        // keep using `./data` until it is moved to the platform dir
+        Some(ref dir) if legacy_exists && !dir.exists() => PathBuf::from(LEGACY_DIR),
+        Some(dir) => dir,
+        None => PathBuf::from(LEGACY_DIR),
+    }
+}
+
+#[cfg(windows)]
+fn platform_dir() -> Option<PathBuf> {
+    env::var_os("APPDATA").map(|d| PathBuf::from(d).join(APP_NAME))
+}
+
+#[cfg(not(windows))]
+fn platform_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|d| d.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+        .map(|d| d.join(APP_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_root_test() {
+        let platform = Some(PathBuf::from("/nonexistent/share/rusty_flexi"));
+        let env_dir = Some(OsString::from("/from/env"));
+        assert_eq!(
+            resolve_root(Some("/from/flag"), env_dir.clone(), platform.clone(), true),
+            PathBuf::from("/from/flag")
+        );
+        assert_eq!(
+            resolve_root(None, env_dir, platform.clone(), true),
+            PathBuf::from("/from/env")
+        );
+        assert_eq!(
+            resolve_root(None, Some(OsString::new()), platform.clone(), false),
+            PathBuf::from("/nonexistent/share/rusty_flexi")
+        );
+        assert_eq!(
+            resolve_root(None, None, platform, true),
+            PathBuf::from(LEGACY_DIR)
+        );
+        assert_eq!(
+            resolve_root(None, None, None, false),
+            PathBuf::from(LEGACY_DIR)
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn filenames_test() {
+        let dir = DataDir::new("/home/me/flexi [work]");
+        assert_eq!(dir.month(2019, 3), "/home/me/flexi [work]/2019_03.json");
+        assert_eq!(
+            dir.days_off(2019),
+            "/home/me/flexi [work]/2019_daysoff.json"
+        );
+        assert_eq!(
+            dir.months_pattern(),
+            "/home/me/flexi [[]work[]]/[0-9][0-9][0-9][0-9]_[0-9][0-9].json"
+        );
+    }
+
+    #[test]
+    fn data_file_test() {
+        for file in [
+            DataFile::Settings,
+            DataFile::Month(2019, 3),
+            DataFile::Month(2019, 12),
+            DataFile::DaysOff(2019),
+            DataFile::SickDays,
+            DataFile::Closures,
+            DataFile::Journal,
+        ]
+        .iter()
+        {
+            assert_eq!(DataFile::parse(&file.name()), Some(*file));
+        }
+        assert_eq!(DataFile::parse("2019_13.json"), None);
+        assert_eq!(DataFile::parse("2019_03.json.last"), None);
+        assert_eq!(DataFile::parse("notes.json"), None);
+    }
+}
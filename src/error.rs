@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors of the data files and settings
+#[derive(Debug)]
+pub enum FlexiError {
+    Io {
+        path: String,
+        cause: io::Error,
+    },
+    /// file is not valid JSON, e.g. truncated
+    Json {
+        path: String,
+        cause: serde_json::Error,
+    },
+    /// invalid JSON and a last good copy exists, e.g. cut short by a crash while written
+    Damaged {
+        path: String,
+        cause: serde_json::Error,
+    },
+    /// valid JSON but not in the expected format
+    Schema {
+        path: String,
+        msg: String,
+    },
+    Database {
+        path: String,
+        cause: rusqlite::Error,
+    },
+    /// data loaded but inconsistent
+    Validation(String),
+}
+
+impl fmt::Display for FlexiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FlexiError::Io { path, cause } => write!(f, "{}: {}", path, cause),
+            FlexiError::Json { path, cause } => write!(f, "{}: invalid JSON: {}", path, cause),
+            FlexiError::Damaged { path, cause } => write!(
+                f,
+                "{} is damaged ({}), start rusty_flexi without arguments to restore its last good copy",
+                path, cause
+            ),
+            FlexiError::Schema { path, msg } => write!(f, "{}: unexpected format: {}", path, msg),
+            FlexiError::Database { path, cause } => write!(f, "{}: {}", path, cause),
+            FlexiError::Validation(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+// failure::Fail comes with std::error::Error
+impl Error for FlexiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FlexiError::Io { cause, .. } => Some(cause),
+            FlexiError::Json { cause, .. } | FlexiError::Damaged { cause, .. } => Some(cause),
+            FlexiError::Database { cause, .. } => Some(cause),
+            _ => None,
+        }
+    }
+}
+
+impl FlexiError {
+    pub fn io(path: &str, cause: io::Error) -> FlexiError {
+        FlexiError::Io {
+            path: path.to_string(),
+            cause,
+        }
+    }
+
+    pub fn database(path: &str, cause: rusqlite::Error) -> FlexiError {
+        FlexiError::Database {
+            path: path.to_string(),
+            cause,
+        }
+    }
+
+    /// syntax errors are JSON errors, missing or wrongly typed fields are schema errors
+    pub fn json(path: &str, cause: serde_json::Error) -> FlexiError {
+        match cause.classify() {
+            serde_json::error::Category::Data => FlexiError::Schema {
+                path: path.to_string(),
+                msg: cause.to_string(),
+            },
+            _ => FlexiError::Json {
+                path: path.to_string(),
+                cause,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_error_test() {
+        let truncated = serde_json::from_str::<Vec<i32>>("[1, 2").unwrap_err();
+        match FlexiError::json("a.json", truncated) {
+            FlexiError::Json { .. } => {}
+            e => panic!("should be a JSON error: {}", e),
+        }
+        let wrong_type = serde_json::from_str::<Vec<i32>>("[\"1\"]").unwrap_err();
+        match FlexiError::json("a.json", wrong_type) {
+            FlexiError::Schema { ref path, .. } => assert_eq!(path, "a.json"),
+            e => panic!("should be a schema error: {}", e),
+        }
+    }
+}
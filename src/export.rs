@@ -0,0 +1,181 @@
+use crate::datadir::data_dir;
+use crate::settings::Settings;
+use crate::timedata::*;
+use chrono::{Datelike, NaiveDate, Weekday};
+use failure::{format_err, Error};
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+
+const HEADER: &str = "date,weekday,status,start,end,pause,worked,delta,note";
+
+// quote a CSV field if needed
+fn field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|f| field(f))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[derive(Default)]
+struct Subtotal {
+    worked: i64,
+    delta: i64,
+}
+
+impl Subtotal {
+    fn add(&mut self, worked: i64, delta: i64) {
+        self.worked += worked;
+        self.delta += delta;
+    }
+
+    fn line(&self, label: String) -> String {
+        line(&[
+            label,
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            self.worked.to_string(),
+            self.delta.to_string(),
+            String::new(),
+        ])
+    }
+}
+
+fn day_line(d: &FlexDay, date: NaiveDate, target: i64, settings: &Settings) -> String {
+    let (start, end, pause) = if d.status.is_timed(settings) {
+        (
+            d.start().format("%H:%M").to_string(),
+            d.end().format("%H:%M").to_string(),
+            d.pause.to_string(),
+        )
+    } else {
+        (String::new(), String::new(), String::new())
+    };
+    line(&[
+        date.format("%Y-%m-%d").to_string(),
+        weekday_to_string(date.weekday()),
+        day_type(settings, d.status).name,
+        start,
+        end,
+        pause,
+        d.total_minutes(settings).to_string(),
+        (d.total_minutes(settings) - target).to_string(),
+        d.note.clone().unwrap_or_default(),
+    ])
+}
+
+/// Write a CSV timesheet of the days from `from` to `to`, one line per day with weekly and
+/// monthly subtotals. Minutes are used for pause, worked time and delta against the schedule.
+pub fn write_csv<W: Write>(
+    from: NaiveDate,
+    to: NaiveDate,
+    settings: &Settings,
+    out: &mut W,
+) -> Result<(), Error> {
+    writeln!(out, "{}", HEADER)?;
+    let mut month: Option<FlexMonth> = None;
+    let mut week = Subtotal::default();
+    let mut month_total = Subtotal::default();
+    let mut total = Subtotal::default();
+    for date in NaiveDateIter::new(from, to) {
+        let (y, m) = grid_month_for(date);
+        if month.as_ref().map(|cur| (cur.year, cur.month)) != Some((y, m)) {
+            month = Some(FlexMonth::load(y, m, settings)?);
+        }
+        let d = month
+            .as_ref()
+            .and_then(|cur| cur.get_week_with_day(date))
+            .map(|(d, _, _)| d)
+            .ok_or_else(|| format_err!("{} not found in month file", date))?;
+        let target = settings.target_minutes_for(&date);
+        writeln!(out, "{}", day_line(d, date, target, settings))?;
+        let worked = d.total_minutes(settings);
+        week.add(worked, worked - target);
+        month_total.add(worked, worked - target);
+        total.add(worked, worked - target);
+
+        if date.weekday() == Weekday::Sun || date == to {
+            writeln!(
+                out,
+                "{}",
+                week.line(format!("Week {}", date.iso_week().week()))
+            )?;
+            week = Subtotal::default();
+        }
+        if date.succ_opt().unwrap().month() != date.month() || date == to {
+            writeln!(
+                out,
+                "{}",
+                month_total.line(format!("{} {}", month_to_string(date.month()), date.year()))
+            )?;
+            month_total = Subtotal::default();
+        }
+    }
+    writeln!(out, "{}", total.line("Total".to_string()))?;
+    Ok(())
+}
+
+/// Export the given calendar month into the `export` folder of the data dir, return the path of the file
+pub fn export_month(year: i32, month: u32, settings: &Settings) -> Result<String, Error> {
+    let from = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    let (y, m) = next_month(year, month);
+    let to = NaiveDate::from_ymd_opt(y, m, 1)
+        .unwrap()
+        .pred_opt()
+        .unwrap();
+    let dir = data_dir().export_dir();
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+    }
+    let path = dir
+        .join(format!("timesheet_{}_{:02}.csv", year, month))
+        .to_string_lossy()
+        .into_owned();
+    let mut file = File::create(&path)?;
+    write_csv(from, to, settings, &mut file)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_test() {
+        assert_eq!(field("Holiday"), "Holiday");
+        assert_eq!(field("a, b"), "\"a, b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn day_line_test() {
+        let settings = Settings::default();
+        let date = NaiveDate::from_ymd_opt(2017, 5, 5).unwrap();
+        let d = FlexDay::new(date, &settings);
+        let target = settings.target_minutes_for(&date);
+        assert_eq!(
+            day_line(&d, date, target, &settings),
+            "2017-05-05,Fri,Worked,09:10,16:50,30,430,0,"
+        );
+        let mut d = d;
+        d.set_note("Dentist, left early");
+        assert_eq!(
+            day_line(&d, date, target, &settings),
+            "2017-05-05,Fri,Worked,09:10,16:50,30,430,0,\"Dentist, left early\""
+        );
+        let sunday = NaiveDate::from_ymd_opt(2017, 5, 7).unwrap();
+        assert_eq!(settings.target_minutes_for(&sunday), 0);
+    }
+}
@@ -1,36 +1,79 @@
 #![cfg_attr(not(test), windows_subsystem = "windows")]
 
+mod backup;
+mod cli;
 mod curses;
+mod datadir;
+mod error;
+mod export;
 mod savable;
 mod settings;
+mod storage;
 mod timedata;
 
 // use crate::curses::settingseditor;
+use crate::cli::Cli;
 use crate::curses::*;
+use crate::datadir::{data_dir, DataDir};
 use crate::settings::Settings;
-use crate::timedata::FlexMonth;
-use chrono::Datelike;
+use crate::storage::storage;
 use failure::Error;
-use pancurses::*;
 
-fn generate_xmas_holidays(year: i32, settings: &Settings) {
-    FlexMonth::load(year, 12, &settings);
-    FlexMonth::load(year, 01, &settings);
+fn main() {
+    // printed once the terminal is restored
+    if let Err(e) = run() {
+        attach_console();
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
 }
 
-fn main() -> Result<(), Error> {
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let flag = cli::take_data_dir(&mut args)?;
+    datadir::init(DataDir::resolve(flag.as_deref()));
     timedata::create_data_dir();
-    let window = initscr();
-    curses::init_window(&window);
-    let today = chrono::Local::today().naive_local();
-    let (mut settings, need_edit_settings) = if let Some(settings) = Settings::load() {
-        (settings, false)
-    } else {
-        (Settings::default(), true)
+    storage::init(storage::open(data_dir())?);
+    if let Some(cli) = Cli::parse(&args)? {
+        attach_console();
+        let settings = Settings::load()?.unwrap_or_default();
+        if let Err(e) = backup::daily(
+            storage(),
+            chrono::Local::now().naive_local(),
+            &settings.backups,
+        ) {
+            eprintln!("Backup failed: {}", e);
+        }
+        return cli.run(&settings);
+    }
+    // leave curses mode before the panic message is printed
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        curses::backend::restore();
+        default_hook(info);
+    }));
+    // terminal is restored when the backend is dropped
+    let backend = curses::backend::open()?;
+    let today = chrono::Local::now().date_naive();
+    let load = || -> Result<_, Error> {
+        let (settings, need_edit_settings) = match Settings::load()? {
+            Some(settings) => (settings, false),
+            None => (Settings::default(), true),
+        };
+        let navigator = Navigator::new(today, &*backend, &settings)?;
+        Ok((settings, need_edit_settings, navigator))
+    };
+    // load again after each damaged file restored
+    let (mut settings, need_edit_settings, mut navigator) = loop {
+        match load() {
+            Ok(loaded) => break loaded,
+            Err(e) => {
+                if !Curses::new(&*backend).offer_recovery(&e)? {
+                    return Err(e);
+                }
+            }
+        }
     };
-    let mut navigator = Navigator::new(today, &window, &settings);
-
-    generate_xmas_holidays(today.year(), &settings);
 
     if need_edit_settings {
         settingseditor::edit_settings(
@@ -39,8 +82,38 @@ fn main() -> Result<(), Error> {
             &mut navigator.days_off,
         )?;
     }
-    navigator.init(&settings);
-    navigator.main_loop(&window, &mut settings)?;
-    endwin();
+    navigator.init(&settings)?;
+    let now = chrono::Local::now().naive_local();
+    if let Err(e) = backup::daily(storage(), now, &settings.backups) {
+        navigator
+            .curses
+            .print_error(&format!("Backup failed: {}", e));
+        navigator.init(&settings)?;
+    }
+    navigator.warn_unclocked(&settings)?;
+    navigator.main_loop(&mut settings)?;
+    backend.end();
     Ok(())
 }
+
+// the windows subsystem has no console, print in the one of the shell started from or a
+// new one. Output already redirected is kept
+#[cfg(windows)]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut std::ffi::c_void;
+        fn AttachConsole(process_id: u32) -> i32;
+        fn AllocConsole() -> i32;
+    }
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const ATTACH_PARENT_PROCESS: u32 = -1i32 as u32;
+    unsafe {
+        if GetStdHandle(STD_OUTPUT_HANDLE).is_null() && AttachConsole(ATTACH_PARENT_PROCESS) == 0 {
+            AllocConsole();
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
@@ -1,16 +1,316 @@
-use serde::{Deserialize, Serialize};
-use serde_json;
-use serde_json::Result;
+use crate::error::FlexiError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs::{self, File};
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
 
-pub trait Savable<'de, T: Serialize + Deserialize<'de>>
-where
-    Self: Serialize,
-{
-    fn from_json(serialized: &'de str) -> Result<T> {
-        serde_json::from_str(serialized)
+/// Upgrade the content of a file by one version, the `version` field is already removed
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+/// migration of a version that only added the `version` field or optional fields, the
+/// format is unchanged
+pub fn unchanged(value: Value) -> Result<Value, String> {
+    Ok(value)
+}
+
+/// first migration of the formats saved as a bare list, moved under `data`
+pub fn wrap_list(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Array(_) => {
+            let mut file = Map::new();
+            file.insert(DATA_KEY.to_string(), value);
+            Ok(Value::Object(file))
+        }
+        _ => Err("expected a list".to_string()),
+    }
+}
+
+// key of the values saved as something else than an object
+const DATA_KEY: &str = "data";
+
+pub trait Savable: Serialize + DeserializeOwned {
+    /// migration N upgrades version N to N + 1, the current version is their count
+    const MIGRATIONS: &'static [Migration];
+
+    fn version() -> u64 {
+        Self::MIGRATIONS.len() as u64
+    }
+
+    #[cfg(test)]
+    fn from_json(serialized: &str) -> Result<Self, FlexiError> {
+        Self::read("JSON", serialized).map(|(data, _)| data)
+    }
+
+    /// parse any version of the format, also returns the version found
+    fn read(path: &str, serialized: &str) -> Result<(Self, u64), FlexiError> {
+        let schema_error = |msg: String| FlexiError::Schema {
+            path: path.to_string(),
+            msg,
+        };
+        let mut value: Value =
+            serde_json::from_str(serialized).map_err(|e| FlexiError::json(path, e))?;
+        // files from before versioning have no version field
+        let found = match value {
+            Value::Object(ref mut file) => match file.remove("version") {
+                Some(version) => version
+                    .as_u64()
+                    .ok_or_else(|| schema_error(format!("invalid version {}", version)))?,
+                None => 0,
+            },
+            _ => 0,
+        };
+        if found > Self::version() {
+            return Err(schema_error(format!(
+                "format version {} is newer than this program's {}, please update",
+                found,
+                Self::version()
+            )));
+        }
+        for (from, migration) in Self::MIGRATIONS.iter().enumerate().skip(found as usize) {
+            value = migration(value)
+                .map_err(|msg| schema_error(format!("upgrade from version {}: {}", from, msg)))?;
+        }
+        if Self::version() > 0 {
+            value = match value {
+                Value::Object(mut file) if file.len() == 1 && file.contains_key(DATA_KEY) => {
+                    file.remove(DATA_KEY).unwrap_or_default()
+                }
+                value => value,
+            };
+        }
+        let data = serde_json::from_value(value).map_err(|e| FlexiError::json(path, e))?;
+        Ok((data, found))
     }
 
     fn to_json(&self) -> String {
-        serde_json::to_string_pretty(&self).unwrap()
+        let value = serde_json::to_value(self).unwrap();
+        if Self::version() == 0 {
+            return serde_json::to_string_pretty(&value).unwrap();
+        }
+        let mut file = Map::new();
+        file.insert("version".to_string(), Value::from(Self::version()));
+        match value {
+            Value::Object(fields) => file.extend(fields),
+            value => {
+                file.insert(DATA_KEY.to_string(), value);
+            }
+        }
+        serde_json::to_string_pretty(&file).unwrap()
+    }
+
+    fn save_file(&self, path: &str) -> Result<(), FlexiError> {
+        write_file(path, format!("{}\n", self.to_json()).as_bytes())
+    }
+}
+
+/// name of the previous content of `path`, kept when it is replaced
+pub fn last_good_filename(path: &str) -> String {
+    format!("{}.last", path)
+}
+
+/// replace the content of `path`, a crash at any point leaves either the old or the new
+/// content. The old one is kept as the last good copy
+pub fn write_file(path: &str, content: &[u8]) -> Result<(), FlexiError> {
+    if Path::new(path).exists() {
+        let old = fs::read(path).map_err(|e| FlexiError::io(path, e))?;
+        replace(&last_good_filename(path), &old)?;
+    }
+    replace(path, content)?;
+    sync_dir(path);
+    Ok(())
+}
+
+// write `content` to a temporary file flushed to disk, then rename it to `path`
+fn replace(path: &str, content: &[u8]) -> Result<(), FlexiError> {
+    let tmp = format!("{}.tmp", path);
+    let mut file = File::create(&tmp).map_err(|e| FlexiError::io(&tmp, e))?;
+    file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| FlexiError::io(&tmp, e))?;
+    drop(file);
+    fs::rename(&tmp, path).map_err(|e| FlexiError::io(path, e))
+}
+
+// make the rename durable, not possible on Windows where it is already
+#[cfg(unix)]
+fn sync_dir(path: &str) {
+    let dir = match Path::new(path).parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &str) {}
+
+/// put back the last good copy of a damaged file, which is kept as `<path>.damaged`
+pub fn restore_last_good(path: &str) -> Result<(), FlexiError> {
+    let damaged = format!("{}.damaged", path);
+    fs::rename(path, &damaged).map_err(|e| FlexiError::io(path, e))?;
+    fs::rename(last_good_filename(path), path).map_err(|e| FlexiError::io(path, e))?;
+    sync_dir(path);
+    Ok(())
+}
+
+/// name of the copy made before upgrading `path` from `version`
+pub fn backup_filename(path: &str, version: u64) -> String {
+    format!("{}.v{}.bak", path, version)
+}
+
+/// load `path`, None if it doesn't exist. Older versions are upgraded in place,
+/// the original file is kept next to it
+pub fn load_file<T: Savable>(path: &str) -> Result<Option<T>, FlexiError> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(FlexiError::io(path, e)),
+    };
+    let mut json = String::new();
+    file.read_to_string(&mut json)
+        .map_err(|e| FlexiError::io(path, e))?;
+    let (data, version) = T::read(path, &json).map_err(|e| match e {
+        FlexiError::Json { path, cause } if Path::new(&last_good_filename(&path)).exists() => {
+            FlexiError::Damaged { path, cause }
+        }
+        e => e,
+    })?;
+    if version < T::version() {
+        let backup = backup_filename(path, version);
+        // an existing backup of this version is the original file
+        if !Path::new(&backup).exists() {
+            fs::copy(path, &backup).map_err(|e| FlexiError::io(&backup, e))?;
+        }
+        data.save_file(path)?;
+    }
+    Ok(Some(data))
+}
+
+/// copy `name` from `tests/fixtures` to a directory of its own, returns the copy
+#[cfg(test)]
+pub fn fixture(name: &str, test: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("rusty_flexi_{}_{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(Path::new(name).file_name().unwrap());
+    fs::copy(Path::new("tests/fixtures").join(name), &path).expect("missing fixture");
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Counter {
+        count: i32,
+        step: i32,
+    }
+
+    // version 1 renamed `value` to `count`, version 2 added `step`
+    fn rename_value(mut value: Value) -> Result<Value, String> {
+        let file = value.as_object_mut().ok_or("expected an object")?;
+        let count = file.remove("value").ok_or("no value")?;
+        file.insert("count".to_string(), count);
+        Ok(value)
+    }
+
+    fn add_step(mut value: Value) -> Result<Value, String> {
+        let file = value.as_object_mut().ok_or("expected an object")?;
+        file.insert("step".to_string(), Value::from(1));
+        Ok(value)
+    }
+
+    impl Savable for Counter {
+        const MIGRATIONS: &'static [Migration] = &[rename_value, add_step];
+    }
+
+    impl Savable for Vec<i32> {
+        const MIGRATIONS: &'static [Migration] = &[wrap_list];
+    }
+
+    #[test]
+    fn migrations_test() {
+        let expected = Counter { count: 3, step: 1 };
+        assert_eq!(Counter::from_json(r#"{"value": 3}"#).unwrap(), expected);
+        assert_eq!(
+            Counter::from_json(r#"{"version": 1, "count": 3}"#).unwrap(),
+            expected
+        );
+        let (counter, version) = Counter::read("a.json", &expected.to_json()).unwrap();
+        assert_eq!(counter, expected);
+        assert_eq!(version, 2);
+        match Counter::from_json(r#"{"version": 3, "count": 3, "step": 1}"#) {
+            Err(FlexiError::Schema { ref msg, .. }) => assert!(msg.contains("newer")),
+            _ => panic!("should not read a newer version"),
+        }
+        match Counter::from_json(r#"{"count": 3}"#) {
+            Err(FlexiError::Schema { ref msg, .. }) => {
+                assert_eq!(msg, "upgrade from version 0: no value")
+            }
+            _ => panic!("should fail the first migration"),
+        }
+    }
+
+    #[test]
+    fn list_test() {
+        let list = vec![1, 2, 3];
+        assert!(list
+            .to_json()
+            .starts_with("{\n  \"version\": 1,\n  \"data\": ["));
+        assert_eq!(Vec::<i32>::from_json(&list.to_json()).unwrap(), list);
+        assert_eq!(Vec::<i32>::from_json("[1, 2, 3]").unwrap(), list);
+    }
+
+    #[test]
+    fn load_file_upgrade_test() {
+        let path = fixture("v0/counter.json", "upgrade");
+        let old = fs::read_to_string(&path).unwrap();
+
+        let counter = load_file::<Counter>(&path).unwrap().unwrap();
+        assert_eq!(counter, Counter { count: 3, step: 1 });
+        assert_eq!(fs::read_to_string(backup_filename(&path, 0)).unwrap(), old);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("{\n  \"version\": 2,"));
+        // loading the upgraded file changes nothing
+        assert_eq!(load_file::<Counter>(&path).unwrap().unwrap(), counter);
+        assert_eq!(fs::read_to_string(backup_filename(&path, 0)).unwrap(), old);
+    }
+
+    #[test]
+    fn write_file_test() {
+        let path = fixture("v0/counter.json", "write");
+        Counter { count: 4, step: 1 }.save_file(&path).unwrap();
+        Counter { count: 5, step: 1 }.save_file(&path).unwrap();
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+        let last = last_good_filename(&path);
+        assert!(!Path::new(&format!("{}.tmp", last)).exists());
+        let last = fs::read_to_string(last).unwrap();
+        assert_eq!(Counter::from_json(&last).unwrap().count, 4);
+
+        // cut short while written by an older version
+        let json = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &json[..json.len() / 2]).unwrap();
+        match load_file::<Counter>(&path) {
+            Err(FlexiError::Damaged { .. }) => {}
+            _ => panic!("should be damaged"),
+        }
+        restore_last_good(&path).unwrap();
+        assert_eq!(load_file::<Counter>(&path).unwrap().unwrap().count, 4);
+        assert!(Path::new(&format!("{}.damaged", path)).exists());
+
+        // no last good copy to offer
+        fs::write(&path, "").unwrap();
+        match load_file::<Counter>(&path) {
+            Err(FlexiError::Json { .. }) => {}
+            _ => panic!("should be invalid JSON"),
+        }
     }
 }
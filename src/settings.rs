@@ -1,12 +1,13 @@
-use crate::savable::Savable;
-use crate::timedata::{weekday_to_string, HOLIDAY_DURATION};
+use crate::datadir::data_dir;
+use crate::error::FlexiError;
+use crate::savable::{unchanged, Migration, Savable};
+use crate::storage::storage;
+use crate::timedata::{day_credit, weekday_to_string, Credit, DayStatus, DayType};
 use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Timelike, Weekday};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
 use std::cmp::Ordering;
-use std::error::Error;
 use std::fmt::{Display, Formatter, Result};
-use std::fs::File;
-use std::io::prelude::*;
 
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
 pub struct SettingsDay {
@@ -21,13 +22,52 @@ impl Default for SettingsDay {
     fn default() -> SettingsDay {
         SettingsDay {
             weekday: Weekday::Mon,
-            start: NaiveTime::from_hms(9, 0, 0),
-            end: NaiveTime::from_hms(17, 0, 0),
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
             pause: Duration::minutes(30).num_minutes(),
         }
     }
 }
 
+impl SettingsDay {
+    /// a day without working time, used for days off in a schedule
+    pub fn off(weekday: Weekday) -> SettingsDay {
+        SettingsDay {
+            weekday,
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            pause: 0,
+        }
+    }
+
+    pub fn total_minutes(&self) -> i64 {
+        self.end.signed_duration_since(self.start).num_minutes() - self.pause
+    }
+
+    pub fn is_off(&self) -> bool {
+        self.total_minutes() <= 0
+    }
+
+    /// the half of the day left to work, without pause, when the morning or the afternoon
+    /// is taken off
+    pub fn half(&self, morning_off: bool) -> SettingsDay {
+        let half = Duration::minutes(self.total_minutes().max(0) / 2);
+        if morning_off {
+            SettingsDay {
+                start: self.end - half,
+                pause: 0,
+                ..*self
+            }
+        } else {
+            SettingsDay {
+                end: self.start + half,
+                pause: 0,
+                ..*self
+            }
+        }
+    }
+}
+
 impl Display for SettingsDay {
     fn fmt(&self, f: &mut Formatter) -> Result {
         let pause = Duration::minutes(self.pause);
@@ -56,32 +96,32 @@ impl Default for WeekSchedule {
             sched: vec![
                 SettingsDay {
                     weekday: Weekday::Mon,
-                    start: NaiveTime::from_hms(9, 10, 0),
-                    end: NaiveTime::from_hms(17, 10, 0),
+                    start: NaiveTime::from_hms_opt(9, 10, 0).unwrap(),
+                    end: NaiveTime::from_hms_opt(17, 10, 0).unwrap(),
                     pause: Duration::minutes(30).num_minutes(),
                 },
                 SettingsDay {
                     weekday: Weekday::Tue,
-                    start: NaiveTime::from_hms(9, 10, 0),
-                    end: NaiveTime::from_hms(17, 10, 0),
+                    start: NaiveTime::from_hms_opt(9, 10, 0).unwrap(),
+                    end: NaiveTime::from_hms_opt(17, 10, 0).unwrap(),
                     pause: Duration::minutes(30).num_minutes(),
                 },
                 SettingsDay {
                     weekday: Weekday::Wed,
-                    start: NaiveTime::from_hms(9, 10, 0),
-                    end: NaiveTime::from_hms(17, 10, 0),
+                    start: NaiveTime::from_hms_opt(9, 10, 0).unwrap(),
+                    end: NaiveTime::from_hms_opt(17, 10, 0).unwrap(),
                     pause: Duration::minutes(30).num_minutes(),
                 },
                 SettingsDay {
                     weekday: Weekday::Thu,
-                    start: NaiveTime::from_hms(9, 10, 0),
-                    end: NaiveTime::from_hms(17, 10, 0),
+                    start: NaiveTime::from_hms_opt(9, 10, 0).unwrap(),
+                    end: NaiveTime::from_hms_opt(17, 10, 0).unwrap(),
                     pause: Duration::minutes(30).num_minutes(),
                 },
                 SettingsDay {
                     weekday: Weekday::Fri,
-                    start: NaiveTime::from_hms(9, 10, 0),
-                    end: NaiveTime::from_hms(16, 50, 0),
+                    start: NaiveTime::from_hms_opt(9, 10, 0).unwrap(),
+                    end: NaiveTime::from_hms_opt(16, 50, 0).unwrap(),
                     pause: Duration::minutes(30).num_minutes(),
                 },
             ],
@@ -89,12 +129,128 @@ impl Default for WeekSchedule {
     }
 }
 
+impl WeekSchedule {
+    /// make sure every day from Monday to Friday is present, in order. Missing days are off.
+    pub fn normalize(&mut self) {
+        let mut wd = Weekday::Mon;
+        while wd != Weekday::Sat {
+            if !self.sched.iter().any(|d| d.weekday == wd) {
+                self.sched.push(SettingsDay::off(wd));
+            }
+            wd = wd.succ();
+        }
+        self.sched.sort_by_key(|d| d.weekday.number_from_monday());
+    }
+}
+
+/// Week schedule and goal in force from a given date
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ScheduleVersion {
+    pub from: NaiveDate,
+    pub week_sched: WeekSchedule,
+    pub week_goal: i64,
+}
+
 #[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Offset {
     pub entry: i64, // TODO switch to Duration when chrono supports Serialize
     pub exit: i64,
 }
 
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LedgerSettings {
+    // first month counted in the flexi account, all saved months if None
+    pub start: Option<NaiveDate>,
+    pub opening_balance: i64, // TODO switch to Duration when chrono supports Serialize
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct PublicHolidaySettings {
+    // built-in calendar: "fr", "de", "gb", "us", none if empty
+    pub country: String,
+    // deduct public holidays and closures from the holidays allowance
+    pub count_against_allowance: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct MonthDay {
+    pub month: u32,
+    pub day: u32,
+}
+
+/// Rules of the holidays allowance, see `Entitlement`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct EntitlementSettings {
+    // first month of the leave year, 1 for calendar years
+    pub year_start_month: u32,
+    // days left at the end of a leave year usable in the next one, at most
+    pub carry_over_cap: f32,
+    // carried days not taken by this day of the next leave year are lost
+    pub carry_over_expiry: Option<MonthDay>,
+    // first day of work, the allowance of its leave year is pro rata
+    pub start: Option<NaiveDate>,
+    // days left before the first leave year counted, on top of its allowance
+    pub opening_balance: f32,
+}
+
+impl Default for EntitlementSettings {
+    fn default() -> EntitlementSettings {
+        EntitlementSettings {
+            year_start_month: 1,
+            carry_over_cap: 0.0,
+            carry_over_expiry: None,
+            start: None,
+            opening_balance: 0.0,
+        }
+    }
+}
+
+/// Thresholds of the working time checks, see `Compliance`. A zero turns a check off
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct ComplianceSettings {
+    // most minutes worked in a day
+    pub max_day: i64,
+    // least minutes off between the end of a day and the start of the next one
+    pub min_rest: i64,
+    // minutes worked from which a break of `min_break` minutes is due
+    pub break_after: i64,
+    pub min_break: i64,
+    // most minutes worked in a week, averaged over `average_weeks` weeks
+    pub max_average_week: i64,
+    pub average_weeks: u32,
+}
+
+impl Default for ComplianceSettings {
+    fn default() -> ComplianceSettings {
+        ComplianceSettings {
+            max_day: Duration::hours(10).num_minutes(),
+            min_rest: Duration::hours(11).num_minutes(),
+            break_after: Duration::hours(6).num_minutes(),
+            min_break: 30,
+            max_average_week: Duration::hours(48).num_minutes(),
+            average_weeks: 17,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct BackupSettings {
+    // number of days and months with a snapshot kept
+    pub daily: usize,
+    pub monthly: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> BackupSettings {
+        BackupSettings {
+            daily: 7,
+            monthly: 12,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Settings {
     #[serde(default)]
@@ -107,8 +263,25 @@ pub struct Settings {
     pub holiday_duration: i64,
     #[serde(default)]
     pub offsets: Offset,
-    #[serde(default = "default_max_undo")]
-    pub max_undo: usize,
+    #[serde(default)]
+    pub ledger: LedgerSettings,
+    #[serde(default)]
+    pub public_holidays: PublicHolidaySettings,
+    #[serde(default)]
+    pub entitlement: EntitlementSettings,
+    #[serde(default)]
+    pub compliance: ComplianceSettings,
+    // schedules replacing `week_sched` and `week_goal` from their date, sorted by date
+    #[serde(default)]
+    pub schedule_history: Vec<ScheduleVersion>,
+    #[serde(default)]
+    pub backups: BackupSettings,
+    // projects and tasks work sessions are tagged with
+    #[serde(default)]
+    pub projects: Vec<String>,
+    // types of day added to the built-in ones, or replacing them by code
+    #[serde(default)]
+    pub day_types: Vec<DayType>,
 }
 
 fn default_week_goal() -> i64 {
@@ -123,69 +296,228 @@ const fn default_holidays_per_year() -> f32 {
     26.0
 }
 
-const fn default_max_undo() -> usize {
-    5
-}
-
 impl Default for Settings {
     fn default() -> Settings {
-        let settings = Settings {
+        Settings {
             week_sched: WeekSchedule::default(),
             holidays_per_year: default_holidays_per_year(),
             week_goal: default_week_goal(),
             holiday_duration: default_holiday_duration(),
             offsets: Offset { entry: 0, exit: 0 },
-            max_undo: 5,
-        };
-        unsafe {
-            HOLIDAY_DURATION = settings.holiday_duration;
+            ledger: LedgerSettings::default(),
+            public_holidays: PublicHolidaySettings::default(),
+            entitlement: EntitlementSettings::default(),
+            compliance: ComplianceSettings::default(),
+            schedule_history: Vec::new(),
+            backups: BackupSettings::default(),
+            projects: Vec::new(),
+            day_types: Vec::new(),
         }
-        settings
     }
 }
 
-impl<'a> Savable<'a, Settings> for Settings {}
+// undo is unlimited since the journal replaced the in-memory list
+fn drop_max_undo(mut value: Value) -> std::result::Result<Value, String> {
+    let settings = value.as_object_mut().ok_or("expected an object")?;
+    settings.remove("max_undo");
+    Ok(value)
+}
+
+impl Savable for Settings {
+    const MIGRATIONS: &'static [Migration] = &[unchanged, drop_max_undo];
+}
 
 impl Settings {
-    pub fn save(&self) {
-        let mut file = match File::create("./data/settings.json") {
-            Err(why) => panic!("couldn't create settings.json: {}", why.description()),
-            Ok(file) => file,
+    pub fn save(&self) -> std::result::Result<(), FlexiError> {
+        storage().save_settings(self)
+    }
+
+    /// None if there is no settings yet
+    pub fn load() -> std::result::Result<Option<Settings>, FlexiError> {
+        let mut settings = match storage().load_settings()? {
+            Some(settings) => settings,
+            None => return Ok(None),
         };
-        unsafe {
-            HOLIDAY_DURATION = self.holiday_duration;
+        settings.validate()?;
+        settings.week_sched.normalize();
+        for version in settings.schedule_history.iter_mut() {
+            version.week_sched.normalize();
         }
-        file.write_all(self.to_json().as_bytes())
-            .expect("Unable to write data");
-        file.write_all("\n".as_bytes())
-            .expect("Unable to write \\n");
-    }
-
-    pub fn load() -> Option<Settings> {
-        match File::open("./data/settings.json") {
-            Err(_) => None,
-            Ok(mut file) => {
-                let mut json = String::new();
-                file.read_to_string(&mut json)
-                    .expect("Failed to read settings.json");
-                let settings = Settings::from_json(&json)
-                                .expect("Settings format has changed, please backup `data/settings.json` and delete it.");
-                unsafe {
-                    HOLIDAY_DURATION = settings.holiday_duration;
-                }
-                Some(settings)
+        settings.schedule_history.sort_by_key(|v| v.from);
+        Ok(Some(settings))
+    }
+
+    fn validate(&self) -> std::result::Result<(), FlexiError> {
+        let invalid = |what: &str| {
+            Err(FlexiError::Validation(format!(
+                "{}: {}",
+                data_dir().settings(),
+                what
+            )))
+        };
+        let mut goals = std::iter::once(self.week_goal)
+            .chain(self.schedule_history.iter().map(|v| v.week_goal));
+        if goals.any(|goal| goal < 0) {
+            return invalid("negative week goal");
+        }
+        if self.holiday_duration < 0 || self.holidays_per_year < 0.0 {
+            return invalid("negative holidays");
+        }
+        let entitlement = &self.entitlement;
+        if !(1..=12).contains(&entitlement.year_start_month) {
+            return invalid("leave year must start on a month from 1 to 12");
+        }
+        if entitlement.carry_over_cap < 0.0 {
+            return invalid("negative carry over cap");
+        }
+        if let Some(expiry) = entitlement.carry_over_expiry {
+            // any year without 29 February
+            if NaiveDate::from_ymd_opt(2019, expiry.month, expiry.day).is_none() {
+                return invalid("carry over expiry is not a valid day");
+            }
+        }
+        let compliance = &self.compliance;
+        let thresholds = [
+            compliance.max_day,
+            compliance.min_rest,
+            compliance.break_after,
+            compliance.min_break,
+            compliance.max_average_week,
+        ];
+        if thresholds.iter().any(|&minutes| minutes < 0) {
+            return invalid("negative compliance threshold");
+        }
+        if compliance.average_weeks == 0 {
+            return invalid("weekly hours must be averaged over one week at least");
+        }
+        let mut dates: Vec<NaiveDate> = self.schedule_history.iter().map(|v| v.from).collect();
+        dates.sort();
+        dates.dedup();
+        if dates.len() != self.schedule_history.len() {
+            return invalid("two schedule versions start on the same day");
+        }
+        let mut projects: Vec<&str> = self.projects.iter().map(|p| p.trim()).collect();
+        if projects.iter().any(|p| p.is_empty()) {
+            return invalid("empty project name");
+        }
+        projects.sort_unstable();
+        projects.dedup();
+        if projects.len() != self.projects.len() {
+            return invalid("two projects with the same name");
+        }
+        let mut codes: Vec<char> = self.day_types.iter().map(|t| t.code).collect();
+        if codes.iter().any(|&c| c == 'N' || c == 'W') {
+            return invalid("day types N and W cannot be changed");
+        }
+        if codes.iter().any(|c| !c.is_ascii_alphabetic()) {
+            return invalid("day type codes must be letters");
+        }
+        if self.day_types.iter().any(|t| t.name.trim().is_empty()) {
+            return invalid("day type without name");
+        }
+        codes.sort_unstable();
+        codes.dedup();
+        if codes.len() != self.day_types.len() {
+            return invalid("two day types with the same code");
+        }
+        Ok(())
+    }
+
+    /// time recorded when clocking in at `now`: seconds cleared, entry offset removed
+    pub fn entry_time(&self, now: NaiveTime) -> NaiveTime {
+        NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap()
+            - Duration::minutes(self.offsets.entry)
+    }
+
+    /// time recorded when clocking out at `now`: seconds cleared, exit offset added
+    pub fn exit_time(&self, now: NaiveTime) -> NaiveTime {
+        NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap()
+            + Duration::minutes(self.offsets.exit)
+    }
+
+    // index in `schedule_history` of the version in force on `day`
+    fn schedule_version_for(&self, day: &NaiveDate) -> Option<usize> {
+        self.schedule_history.iter().rposition(|v| v.from <= *day)
+    }
+
+    /// week schedule in force on `day`
+    pub fn week_sched_for(&self, day: &NaiveDate) -> &WeekSchedule {
+        match self.schedule_version_for(day) {
+            Some(idx) => &self.schedule_history[idx].week_sched,
+            None => &self.week_sched,
+        }
+    }
+
+    /// week goal in force on `day`
+    pub fn week_goal_for(&self, day: &NaiveDate) -> i64 {
+        match self.schedule_version_for(day) {
+            Some(idx) => self.schedule_history[idx].week_goal,
+            None => self.week_goal,
+        }
+    }
+
+    /// week schedule and goal in force on `day`, to edit them
+    pub fn schedule_for_mut(&mut self, day: &NaiveDate) -> (&mut WeekSchedule, &mut i64) {
+        match self.schedule_version_for(day) {
+            Some(idx) => {
+                let version = &mut self.schedule_history[idx];
+                (&mut version.week_sched, &mut version.week_goal)
             }
+            None => (&mut self.week_sched, &mut self.week_goal),
         }
     }
 
+    /// date from which the schedule in force on `day` applies, None for the base schedule
+    pub fn schedule_start_for(&self, day: &NaiveDate) -> Option<NaiveDate> {
+        self.schedule_version_for(day)
+            .map(|idx| self.schedule_history[idx].from)
+    }
+
+    /// minutes expected to be worked on `day` according to the week schedule
+    pub fn target_minutes_for(&self, day: &NaiveDate) -> i64 {
+        if self.is_working_day(day) {
+            self.get_default_day_settings_for(day).total_minutes()
+        } else {
+            0
+        }
+    }
+
+    pub fn is_working_day(&self, day: &NaiveDate) -> bool {
+        match day.weekday() {
+            Weekday::Sat | Weekday::Sun => false,
+            _ => !self.get_default_day_settings_for(day).is_off(),
+        }
+    }
+
+    /// working window of `day` given its status, half days only keep the worked half
+    pub fn get_default_day_settings_for_status(
+        &self,
+        day: &NaiveDate,
+        status: DayStatus,
+    ) -> SettingsDay {
+        let default = self.get_default_day_settings_for(day);
+        match day_credit(self, status) {
+            Credit::MorningOff => default.half(true),
+            Credit::AfternoonOff => default.half(false),
+            _ => default,
+        }
+    }
+
+    /// minutes credited for the half of `day` taken off, from the schedule in force then
+    pub fn half_day_minutes(&self, day: &NaiveDate) -> i64 {
+        self.get_default_day_settings_for(day)
+            .total_minutes()
+            .max(0)
+            / 2
+    }
+
     pub fn get_default_day_settings_for(&self, day: &NaiveDate) -> SettingsDay {
         match day.weekday() {
-            Weekday::Sat | Weekday::Sun => {
-                let mut d = SettingsDay::default();
-                d.weekday = day.weekday();
-                d
-            }
-            _ => match self.week_sched.sched.binary_search_by(|flex_day| {
+            Weekday::Sat | Weekday::Sun => SettingsDay {
+                weekday: day.weekday(),
+                ..Default::default()
+            },
+            _ => match self.week_sched_for(day).sched.binary_search_by(|flex_day| {
                 let num_left = flex_day.weekday.number_from_monday();
                 let num_right = day.weekday().number_from_monday();
                 if flex_day.weekday == day.weekday() {
@@ -198,8 +530,8 @@ impl Settings {
                     }
                 }
             }) {
-                Ok(idx) => self.week_sched.sched[idx],
-                Err(_) => panic!("couldn't find {:?} in week sched", day.weekday()),
+                Ok(idx) => self.week_sched_for(day).sched[idx],
+                Err(_) => SettingsDay::off(day.weekday()),
             },
         }
     }
@@ -212,9 +544,11 @@ impl Settings {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::savable::{backup_filename, fixture, load_file};
 
     fn expected_test_json() -> &'static str {
         r#"{
+  "version": 2,
   "week_sched": {
     "sched": [
       {
@@ -256,16 +590,46 @@ mod tests {
     "entry": 0,
     "exit": 0
   },
-  "max_undo": 5
+  "ledger": {
+    "start": null,
+    "opening_balance": 0
+  },
+  "public_holidays": {
+    "country": "",
+    "count_against_allowance": false
+  },
+  "entitlement": {
+    "year_start_month": 1,
+    "carry_over_cap": 0.0,
+    "carry_over_expiry": null,
+    "start": null,
+    "opening_balance": 0.0
+  },
+  "compliance": {
+    "max_day": 600,
+    "min_rest": 660,
+    "break_after": 360,
+    "min_break": 30,
+    "max_average_week": 2880,
+    "average_weeks": 17
+  },
+  "schedule_history": [],
+  "backups": {
+    "daily": 7,
+    "monthly": 12
+  },
+  "projects": [],
+  "day_types": []
 }"#
     }
 
     #[test]
     fn save_and_load_test() {
+        crate::datadir::test_data_dir("save_and_load_test");
         let settings = Settings::default();
-        settings.save();
-        assert!(File::open("./data/settings.json").is_ok());
-        let loaded = Settings::load();
+        settings.save().unwrap();
+        assert!(std::path::Path::new(&data_dir().settings()).exists());
+        let loaded = Settings::load().unwrap();
         assert_eq!(loaded, Some(settings));
     }
 
@@ -286,17 +650,79 @@ mod tests {
         assert_eq!(settings, expected);
     }
 
+    #[test]
+    fn settings_fixture_test() {
+        let path = fixture("v0/settings.json", "settings");
+        let settings = load_file::<Settings>(&path).unwrap().unwrap();
+        assert_eq!(settings, Settings::default());
+        let upgraded = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(upgraded.trim_end(), expected_test_json());
+        assert!(std::path::Path::new(&backup_filename(&path, 0)).exists());
+    }
+
     #[test]
     fn get_default_day_settings_for_test() {
         let settings: Settings = Default::default();
 
-        let cur_date = NaiveDate::from_ymd(2017, 05, 05);
+        let cur_date = NaiveDate::from_ymd_opt(2017, 5, 5).unwrap();
         let expected = SettingsDay {
             weekday: Weekday::Fri,
-            start: NaiveTime::from_hms(9, 10, 00),
-            end: NaiveTime::from_hms(16, 50, 00),
+            start: NaiveTime::from_hms_opt(9, 10, 00).unwrap(),
+            end: NaiveTime::from_hms_opt(16, 50, 00).unwrap(),
             pause: 30,
         };
         assert_eq!(settings.get_default_day_settings_for(&cur_date), expected);
+
+        // 07:10 worked on Fridays, half of it when half a day is taken
+        let afternoon = SettingsDay {
+            start: NaiveTime::from_hms_opt(13, 15, 00).unwrap(),
+            pause: 0,
+            ..expected
+        };
+        assert_eq!(
+            settings.get_default_day_settings_for_status(&cur_date, DayStatus::HalfAm),
+            afternoon
+        );
+        let morning = SettingsDay {
+            end: NaiveTime::from_hms_opt(12, 45, 00).unwrap(),
+            pause: 0,
+            ..expected
+        };
+        assert_eq!(
+            settings.get_default_day_settings_for_status(&cur_date, DayStatus::HalfPm),
+            morning
+        );
+        assert_eq!(settings.half_day_minutes(&cur_date), 215);
+    }
+
+    #[test]
+    fn schedule_history_test() {
+        let mut settings: Settings = Default::default();
+        // part time from 2019-03-01, Fridays off
+        let mut part_time = WeekSchedule::default();
+        part_time.sched.pop();
+        part_time.normalize();
+        assert_eq!(part_time.sched.len(), 5);
+        settings.schedule_history.push(ScheduleVersion {
+            from: NaiveDate::from_ymd_opt(2019, 3, 1).unwrap(),
+            week_sched: part_time,
+            week_goal: 30 * 60,
+        });
+
+        let before = NaiveDate::from_ymd_opt(2019, 2, 22).unwrap();
+        let after = NaiveDate::from_ymd_opt(2019, 3, 8).unwrap();
+        assert_eq!(settings.week_goal_for(&before), 37 * 60);
+        assert_eq!(settings.week_goal_for(&after), 30 * 60);
+        assert!(settings.is_working_day(&before));
+        assert!(!settings.is_working_day(&after));
+        assert_eq!(settings.target_minutes_for(&after), 0);
+        // half a day off is half of the day of the schedule in force
+        assert_eq!(settings.half_day_minutes(&before), 215);
+        assert_eq!(settings.half_day_minutes(&after), 0);
+        assert_eq!(
+            settings.schedule_start_for(&after),
+            Some(NaiveDate::from_ymd_opt(2019, 3, 1).unwrap())
+        );
+        assert_eq!(settings.schedule_start_for(&before), None);
     }
 }
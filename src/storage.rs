@@ -0,0 +1,302 @@
+mod json;
+mod sqlite;
+
+pub use self::json::Json;
+pub use self::sqlite::Sqlite;
+
+use crate::backup::Snapshot;
+use crate::datadir::{data_dir, DataDir, DataFile};
+use crate::error::FlexiError;
+use crate::savable::last_good_filename;
+use crate::settings::Settings;
+use crate::timedata::{DayStatus, DaysOff, FlexDay, FlexMonth, JournalEntry, SickDays};
+use chrono::{NaiveDate, NaiveDateTime};
+#[cfg(test)]
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static STORAGE: OnceLock<Box<dyn Storage>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Json,
+    Sqlite,
+}
+
+impl Kind {
+    pub fn parse(s: &str) -> Option<Kind> {
+        match s {
+            "json" => Some(Kind::Json),
+            "sqlite" => Some(Kind::Sqlite),
+            _ => None,
+        }
+    }
+}
+
+/// Where months, days off and settings are loaded from and saved to
+pub trait Storage: Send + Sync {
+    fn kind(&self) -> Kind;
+    fn dir(&self) -> &DataDir;
+
+    /// None if there is no settings yet
+    fn load_settings(&self) -> Result<Option<Settings>, FlexiError>;
+    fn save_settings(&self, settings: &Settings) -> Result<(), FlexiError>;
+
+    /// None if the month was never saved
+    fn load_month(&self, year: i32, month: u32) -> Result<Option<FlexMonth>, FlexiError>;
+    fn save_month(&self, month: &FlexMonth) -> Result<(), FlexiError>;
+    /// (year, month) of every saved month, in chronological order
+    fn saved_months(&self) -> Result<Vec<(i32, u32)>, FlexiError>;
+
+    /// balance of every saved month
+    fn month_balances(&self) -> Result<BTreeMap<(i32, u32), i64>, FlexiError> {
+        let mut balances = BTreeMap::new();
+        for (year, month) in self.saved_months()? {
+            if let Some(m) = self.load_month(year, month)? {
+                balances.insert((year, month), m.balance);
+            }
+        }
+        Ok(balances)
+    }
+
+    /// dates of the days with `status` in every saved month
+    fn find_days(&self, status: DayStatus) -> Result<Vec<NaiveDate>, FlexiError>;
+
+    /// days whose note contains `text`, in chronological order
+    fn search_notes(&self, text: &str) -> Result<Vec<FlexDay>, FlexiError> {
+        let mut days = Vec::new();
+        for (year, month) in self.saved_months()? {
+            if let Some(m) = self.load_month(year, month)? {
+                days.extend(
+                    m.weeks
+                        .into_iter()
+                        .flat_map(|w| w.days)
+                        .filter(|d| d.note_matches(text)),
+                );
+            }
+        }
+        Ok(days)
+    }
+
+    /// worked days before `today` with the start or the end left from the schedule, in
+    /// chronological order
+    fn unclocked_days(
+        &self,
+        today: NaiveDate,
+        settings: &Settings,
+    ) -> Result<Vec<FlexDay>, FlexiError> {
+        let mut days = Vec::new();
+        for (year, month) in self.saved_months()? {
+            if let Some(m) = self.load_month(year, month)? {
+                days.extend(
+                    m.weeks
+                        .into_iter()
+                        .flat_map(|w| w.days)
+                        .filter(|d| {
+                            d.date.is_some_and(|date| date < today) && !d.is_clocked(settings)
+                        }),
+                );
+            }
+        }
+        Ok(days)
+    }
+
+    /// days off of `year`, without the sick days
+    fn load_days_off(&self, year: i32) -> Result<Option<DaysOff>, FlexiError>;
+    fn save_days_off(&self, days_off: &DaysOff) -> Result<(), FlexiError>;
+    fn load_sick_days(&self) -> Result<Option<SickDays>, FlexiError>;
+    fn save_sick_days(&self, sick_days: &SickDays) -> Result<(), FlexiError>;
+
+    /// every entry of the journal, oldest first
+    fn load_journal(&self) -> Result<Vec<JournalEntry>, FlexiError>;
+    fn append_journal(&self, entry: &JournalEntry) -> Result<(), FlexiError>;
+
+    /// every data file in the JSON layout, by name
+    fn files(&self) -> Result<BTreeMap<String, String>, FlexiError>;
+    /// replace all the data by `files`, in the JSON layout
+    fn replace_files(&self, files: &BTreeMap<String, String>) -> Result<(), FlexiError>;
+}
+
+/// storage found in `dir`: the database if there is one, the JSON files otherwise
+pub fn open(dir: &DataDir) -> Result<Box<dyn Storage>, FlexiError> {
+    if Path::new(&dir.database()).exists() {
+        Ok(Box::new(Sqlite::open(dir.clone())?))
+    } else {
+        Ok(Box::new(Json::new(dir.clone())))
+    }
+}
+
+/// set the storage used by the whole program, must be called before any data access
+pub fn init(storage: Box<dyn Storage>) {
+    let _ = STORAGE.set(storage);
+}
+
+#[cfg(not(test))]
+pub fn storage() -> &'static dyn Storage {
+    STORAGE
+        .get_or_init(|| Box::new(Json::new(data_dir().clone())))
+        .as_ref()
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_STORAGE: Cell<Option<&'static dyn Storage>> = const { Cell::new(None) };
+}
+
+// JSON files of the data dir of the running test
+#[cfg(test)]
+pub fn storage() -> &'static dyn Storage {
+    TEST_STORAGE.with(|storage| match storage.get() {
+        Some(s) if s.dir() == data_dir() => s,
+        _ => {
+            let s: &'static dyn Storage = Box::leak(Box::new(Json::new(data_dir().clone())));
+            storage.set(Some(s));
+            s
+        }
+    })
+}
+
+/// copy the data of `dir` to the storage of kind `to`, and remove it from the current one.
+/// The data is backed up first, the name of the backup is returned
+pub fn convert(dir: &DataDir, to: Kind, now: NaiveDateTime) -> Result<Option<String>, FlexiError> {
+    let from = open(dir)?;
+    if from.kind() == to {
+        return Err(FlexiError::Validation(format!(
+            "data in {} is already stored as {:?}",
+            dir.root().display(),
+            to
+        )));
+    }
+    let backup = Snapshot::take(&*from, now)?;
+    let files = from.files()?;
+    match to {
+        Kind::Sqlite => {
+            let database = dir.database();
+            let converted = Sqlite::open(dir.clone()).and_then(|db| db.replace_files(&files));
+            if let Err(e) = converted {
+                // JSON files stay in use
+                let _ = fs::remove_file(&database);
+                return Err(e);
+            }
+            for name in files.keys() {
+                match DataFile::parse(name) {
+                    Some(DataFile::Closures) | None => {}
+                    Some(file) => {
+                        let path = dir.file(file);
+                        fs::remove_file(&path).map_err(|e| FlexiError::io(&path, e))?;
+                        // the last good copy would be stale if converted back
+                        let _ = fs::remove_file(last_good_filename(&path));
+                    }
+                }
+            }
+        }
+        Kind::Json => {
+            Json::new(dir.clone()).replace_files(&files)?;
+            drop(from);
+            let database = dir.database();
+            fs::remove_file(&database).map_err(|e| FlexiError::io(&database, e))?;
+        }
+    }
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::savable::Savable;
+    use crate::timedata::{Action, FlexDay};
+
+    pub fn test_dir(name: &str) -> DataDir {
+        let root = std::env::temp_dir().join(format!(
+            "rusty_flexi_{}_storage_{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        DataDir::new(root)
+    }
+
+    pub fn journal_entry(date: NaiveDate) -> JournalEntry {
+        let old = FlexDay::new(date, &Settings::default());
+        let mut new = old.clone();
+        new.status = DayStatus::Sick;
+        JournalEntry {
+            at: date.and_hms_opt(9, 0, 0).unwrap(),
+            action: Action::Edit,
+            old,
+            new,
+        }
+    }
+
+    #[test]
+    fn json_journal_test() {
+        let dir = test_dir("journal");
+        let json = Json::new(dir.clone());
+        assert!(json.load_journal().unwrap().is_empty());
+        let entry = journal_entry(NaiveDate::from_ymd_opt(2019, 5, 14).unwrap());
+        json.append_journal(&entry).unwrap();
+        json.append_journal(&entry).unwrap();
+
+        // cut short while appended
+        let content = fs::read_to_string(dir.journal()).unwrap();
+        fs::write(dir.journal(), &content[..content.len() - 10]).unwrap();
+        assert_eq!(json.load_journal().unwrap(), vec![entry.clone()]);
+        json.append_journal(&entry).unwrap();
+        assert_eq!(json.load_journal().unwrap().len(), 2);
+
+        fs::write(dir.journal(), "{}\n").unwrap();
+        assert!(json.load_journal().is_err());
+    }
+
+    #[test]
+    fn convert_test() {
+        let dir = test_dir("convert");
+        let settings = Settings::default();
+        let json = Json::new(dir.clone());
+        json.save_settings(&settings).unwrap();
+        let mut may = FlexMonth::new(2019, 5, &settings);
+        let date = NaiveDate::from_ymd_opt(2019, 5, 14).unwrap();
+        let mut day = may.get_week_with_day(date).unwrap().0.clone();
+        day.status = DayStatus::Sick;
+        may.update_day(day);
+        json.save_month(&may).unwrap();
+        json.save_sick_days(&vec![date]).unwrap();
+        fs::write(dir.closures(), "[]\n").unwrap();
+        json.append_journal(&journal_entry(date)).unwrap();
+        let files = json.files().unwrap();
+        // not data, kept as it is
+        let stray = dir.root().join("2019_05.json.orig.json");
+        fs::write(&stray, "{}\n").unwrap();
+
+        let now = NaiveDate::from_ymd_opt(2019, 5, 20)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        assert!(convert(&dir, Kind::Sqlite, now).unwrap().is_some());
+        let db = open(&dir).unwrap();
+        assert_eq!(db.kind(), Kind::Sqlite);
+        assert!(!Path::new(&dir.month(2019, 5)).exists());
+        assert!(Path::new(&dir.closures()).exists());
+        assert!(stray.exists());
+        assert_eq!(db.load_month(2019, 5).unwrap(), Some(may));
+        assert_eq!(db.find_days(DayStatus::Sick).unwrap(), vec![date]);
+        assert_eq!(db.load_journal().unwrap(), vec![journal_entry(date)]);
+        assert_eq!(db.files().unwrap(), files);
+        assert!(convert(&dir, Kind::Sqlite, now).is_err());
+        drop(db);
+
+        convert(&dir, Kind::Json, now).unwrap();
+        assert!(!Path::new(&dir.database()).exists());
+        assert_eq!(open(&dir).unwrap().kind(), Kind::Json);
+        fs::remove_file(&stray).unwrap();
+        assert_eq!(json.files().unwrap(), files);
+        assert_eq!(
+            Settings::from_json(&files["settings.json"]).unwrap(),
+            settings
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use super::{Kind, Storage};
+use crate::datadir::{DataDir, DataFile};
+use crate::error::FlexiError;
+use crate::savable::{load_file, write_file, Savable};
+use crate::settings::Settings;
+use crate::timedata::{month_to_string, DayStatus, DaysOff, FlexMonth, JournalEntry, SickDays};
+use chrono::NaiveDate;
+use glob::glob;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+/// One JSON file per month, per year of days off, plus the settings and sick days
+pub struct Json {
+    dir: DataDir,
+}
+
+impl Json {
+    pub fn new(dir: DataDir) -> Json {
+        Json { dir }
+    }
+}
+
+impl Storage for Json {
+    fn kind(&self) -> Kind {
+        Kind::Json
+    }
+
+    fn dir(&self) -> &DataDir {
+        &self.dir
+    }
+
+    fn load_settings(&self) -> Result<Option<Settings>, FlexiError> {
+        load_file::<Settings>(&self.dir.settings())
+    }
+
+    fn save_settings(&self, settings: &Settings) -> Result<(), FlexiError> {
+        settings.save_file(&self.dir.settings())
+    }
+
+    fn load_month(&self, year: i32, month: u32) -> Result<Option<FlexMonth>, FlexiError> {
+        let path = self.dir.month(year, month);
+        match load_file::<FlexMonth>(&path)? {
+            Some(m) => {
+                if (m.year, m.month) != (year, month) {
+                    return Err(FlexiError::Validation(format!(
+                        "{} holds {} {} instead",
+                        path,
+                        month_to_string(m.month),
+                        m.year
+                    )));
+                }
+                m.validate(&path)?;
+                Ok(Some(m))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn save_month(&self, month: &FlexMonth) -> Result<(), FlexiError> {
+        month.save_file(&self.dir.month(month.year, month.month))
+    }
+
+    fn saved_months(&self) -> Result<Vec<(i32, u32)>, FlexiError> {
+        let pattern = self.dir.months_pattern();
+        let mut months: Vec<(i32, u32)> = glob(&pattern)
+            .map_err(|e| FlexiError::Validation(format!("{}: {}", pattern, e)))?
+            .filter_map(|path| {
+                let name = path.ok()?.file_name()?.to_string_lossy().into_owned();
+                match DataFile::parse(&name)? {
+                    DataFile::Month(year, month) => Some((year, month)),
+                    _ => None,
+                }
+            })
+            .collect();
+        months.sort();
+        Ok(months)
+    }
+
+    fn find_days(&self, status: DayStatus) -> Result<Vec<NaiveDate>, FlexiError> {
+        let mut dates = Vec::new();
+        for (year, month) in self.saved_months()? {
+            if let Some(m) = self.load_month(year, month)? {
+                dates.extend(m.get_days_with(status));
+            }
+        }
+        Ok(dates)
+    }
+
+    fn load_days_off(&self, year: i32) -> Result<Option<DaysOff>, FlexiError> {
+        load_file::<DaysOff>(&self.dir.days_off(year))
+    }
+
+    fn save_days_off(&self, days_off: &DaysOff) -> Result<(), FlexiError> {
+        days_off.save_file(&self.dir.days_off(days_off.year()))
+    }
+
+    fn load_sick_days(&self) -> Result<Option<SickDays>, FlexiError> {
+        load_file::<SickDays>(&self.dir.sick_days())
+    }
+
+    fn save_sick_days(&self, sick_days: &SickDays) -> Result<(), FlexiError> {
+        sick_days.save_file(&self.dir.sick_days())
+    }
+
+    fn load_journal(&self) -> Result<Vec<JournalEntry>, FlexiError> {
+        let path = self.dir.journal();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(FlexiError::io(&path, e)),
+        };
+        let mut entries = Vec::new();
+        let lines: Vec<&str> = content.lines().collect();
+        for (idx, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                // cut short while appended, dropped so the next entry starts on its own line
+                Err(_) if idx + 1 == lines.len() && !content.ends_with('\n') => {
+                    let good: String = lines[..idx].iter().map(|l| format!("{}\n", l)).collect();
+                    write_file(&path, good.as_bytes())?;
+                }
+                Err(e) => {
+                    return Err(FlexiError::json(&format!("{} line {}", path, idx + 1), e));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    fn append_journal(&self, entry: &JournalEntry) -> Result<(), FlexiError> {
+        let path = self.dir.journal();
+        let line = format!("{}\n", serde_json::to_string(entry).unwrap());
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| {
+                file.write_all(line.as_bytes())?;
+                file.sync_all()
+            })
+            .map_err(|e| FlexiError::io(&path, e))
+    }
+
+    fn files(&self) -> Result<BTreeMap<String, String>, FlexiError> {
+        let root = self.dir.root().to_string_lossy();
+        let entries = match fs::read_dir(self.dir.root()) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(FlexiError::io(&root, e)),
+        };
+        let mut files = BTreeMap::new();
+        for entry in entries {
+            let path = entry.map_err(|e| FlexiError::io(&root, e))?.path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let is_json = path.extension().is_some_and(|ext| ext == "json");
+            if !path.is_file() || !(is_json || DataFile::parse(&name) == Some(DataFile::Journal)) {
+                continue;
+            }
+            let content = fs::read_to_string(&path)
+                .map_err(|e| FlexiError::io(&path.to_string_lossy(), e))?;
+            files.insert(name, content);
+        }
+        Ok(files)
+    }
+
+    fn replace_files(&self, files: &BTreeMap<String, String>) -> Result<(), FlexiError> {
+        for (name, content) in files.iter() {
+            write_file(
+                &self.dir.root().join(name).to_string_lossy(),
+                content.as_bytes(),
+            )?;
+        }
+        // files that are not data are left alone
+        for name in self.files()?.keys() {
+            if !files.contains_key(name) && DataFile::parse(name).is_some() {
+                let path = self.dir.root().join(name).to_string_lossy().into_owned();
+                fs::remove_file(&path).map_err(|e| FlexiError::io(&path, e))?;
+            }
+        }
+        Ok(())
+    }
+}
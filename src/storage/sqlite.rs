@@ -0,0 +1,590 @@
+use super::{Kind, Storage};
+use crate::datadir::{DataDir, DataFile};
+use crate::error::FlexiError;
+use crate::savable::{write_file, Savable};
+use crate::settings::Settings;
+use crate::timedata::{DayStatus, DaysOff, FlexDay, FlexMonth, FlexWeek, JournalEntry, SickDays};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+// statements upgrading the tables by one version, the version is their count and is kept
+// in `PRAGMA user_version`
+const MIGRATIONS: &[&str] = &[SCHEMA, JOURNAL, NOTES, HALF_DAYS];
+
+const SCHEMA: &str = "
+CREATE TABLE settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    data TEXT NOT NULL
+);
+CREATE TABLE months (
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL,
+    one_week_goal INTEGER NOT NULL,
+    week_goals TEXT NOT NULL,
+    balance INTEGER NOT NULL,
+    PRIMARY KEY (year, month)
+);
+-- each day belongs to the month whose grid holds it
+CREATE TABLE days (
+    date TEXT PRIMARY KEY,
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX days_month ON days (year, month);
+CREATE INDEX days_status ON days (status);
+CREATE TABLE days_off (
+    year INTEGER PRIMARY KEY,
+    holidays_left REAL NOT NULL
+);
+-- rolling list kept by DaysOff, a JSON list of dates
+CREATE TABLE sick_days (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    dates TEXT NOT NULL
+);";
+
+const JOURNAL: &str = "
+CREATE TABLE journal (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    date TEXT NOT NULL,
+    entry TEXT NOT NULL
+);
+CREATE INDEX journal_date ON journal (date);";
+
+const NOTES: &str = "
+ALTER TABLE days ADD COLUMN note TEXT;
+UPDATE days SET note = json_extract(data, '$.note');";
+
+// half days starting in the afternoon had their morning off
+const HALF_DAYS: &str = "
+UPDATE days SET status = CASE
+    WHEN json_extract(data, '$.sessions[0].start') >= '12:00' THEN 'HalfAm'
+    ELSE 'HalfPm' END
+WHERE status = 'Half';
+UPDATE days SET data = json_set(data, '$.status', status)
+WHERE json_extract(data, '$.status') = 'Half';";
+
+/// Every data in one SQLite database, days are rows of their own
+pub struct Sqlite {
+    dir: DataDir,
+    path: String,
+    conn: Mutex<Connection>,
+}
+
+fn status_str(status: DayStatus) -> String {
+    match serde_json::to_value(status) {
+        Ok(serde_json::Value::String(s)) => s,
+        _ => format!("{:?}", status),
+    }
+}
+
+impl Sqlite {
+    /// open the database of `dir`, created if needed
+    pub fn open(dir: DataDir) -> Result<Sqlite, FlexiError> {
+        let path = dir.database();
+        let conn = Connection::open(&path).map_err(|e| FlexiError::database(&path, e))?;
+        let db = Sqlite {
+            dir,
+            path,
+            conn: Mutex::new(conn),
+        };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn migrate(&self) -> Result<(), FlexiError> {
+        let mut conn = self.conn.lock().unwrap();
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| self.error(e))?;
+        if version > MIGRATIONS.len() {
+            return Err(FlexiError::Schema {
+                path: self.path.clone(),
+                msg: format!(
+                    "schema version {} is newer than this program's {}, please update",
+                    version,
+                    MIGRATIONS.len()
+                ),
+            });
+        }
+        for (from, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction().map_err(|e| self.error(e))?;
+            tx.execute_batch(migration)
+                .and_then(|_| tx.pragma_update(None, "user_version", from + 1))
+                .and_then(|_| tx.commit())
+                .map_err(|e| self.error(e))?;
+        }
+        Ok(())
+    }
+
+    fn error(&self, e: rusqlite::Error) -> FlexiError {
+        FlexiError::database(&self.path, e)
+    }
+
+    // JSON column of a row, `what` names it in errors
+    fn parse<T: serde::de::DeserializeOwned>(
+        &self,
+        what: &str,
+        json: &str,
+    ) -> Result<T, FlexiError> {
+        serde_json::from_str(json)
+            .map_err(|e| FlexiError::json(&format!("{} {}", self.path, what), e))
+    }
+
+    fn write_settings(&self, tx: &Transaction, settings: &Settings) -> Result<(), FlexiError> {
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (id, data) VALUES (1, ?1)",
+            params![settings.to_json()],
+        )
+        .map_err(|e| self.error(e))?;
+        Ok(())
+    }
+
+    fn write_month(&self, tx: &Transaction, month: &FlexMonth) -> Result<(), FlexiError> {
+        let week_goals = serde_json::to_string(&month.week_goals).unwrap();
+        let name = format!(
+            "{} {}",
+            self.path,
+            DataFile::Month(month.year, month.month).name()
+        );
+        month.validate(&name)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO months (year, month, one_week_goal, week_goals, balance)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                month.year,
+                month.month,
+                month.one_week_goal,
+                week_goals,
+                month.balance
+            ],
+        )
+        .and_then(|_| {
+            tx.execute(
+                "DELETE FROM days WHERE year = ?1 AND month = ?2",
+                params![month.year, month.month],
+            )
+        })
+        .map_err(|e| self.error(e))?;
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO days (date, year, month, status, data, note)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(|e| self.error(e))?;
+        for day in month.weeks.iter().flat_map(|w| w.days.iter()) {
+            insert
+                .execute(params![
+                    day.date,
+                    month.year,
+                    month.month,
+                    status_str(day.status),
+                    serde_json::to_string(day).unwrap(),
+                    day.note
+                ])
+                .map_err(|e| self.error(e))?;
+        }
+        Ok(())
+    }
+
+    fn write_days_off(&self, tx: &Transaction, days_off: &DaysOff) -> Result<(), FlexiError> {
+        tx.execute(
+            "INSERT OR REPLACE INTO days_off (year, holidays_left) VALUES (?1, ?2)",
+            params![days_off.year(), days_off.holidays_left as f64],
+        )
+        .map_err(|e| self.error(e))?;
+        Ok(())
+    }
+
+    fn write_sick_days(&self, tx: &Transaction, sick_days: &SickDays) -> Result<(), FlexiError> {
+        tx.execute(
+            "INSERT OR REPLACE INTO sick_days (id, dates) VALUES (1, ?1)",
+            params![serde_json::to_string(sick_days).unwrap()],
+        )
+        .map_err(|e| self.error(e))?;
+        Ok(())
+    }
+
+    // run `f` in a transaction, nothing is written if it fails
+    fn write<F>(&self, f: F) -> Result<(), FlexiError>
+    where
+        F: FnOnce(&Transaction) -> Result<(), FlexiError>,
+    {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| self.error(e))?;
+        f(&tx)?;
+        tx.commit().map_err(|e| self.error(e))
+    }
+
+    fn write_journal(&self, tx: &Transaction, entry: &JournalEntry) -> Result<(), FlexiError> {
+        tx.execute(
+            "INSERT INTO journal (date, entry) VALUES (?1, ?2)",
+            params![entry.date(), serde_json::to_string(entry).unwrap()],
+        )
+        .map_err(|e| self.error(e))?;
+        Ok(())
+    }
+
+    fn days_off_years(&self) -> Result<Vec<i32>, FlexiError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT year FROM days_off ORDER BY year")
+            .map_err(|e| self.error(e))?;
+        let years = stmt
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| self.error(e))?;
+        Ok(years)
+    }
+}
+
+impl Storage for Sqlite {
+    fn kind(&self) -> Kind {
+        Kind::Sqlite
+    }
+
+    fn dir(&self) -> &DataDir {
+        &self.dir
+    }
+
+    fn load_settings(&self) -> Result<Option<Settings>, FlexiError> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT data FROM settings WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|e| self.error(e))?;
+        match data {
+            Some(data) => Ok(Some(Settings::read(&self.path, &data)?.0)),
+            None => Ok(None),
+        }
+    }
+
+    fn save_settings(&self, settings: &Settings) -> Result<(), FlexiError> {
+        self.write(|tx| self.write_settings(tx, settings))
+    }
+
+    fn load_month(&self, year: i32, month: u32) -> Result<Option<FlexMonth>, FlexiError> {
+        let conn = self.conn.lock().unwrap();
+        let row: Option<(i64, String, i64)> = conn
+            .query_row(
+                "SELECT one_week_goal, week_goals, balance FROM months WHERE year = ?1 AND month = ?2",
+                params![year, month],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .map_err(|e| self.error(e))?;
+        let (one_week_goal, week_goals, balance) = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let mut stmt = conn
+            .prepare("SELECT data FROM days WHERE year = ?1 AND month = ?2 ORDER BY date")
+            .map_err(|e| self.error(e))?;
+        let data: Vec<String> = stmt
+            .query_map(params![year, month], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| self.error(e))?;
+        let name = DataFile::Month(year, month).name();
+        let mut days = Vec::new();
+        for day in data.iter() {
+            days.push(self.parse::<FlexDay>(&name, day)?);
+        }
+        let weeks = days
+            .chunks(7)
+            .map(|week| <[FlexDay; 7]>::try_from(week.to_vec()).map(FlexWeek::new))
+            .collect::<Result<Vec<FlexWeek>, _>>()
+            .map_err(|_| {
+                FlexiError::Validation(format!("{} {} has an incomplete week", self.path, name))
+            })?;
+        let m = FlexMonth {
+            weeks,
+            year,
+            month,
+            one_week_goal,
+            week_goals: self.parse(&name, &week_goals)?,
+            balance,
+        };
+        m.validate(&format!("{} {}", self.path, name))?;
+        Ok(Some(m))
+    }
+
+    fn save_month(&self, month: &FlexMonth) -> Result<(), FlexiError> {
+        self.write(|tx| self.write_month(tx, month))
+    }
+
+    fn saved_months(&self) -> Result<Vec<(i32, u32)>, FlexiError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT year, month FROM months ORDER BY year, month")
+            .map_err(|e| self.error(e))?;
+        let months = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| self.error(e))?;
+        Ok(months)
+    }
+
+    fn month_balances(&self) -> Result<BTreeMap<(i32, u32), i64>, FlexiError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT year, month, balance FROM months ORDER BY year, month")
+            .map_err(|e| self.error(e))?;
+        let balances = stmt
+            .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| self.error(e))?;
+        Ok(balances)
+    }
+
+    fn find_days(&self, status: DayStatus) -> Result<Vec<NaiveDate>, FlexiError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT date FROM days WHERE status = ?1 ORDER BY date")
+            .map_err(|e| self.error(e))?;
+        let dates = stmt
+            .query_map(params![status_str(status)], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| self.error(e))?;
+        Ok(dates)
+    }
+
+    fn search_notes(&self, text: &str) -> Result<Vec<FlexDay>, FlexiError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT data FROM days WHERE note IS NOT NULL ORDER BY date")
+            .map_err(|e| self.error(e))?;
+        let data: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| self.error(e))?;
+        let mut days = Vec::new();
+        for d in data {
+            let day: FlexDay = self.parse("days", &d)?;
+            if day.note_matches(text) {
+                days.push(day);
+            }
+        }
+        Ok(days)
+    }
+
+    fn load_days_off(&self, year: i32) -> Result<Option<DaysOff>, FlexiError> {
+        let holidays_left: Option<f64> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT holidays_left FROM days_off WHERE year = ?1",
+                params![year],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| self.error(e))?;
+        Ok(holidays_left.map(|left| DaysOff::with_holidays_left(year, left as f32)))
+    }
+
+    fn save_days_off(&self, days_off: &DaysOff) -> Result<(), FlexiError> {
+        self.write(|tx| self.write_days_off(tx, days_off))
+    }
+
+    fn load_sick_days(&self) -> Result<Option<SickDays>, FlexiError> {
+        let dates: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT dates FROM sick_days WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|e| self.error(e))?;
+        match dates {
+            Some(dates) => Ok(Some(self.parse(&DataFile::SickDays.name(), &dates)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn save_sick_days(&self, sick_days: &SickDays) -> Result<(), FlexiError> {
+        self.write(|tx| self.write_sick_days(tx, sick_days))
+    }
+
+    fn load_journal(&self) -> Result<Vec<JournalEntry>, FlexiError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT entry FROM journal ORDER BY id")
+            .map_err(|e| self.error(e))?;
+        let data: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| self.error(e))?;
+        let name = DataFile::Journal.name();
+        data.iter().map(|entry| self.parse(&name, entry)).collect()
+    }
+
+    fn append_journal(&self, entry: &JournalEntry) -> Result<(), FlexiError> {
+        self.write(|tx| self.write_journal(tx, entry))
+    }
+
+    fn files(&self) -> Result<BTreeMap<String, String>, FlexiError> {
+        let mut files = BTreeMap::new();
+        let mut add = |file: DataFile, json: String| {
+            files.insert(file.name(), format!("{}\n", json));
+        };
+        if let Some(settings) = self.load_settings()? {
+            add(DataFile::Settings, settings.to_json());
+        }
+        for (year, month) in self.saved_months()? {
+            if let Some(m) = self.load_month(year, month)? {
+                add(DataFile::Month(year, month), m.to_json());
+            }
+        }
+        for year in self.days_off_years()? {
+            if let Some(days_off) = self.load_days_off(year)? {
+                add(DataFile::DaysOff(year), days_off.to_json());
+            }
+        }
+        if let Some(sick_days) = self.load_sick_days()? {
+            add(DataFile::SickDays, sick_days.to_json());
+        }
+        let journal: String = self
+            .load_journal()?
+            .iter()
+            .map(|entry| format!("{}\n", serde_json::to_string(entry).unwrap()))
+            .collect();
+        if !journal.is_empty() {
+            files.insert(DataFile::Journal.name(), journal);
+        }
+        let closures = self.dir.closures();
+        if Path::new(&closures).exists() {
+            let content =
+                fs::read_to_string(&closures).map_err(|e| FlexiError::io(&closures, e))?;
+            files.insert(DataFile::Closures.name(), content);
+        }
+        Ok(files)
+    }
+
+    fn replace_files(&self, files: &BTreeMap<String, String>) -> Result<(), FlexiError> {
+        self.write(|tx| {
+            tx.execute_batch(
+                "DELETE FROM settings; DELETE FROM months; DELETE FROM days;
+                 DELETE FROM days_off; DELETE FROM sick_days; DELETE FROM journal;",
+            )
+            .map_err(|e| self.error(e))?;
+            for (name, content) in files.iter() {
+                // other JSON files of the data dir, e.g. editor backups, stay as files
+                let file = match DataFile::parse(name) {
+                    Some(file) => file,
+                    None => continue,
+                };
+                match file {
+                    DataFile::Settings => {
+                        self.write_settings(tx, &Settings::read(name, content)?.0)?
+                    }
+                    DataFile::Month(..) => {
+                        self.write_month(tx, &FlexMonth::read(name, content)?.0)?
+                    }
+                    DataFile::DaysOff(..) => {
+                        self.write_days_off(tx, &DaysOff::read(name, content)?.0)?
+                    }
+                    DataFile::SickDays => {
+                        self.write_sick_days(tx, &SickDays::read(name, content)?.0)?
+                    }
+                    DataFile::Journal => {
+                        for (idx, line) in content.lines().enumerate() {
+                            let entry = serde_json::from_str(line).map_err(|e| {
+                                FlexiError::json(&format!("{} line {}", name, idx + 1), e)
+                            })?;
+                            self.write_journal(tx, &entry)?;
+                        }
+                    }
+                    DataFile::Closures => {}
+                }
+            }
+            Ok(())
+        })?;
+        let closures = self.dir.closures();
+        match files.get(&DataFile::Closures.name()) {
+            Some(content) => write_file(&closures, content.as_bytes()),
+            None if Path::new(&closures).exists() => {
+                fs::remove_file(&closures).map_err(|e| FlexiError::io(&closures, e))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{journal_entry, test_dir};
+    use super::*;
+
+    #[test]
+    fn month_test() {
+        let db = Sqlite::open(test_dir("sqlite_month")).unwrap();
+        let settings = Settings::default();
+        assert_eq!(db.load_month(2019, 5).unwrap(), None);
+        let mut may = FlexMonth::new(2019, 5, &settings);
+        let date = NaiveDate::from_ymd_opt(2019, 5, 14).unwrap();
+        let mut day = may.get_week_with_day(date).unwrap().0.clone();
+        day.status = DayStatus::Sick;
+        day.set_note("Flu");
+        may.update_day(day.clone());
+        may.update_balance(&settings);
+        db.save_month(&may).unwrap();
+        db.save_month(&FlexMonth::new(2019, 6, &settings)).unwrap();
+
+        assert_eq!(db.load_month(2019, 5).unwrap(), Some(may.clone()));
+        assert_eq!(db.saved_months().unwrap(), vec![(2019, 5), (2019, 6)]);
+        assert_eq!(db.month_balances().unwrap()[&(2019, 5)], may.balance);
+        assert_eq!(db.find_days(DayStatus::Sick).unwrap(), vec![date]);
+        assert_eq!(db.search_notes("flu").unwrap(), vec![day]);
+        assert!(db.search_notes("cold").unwrap().is_empty());
+    }
+
+    #[test]
+    fn journal_test() {
+        let dir = test_dir("sqlite_journal");
+        // database of the first version, without journal
+        let conn = Connection::open(dir.database()).unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        drop(conn);
+
+        let db = Sqlite::open(dir).unwrap();
+        let entry = journal_entry(NaiveDate::from_ymd_opt(2019, 5, 14).unwrap());
+        db.append_journal(&entry).unwrap();
+        assert_eq!(db.load_journal().unwrap(), vec![entry]);
+        let files = db.files().unwrap();
+        assert_eq!(files["journal.jsonl"].lines().count(), 1);
+        db.replace_files(&files).unwrap();
+        assert_eq!(db.files().unwrap(), files);
+    }
+
+    #[test]
+    fn days_off_test() {
+        let dir = test_dir("sqlite_daysoff");
+        let db = Sqlite::open(dir.clone()).unwrap();
+        assert!(db.load_days_off(2019).unwrap().is_none());
+        assert!(db.load_sick_days().unwrap().is_none());
+        db.save_days_off(&DaysOff::with_holidays_left(2019, 12.5))
+            .unwrap();
+        let sick_days = vec![NaiveDate::from_ymd_opt(2019, 5, 14).unwrap()];
+        db.save_sick_days(&sick_days).unwrap();
+        drop(db);
+
+        // data is still there once reopened
+        let db = Sqlite::open(dir).unwrap();
+        assert_eq!(db.load_days_off(2019).unwrap().unwrap().holidays_left, 12.5);
+        assert_eq!(db.load_sick_days().unwrap(), Some(sick_days));
+        assert!(db.replace_files(&BTreeMap::new()).is_ok());
+        assert!(db.load_days_off(2019).unwrap().is_none());
+    }
+}
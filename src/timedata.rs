@@ -1,33 +1,46 @@
 pub use self::flexday::FlexDay;
-pub use self::flexday::HOLIDAY_DURATION;
 pub use self::flexweek::FlexWeek;
 pub use self::flexmonth::FlexMonth;
 pub use self::flexmonth::find_first_monday_of_grid;
 pub use self::flexmonth::find_last_sunday_for;
+pub use self::flexmonth::grid_month_for;
 pub use self::flexmonth::next_month;
 pub use self::flexmonth::prev_month;
 pub use self::naivedate_iterator::NaiveDateIter;
 pub use self::flexday::DayStatus;
 pub use self::daysoff::DaysOff;
 pub use self::daysoff::SickDays;
+pub use self::calendar::HolidayCalendar;
+pub use self::compliance::{Compliance, Violation};
+pub use self::entitlement::{Entitlement, HolidaysTaken, LeaveYear};
+pub use self::ledger::Ledger;
+pub use self::leave::LeaveTimes;
+pub use self::journal::{Action, Journal, JournalEntry};
+pub use self::projects::ProjectReport;
+pub use self::daytype::{
+    day_credit, day_type, Allowance, Color, Credit, DayType, DayTypes,
+};
 
 mod flexday;
 mod flexweek;
 mod flexmonth;
 mod naivedate_iterator;
 mod daysoff;
+mod calendar;
+mod compliance;
+mod entitlement;
+mod ledger;
+mod leave;
+mod journal;
+mod projects;
+mod daytype;
 
-use std::fs;
-use std::path::Path;
+use crate::datadir::data_dir;
 use chrono::Weekday;
 
 pub fn create_data_dir() {
-    let dir = Path::new("./data");
-    if !dir.exists() {
-        match fs::create_dir_all(dir) {
-            Err(why) => println!("failed to create data dir: {}", why),
-            _ => {}
-        }
+    if let Err(why) = data_dir().create() {
+        println!("failed to create data dir: {}", why);
     }
 }
 
@@ -60,3 +73,13 @@ pub fn month_to_string(m: u32) -> &'static str {
         _ => "???",
     }
 }
+
+/// `minutes` as hours and minutes, e.g. "-07:30"
+pub fn minutes_to_string(minutes: i64) -> String {
+    format!(
+        "{}{:02}:{:02}",
+        if minutes < 0 { "-" } else { "" },
+        (minutes / 60).abs(),
+        (minutes % 60).abs()
+    )
+}
@@ -0,0 +1,352 @@
+use crate::datadir::data_dir;
+use crate::error::FlexiError;
+use crate::savable::{load_file, Migration, Savable};
+use crate::settings::Settings;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What to do when a fixed date holiday falls on a weekend
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub enum Observance {
+    /// lost
+    #[default]
+    Keep,
+    /// moved to the next working day not already off (UK style)
+    NextWorkday,
+    /// Saturday moves to Friday, Sunday to Monday (US style)
+    NearestWeekday,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "rule")]
+pub enum HolidayRule {
+    /// same date every year
+    Fixed {
+        month: u32,
+        day: u32,
+        #[serde(default)]
+        observed: Observance,
+    },
+    /// number of days after Easter Sunday, before it if negative
+    Easter { offset: i64 },
+    /// nth weekday of the month, counted from the end of the month if negative
+    NthWeekday {
+        month: u32,
+        weekday: Weekday,
+        n: i32,
+    },
+    /// one-off range of days, both included
+    Range { from: NaiveDate, to: NaiveDate },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Holiday {
+    pub name: String,
+    #[serde(flatten)]
+    pub rule: HolidayRule,
+}
+
+pub type Closures = Vec<Holiday>;
+
+// written by hand, not versioned
+impl Savable for Closures {
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+/// Easter Sunday of the given year (anonymous Gregorian algorithm)
+pub fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: i32) -> Option<NaiveDate> {
+    if n > 0 {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let shift =
+            (7 + weekday.num_days_from_monday() - first.weekday().num_days_from_monday()) % 7;
+        let date = first + Duration::days(shift as i64 + 7 * (n as i64 - 1));
+        if date.month() == month {
+            Some(date)
+        } else {
+            None
+        }
+    } else if n < 0 {
+        let (y, m) = super::next_month(year, month);
+        let last = NaiveDate::from_ymd_opt(y, m, 1)?.pred_opt().unwrap();
+        let shift =
+            (7 + last.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        let date = last - Duration::days(shift as i64 + 7 * (-n as i64 - 1));
+        if date.month() == month {
+            Some(date)
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+impl HolidayRule {
+    /// dates of the rule in `year`, before any weekend observance
+    fn dates_in(&self, year: i32) -> Vec<NaiveDate> {
+        match *self {
+            HolidayRule::Fixed { month, day, .. } => NaiveDate::from_ymd_opt(year, month, day)
+                .into_iter()
+                .collect(),
+            HolidayRule::Easter { offset } => vec![easter_sunday(year) + Duration::days(offset)],
+            HolidayRule::NthWeekday { month, weekday, n } => {
+                nth_weekday(year, month, weekday, n).into_iter().collect()
+            }
+            HolidayRule::Range { from, to } => super::NaiveDateIter::new(from, to)
+                .filter(|d| d.year() == year)
+                .collect(),
+        }
+    }
+
+    fn observance(&self) -> Observance {
+        match *self {
+            HolidayRule::Fixed { observed, .. } => observed,
+            _ => Observance::Keep,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HolidayCalendar {
+    pub holidays: Vec<Holiday>,
+}
+
+fn fixed(name: &str, month: u32, day: u32, observed: Observance) -> Holiday {
+    Holiday {
+        name: name.to_string(),
+        rule: HolidayRule::Fixed {
+            month,
+            day,
+            observed,
+        },
+    }
+}
+
+fn easter(name: &str, offset: i64) -> Holiday {
+    Holiday {
+        name: name.to_string(),
+        rule: HolidayRule::Easter { offset },
+    }
+}
+
+fn nth(name: &str, month: u32, weekday: Weekday, n: i32) -> Holiday {
+    Holiday {
+        name: name.to_string(),
+        rule: HolidayRule::NthWeekday { month, weekday, n },
+    }
+}
+
+impl HolidayCalendar {
+    /// built-in public holidays of a country: "fr", "de", "gb" (England and Wales) or "us"
+    pub fn builtin(country: &str) -> Option<HolidayCalendar> {
+        use self::Observance::*;
+        let holidays = match country {
+            "fr" => vec![
+                fixed("Jour de l'an", 1, 1, Keep),
+                easter("Lundi de Pâques", 1),
+                fixed("Fête du travail", 5, 1, Keep),
+                fixed("Victoire 1945", 5, 8, Keep),
+                easter("Ascension", 39),
+                easter("Lundi de Pentecôte", 50),
+                fixed("Fête nationale", 7, 14, Keep),
+                fixed("Assomption", 8, 15, Keep),
+                fixed("Toussaint", 11, 1, Keep),
+                fixed("Armistice 1918", 11, 11, Keep),
+                fixed("Noël", 12, 25, Keep),
+            ],
+            "de" => vec![
+                fixed("Neujahr", 1, 1, Keep),
+                easter("Karfreitag", -2),
+                easter("Ostermontag", 1),
+                fixed("Tag der Arbeit", 5, 1, Keep),
+                easter("Christi Himmelfahrt", 39),
+                easter("Pfingstmontag", 50),
+                fixed("Tag der Deutschen Einheit", 10, 3, Keep),
+                fixed("1. Weihnachtstag", 12, 25, Keep),
+                fixed("2. Weihnachtstag", 12, 26, Keep),
+            ],
+            "gb" => vec![
+                fixed("New Year's Day", 1, 1, NextWorkday),
+                easter("Good Friday", -2),
+                easter("Easter Monday", 1),
+                nth("Early May bank holiday", 5, Weekday::Mon, 1),
+                nth("Spring bank holiday", 5, Weekday::Mon, -1),
+                nth("Summer bank holiday", 8, Weekday::Mon, -1),
+                fixed("Christmas Day", 12, 25, NextWorkday),
+                fixed("Boxing Day", 12, 26, NextWorkday),
+            ],
+            "us" => vec![
+                fixed("New Year's Day", 1, 1, NearestWeekday),
+                nth("Martin Luther King Jr. Day", 1, Weekday::Mon, 3),
+                nth("Washington's Birthday", 2, Weekday::Mon, 3),
+                nth("Memorial Day", 5, Weekday::Mon, -1),
+                fixed("Juneteenth", 6, 19, NearestWeekday),
+                fixed("Independence Day", 7, 4, NearestWeekday),
+                nth("Labor Day", 9, Weekday::Mon, 1),
+                nth("Columbus Day", 10, Weekday::Mon, 2),
+                fixed("Veterans Day", 11, 11, NearestWeekday),
+                nth("Thanksgiving Day", 11, Weekday::Thu, 4),
+                fixed("Christmas Day", 12, 25, NearestWeekday),
+            ],
+            _ => return None,
+        };
+        Some(HolidayCalendar { holidays })
+    }
+
+    pub fn closures_filename() -> String {
+        data_dir().closures()
+    }
+
+    /// built-in calendar selected in settings plus the company closures from `closures.json`
+    pub fn load(settings: &Settings) -> Result<HolidayCalendar, FlexiError> {
+        let mut calendar =
+            HolidayCalendar::builtin(&settings.public_holidays.country).unwrap_or_default();
+        if let Some(closures) = load_file::<Closures>(&HolidayCalendar::closures_filename())? {
+            calendar.holidays.extend(closures);
+        }
+        Ok(calendar)
+    }
+
+    /// every day off of the calendar in `year` with its name, weekend observance applied
+    pub fn days_in(&self, year: i32) -> BTreeMap<NaiveDate, String> {
+        let mut days = BTreeMap::new();
+        let mut to_observe = Vec::new();
+        for h in &self.holidays {
+            for date in h.rule.dates_in(year) {
+                match h.rule.observance() {
+                    Observance::Keep => {
+                        days.entry(date).or_insert_with(|| h.name.clone());
+                    }
+                    observance => to_observe.push((date, observance, &h.name)),
+                }
+            }
+        }
+        // in date order so that Christmas is moved before Boxing Day
+        to_observe.sort_by_key(|&(date, _, _)| date);
+        for (date, observance, name) in to_observe {
+            let observed = match observance {
+                Observance::NearestWeekday => match date.weekday() {
+                    Weekday::Sat => date.pred_opt().unwrap(),
+                    Weekday::Sun => date.succ_opt().unwrap(),
+                    _ => date,
+                },
+                _ => {
+                    let mut observed = date;
+                    while is_weekend(observed) || days.contains_key(&observed) {
+                        observed = observed.succ_opt().unwrap();
+                    }
+                    observed
+                }
+            };
+            days.entry(observed).or_insert_with(|| name.clone());
+        }
+        days
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easter_test() {
+        assert_eq!(
+            easter_sunday(2019),
+            NaiveDate::from_ymd_opt(2019, 4, 21).unwrap()
+        );
+        assert_eq!(
+            easter_sunday(2020),
+            NaiveDate::from_ymd_opt(2020, 4, 12).unwrap()
+        );
+        assert_eq!(
+            easter_sunday(2024),
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap()
+        );
+        assert_eq!(
+            easter_sunday(2038),
+            NaiveDate::from_ymd_opt(2038, 4, 25).unwrap()
+        );
+    }
+
+    #[test]
+    fn nth_weekday_test() {
+        // last Monday of May 2019
+        assert_eq!(
+            nth_weekday(2019, 5, Weekday::Mon, -1),
+            Some(NaiveDate::from_ymd_opt(2019, 5, 27).unwrap())
+        );
+        // 4th Thursday of November 2019
+        assert_eq!(
+            nth_weekday(2019, 11, Weekday::Thu, 4),
+            Some(NaiveDate::from_ymd_opt(2019, 11, 28).unwrap())
+        );
+        assert_eq!(nth_weekday(2019, 2, Weekday::Mon, 5), None);
+    }
+
+    #[test]
+    fn builtin_test() {
+        let fr = HolidayCalendar::builtin("fr").unwrap().days_in(2019);
+        assert_eq!(fr.len(), 11);
+        assert_eq!(
+            fr.get(&NaiveDate::from_ymd_opt(2019, 5, 30).unwrap()),
+            Some(&"Ascension".to_string())
+        );
+
+        // Christmas 2021 on Saturday, Boxing day on Sunday
+        let gb = HolidayCalendar::builtin("gb").unwrap().days_in(2021);
+        assert!(gb.contains_key(&NaiveDate::from_ymd_opt(2021, 12, 27).unwrap()));
+        assert!(gb.contains_key(&NaiveDate::from_ymd_opt(2021, 12, 28).unwrap()));
+        assert!(!gb.contains_key(&NaiveDate::from_ymd_opt(2021, 12, 25).unwrap()));
+
+        // Independence day 2020 on Saturday
+        let us = HolidayCalendar::builtin("us").unwrap().days_in(2020);
+        assert!(us.contains_key(&NaiveDate::from_ymd_opt(2020, 7, 3).unwrap()));
+
+        assert!(HolidayCalendar::builtin("xx").is_none());
+    }
+
+    #[test]
+    fn closures_from_json_test() {
+        let json = r#"[
+  { "name": "Winter closure", "rule": "Range", "from": "2019-12-23", "to": "2020-01-03" },
+  { "name": "Founder's day", "rule": "Fixed", "month": 6, "day": 14 }
+]"#;
+        let calendar = HolidayCalendar {
+            holidays: Closures::from_json(json).expect("should be a json"),
+        };
+        let days = calendar.days_in(2019);
+        assert_eq!(days.len(), 10);
+        let weekdays_off_in = |year| {
+            calendar
+                .days_in(year)
+                .keys()
+                .filter(|&&date| !is_weekend(date) && date.year() == year)
+                .count()
+        };
+        assert_eq!(weekdays_off_in(2019), 8);
+        assert_eq!(weekdays_off_in(2020), 3);
+    }
+}
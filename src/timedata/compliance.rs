@@ -0,0 +1,298 @@
+use crate::error::FlexiError;
+use crate::settings::Settings;
+use crate::storage::storage;
+use crate::timedata::{minutes_to_string, FlexDay, FlexMonth};
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Timelike, Weekday};
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+/// Working time rule broken by a day or a week
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    LongDay,
+    ShortRest,
+    MissingBreak,
+    LongWeeks,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    // the day, or the Monday of the week for `Rule::LongWeeks`
+    pub date: NaiveDate,
+    pub rule: Rule,
+    // minutes worked, rested or paused
+    pub minutes: i64,
+    // threshold of the settings
+    pub limit: i64,
+}
+
+impl Violation {
+    /// what is wrong, without the date
+    pub fn describe(&self) -> String {
+        let (what, than) = match self.rule {
+            Rule::LongDay => ("worked", "more"),
+            Rule::ShortRest => ("rest", "less"),
+            Rule::MissingBreak => ("break", "less"),
+            Rule::LongWeeks => ("week average", "more"),
+        };
+        format!(
+            "{} {}, {} than {}",
+            what,
+            minutes_to_string(self.minutes),
+            than,
+            minutes_to_string(self.limit)
+        )
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.date.format("%a %d/%m/%Y"), self.describe())
+    }
+}
+
+/// Checks of the days worked against the thresholds of the settings
+pub struct Compliance {
+    // the thresholds, and the day types telling the worked days
+    settings: Settings,
+    // every day of the saved months
+    days: BTreeMap<NaiveDate, FlexDay>,
+}
+
+impl Compliance {
+    pub fn new(settings: &Settings) -> Compliance {
+        Compliance {
+            settings: settings.clone(),
+            days: BTreeMap::new(),
+        }
+    }
+
+    /// checks of the saved months
+    pub fn load(settings: &Settings) -> Result<Compliance, FlexiError> {
+        let mut compliance = Compliance::new(settings);
+        for (year, month) in storage().saved_months()? {
+            if let Some(m) = storage().load_month(year, month)? {
+                compliance.update(&m);
+            }
+        }
+        Ok(compliance)
+    }
+
+    /// take the days of `month` as they are, to be called each time it is saved
+    pub fn update(&mut self, month: &FlexMonth) {
+        for d in month.weeks.iter().flat_map(|w| w.days.iter()) {
+            if let Some(date) = d.date {
+                self.days.insert(date, d.clone());
+            }
+        }
+    }
+
+    /// rules broken from `from` to `to`, by date. Weeks are checked on their Monday
+    pub fn violations(&self, from: NaiveDate, to: NaiveDate) -> Vec<Violation> {
+        let mut found = Vec::new();
+        for (date, d) in self.days.range(from..=to) {
+            found.extend(self.check_day(d, self.days.get(&date.pred_opt().unwrap())));
+            if date.weekday() == Weekday::Mon {
+                found.extend(self.check_weeks(*date));
+            }
+        }
+        found.sort_by_key(|v| (v.date, v.rule));
+        found
+    }
+
+    fn check_day(&self, d: &FlexDay, prev: Option<&FlexDay>) -> Vec<Violation> {
+        let mut found = Vec::new();
+        let worked = d.worked_minutes(&self.settings);
+        let date = match d.date {
+            Some(date) if worked > 0 => date,
+            _ => return found,
+        };
+        let rules = &self.settings.compliance;
+        let mut flag = |rule, minutes, limit| {
+            found.push(Violation {
+                date,
+                rule,
+                minutes,
+                limit,
+            })
+        };
+        if rules.max_day > 0 && worked > rules.max_day {
+            flag(Rule::LongDay, worked, rules.max_day);
+        }
+        if let Some(prev) = prev.filter(|p| p.worked_minutes(&self.settings) > 0) {
+            let rest = Duration::days(1).num_minutes() - minute_of_day(prev.end())
+                + minute_of_day(d.start());
+            if rules.min_rest > 0 && rest < rules.min_rest {
+                flag(Rule::ShortRest, rest, rules.min_rest);
+            }
+        }
+        let pause = break_minutes(d);
+        if rules.min_break > 0 && worked > rules.break_after && pause < rules.min_break {
+            flag(Rule::MissingBreak, pause, rules.min_break);
+        }
+        found
+    }
+
+    // average of the week starting on `monday` and of the weeks before it, the weeks
+    // without saved days are left out
+    fn check_weeks(&self, monday: NaiveDate) -> Option<Violation> {
+        let rules = &self.settings.compliance;
+        if rules.max_average_week == 0 {
+            return None;
+        }
+        let first = monday - Duration::weeks(i64::from(rules.average_weeks) - 1);
+        let mut worked = 0;
+        let mut weeks = BTreeSet::new();
+        for (date, d) in self.days.range(first..monday + Duration::weeks(1)) {
+            worked += d.worked_minutes(&self.settings);
+            weeks.insert((*date - first).num_weeks());
+        }
+        let average = worked / weeks.len().max(1) as i64;
+        if average > rules.max_average_week {
+            Some(Violation {
+                date: monday,
+                rule: Rule::LongWeeks,
+                minutes: average,
+                limit: rules.max_average_week,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+fn minute_of_day(time: NaiveTime) -> i64 {
+    i64::from(time.num_seconds_from_midnight() / 60)
+}
+
+// the pause and the time between the sessions
+fn break_minutes(d: &FlexDay) -> i64 {
+    d.sessions
+        .windows(2)
+        .map(|pair| {
+            pair[1]
+                .start
+                .signed_duration_since(pair[0].end)
+                .num_minutes()
+        })
+        .filter(|&gap| gap > 0)
+        .fold(d.pause, |acc, gap| acc + gap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ComplianceSettings;
+    use crate::timedata::flexday::Session;
+
+    fn set_sessions(
+        month: &mut FlexMonth,
+        date: NaiveDate,
+        sessions: &[(u32, u32, u32, u32)],
+        pause: i64,
+    ) {
+        let (d, _, _) = month.get_week_with_day(date).unwrap();
+        let mut d = d.clone();
+        d.sessions = sessions
+            .iter()
+            .map(|&(h1, m1, h2, m2)| {
+                Session::new(
+                    NaiveTime::from_hms_opt(h1, m1, 0).unwrap(),
+                    NaiveTime::from_hms_opt(h2, m2, 0).unwrap(),
+                )
+            })
+            .collect();
+        d.pause = pause;
+        month.update_day(d);
+    }
+
+    // first week of May 2017 breaking each rule of the days once, 39:35 worked
+    fn may(settings: &Settings) -> FlexMonth {
+        let mut month = FlexMonth::new(2017, 5, settings);
+        set_sessions(
+            &mut month,
+            NaiveDate::from_ymd_opt(2017, 5, 1).unwrap(),
+            &[(8, 0, 19, 0)],
+            30,
+        );
+        set_sessions(
+            &mut month,
+            NaiveDate::from_ymd_opt(2017, 5, 3).unwrap(),
+            &[(9, 10, 12, 0), (12, 15, 16, 0)],
+            0,
+        );
+        set_sessions(
+            &mut month,
+            NaiveDate::from_ymd_opt(2017, 5, 4).unwrap(),
+            &[(12, 0, 20, 0)],
+            30,
+        );
+        set_sessions(
+            &mut month,
+            NaiveDate::from_ymd_opt(2017, 5, 5).unwrap(),
+            &[(6, 0, 14, 0)],
+            30,
+        );
+        month
+    }
+
+    #[test]
+    fn day_rules_test() {
+        let settings = Settings::default();
+        let mut compliance = Compliance::new(&settings);
+        compliance.update(&may(&settings));
+        let found: Vec<(u32, Rule, i64)> = compliance
+            .violations(
+                NaiveDate::from_ymd_opt(2017, 5, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2017, 5, 31).unwrap(),
+            )
+            .iter()
+            .map(|v| (v.date.day(), v.rule, v.minutes))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (1, Rule::LongDay, 630),
+                (3, Rule::MissingBreak, 15),
+                (5, Rule::ShortRest, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_weeks_test() {
+        let settings = Settings {
+            compliance: ComplianceSettings {
+                max_average_week: Duration::hours(37).num_minutes() + 30,
+                average_weeks: 2,
+                ..ComplianceSettings::default()
+            },
+            ..Settings::default()
+        };
+        let mut compliance = Compliance::new(&settings);
+        compliance.update(&may(&settings));
+        let weeks: Vec<(u32, i64)> = compliance
+            .violations(
+                NaiveDate::from_ymd_opt(2017, 5, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2017, 5, 31).unwrap(),
+            )
+            .iter()
+            .filter(|v| v.rule == Rule::LongWeeks)
+            .map(|v| (v.date.day(), v.minutes))
+            .collect();
+        // the week before May is not saved, the third one averages 37:10
+        assert_eq!(weeks, vec![(1, 2375), (8, 2302)]);
+        let v = Violation {
+            date: NaiveDate::from_ymd_opt(2017, 5, 1).unwrap(),
+            rule: Rule::LongWeeks,
+            minutes: 2375,
+            limit: 2250,
+        };
+        assert_eq!(
+            v.to_string(),
+            "Mon 01/05/2017 week average 39:35, more than 37:30"
+        );
+    }
+}
@@ -1,139 +1,133 @@
-use crate::savable::Savable;
+use crate::error::FlexiError;
+use crate::savable::{unchanged, wrap_list, Migration, Savable};
 use crate::settings::Settings;
+use crate::storage::storage;
 use crate::timedata::*;
-use chrono;
 use chrono::naive::NaiveDate;
 use chrono::Datelike;
-use failure::Error;
-use glob::glob;
 use serde_derive::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::prelude::*;
 
 pub type SickDays = Vec<NaiveDate>;
 
+// the holidays left are computed by `Entitlement`, the saved value is only informative
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DaysOff {
     year: i32,
     pub holidays_left: f32,
     #[serde(skip)] // sick days are in another standalone file
     pub sick_days: SickDays,
+    #[serde(skip)]
+    taken: HolidaysTaken,
+    #[serde(skip)]
+    leave_year: Option<LeaveYear>,
 }
 
-impl<'a> Savable<'a, DaysOff> for DaysOff {}
-impl<'a> Savable<'a, SickDays> for SickDays {}
+impl Savable for DaysOff {
+    const MIGRATIONS: &'static [Migration] = &[unchanged];
+}
+
+impl Savable for SickDays {
+    const MIGRATIONS: &'static [Migration] = &[wrap_list];
+}
 
 impl DaysOff {
+    /// days off of `year` without any holiday taken
     pub fn new(year: i32, settings: &Settings) -> DaysOff {
+        let mut daysoff = DaysOff::with_holidays_left(year, settings.holidays_per_year);
+        daysoff.refresh(settings);
+        daysoff
+    }
+
+    /// days off of `year` as saved, without sick days
+    pub fn with_holidays_left(year: i32, holidays_left: f32) -> DaysOff {
         DaysOff {
-            year: year,
-            holidays_left: settings.holidays_per_year,
+            year,
+            holidays_left,
             sick_days: SickDays::default(),
+            taken: HolidaysTaken::new(),
+            leave_year: None,
         }
     }
 
-    pub fn filename(year: i32) -> String {
-        format!("./data/{}_daysoff.json", year)
+    pub fn year(&self) -> i32 {
+        self.year
     }
 
-    pub fn save(&self) -> Result<(), Error> {
-        let mut file = File::create(DaysOff::filename(self.year))?;
-
-        file.write_all(self.to_json().as_bytes())?;
-        file.write_all("\n".as_bytes())?;
-
-        let mut file = File::create("./data/sickdays.json")?;
-        file.write_all(self.sick_days.to_json().as_bytes())?;
-        file.write_all("\n".as_bytes())?;
-        Ok(())
+    pub fn save(&self) -> Result<(), FlexiError> {
+        storage().save_days_off(self)?;
+        storage().save_sick_days(&self.sick_days)
     }
 
-    // TODO change to Result
-    pub fn load(year: i32, settings: &Settings) -> DaysOff {
-        let mut daysoff = match File::open(DaysOff::filename(year)) {
-            Err(_) => DaysOff::new(year, &settings),
-            Ok(mut file) => {
-                let mut json = String::new();
-                file.read_to_string(&mut json)
-                    .expect(&format!("Failed to read {}", DaysOff::filename(year)));
-                DaysOff::from_json(&json).expect(&format!(
-                    "Failed to deserialized {}",
-                    DaysOff::filename(year)
-                ))
-            }
+    pub fn load(year: i32, settings: &Settings) -> Result<DaysOff, FlexiError> {
+        let mut daysoff = match storage().load_days_off(year)? {
+            Some(daysoff) => daysoff,
+            None => DaysOff::new(year, settings),
         };
+        let today = chrono::Local::now().date_naive();
+        daysoff.taken = Entitlement::load_taken(settings, today)?;
+        daysoff.refresh(settings);
 
-        let mut need_save = false;
-        daysoff.sick_days = match File::open("./data/sickdays.json") {
-            Err(_) => {
-                need_save = true;
-                DaysOff::rebuild_sick_days().unwrap() // TODO propagate error
+        match storage().load_sick_days()? {
+            Some(sick_days) => daysoff.sick_days = sick_days,
+            None => {
+                daysoff.sick_days = DaysOff::rebuild_sick_days(settings)?;
+                daysoff.save()?;
             }
-            Ok(mut file) => {
-                let mut json = String::new();
-                file.read_to_string(&mut json)
-                    .expect(&format!("Failed to read sickdays.json"));
-                SickDays::from_json(&json).expect(&format!("Failed to deserialized sickdays.json"))
-            }
-        };
-        if need_save {
-            daysoff.save().unwrap(); // TODO
         }
-        daysoff
+        Ok(daysoff)
     }
 
-    fn rebuild_sick_days() -> Result<SickDays, Error> {
-        Ok(glob("./data/[0-9][0-9][0-9][0-9]_[0-9][0-9].json")?
-            .flat_map(|path| {
-                FlexMonth::load_with_file(path.unwrap().to_string_lossy().to_string())
-                    .get_sick_days()
-            })
-            .collect())
+    fn rebuild_sick_days(settings: &Settings) -> Result<SickDays, FlexiError> {
+        let mut sick_days = SickDays::new();
+        for status in DayTypes::new(settings).drawing_from(Allowance::Sick) {
+            sick_days.extend(storage().find_days(status)?);
+        }
+        sick_days.sort();
+        Ok(sick_days)
     }
 
-    pub fn update_days_off(&mut self, old_status: DayStatus, day: FlexDay) {
+    /// leave year of the holidays left: the one of today during `year`, else the one
+    /// holding its last day
+    pub fn leave_year(&self) -> Option<&LeaveYear> {
+        self.leave_year.as_ref()
+    }
+
+    /// compute the holidays left again, e.g. after the rules changed
+    pub fn refresh(&mut self, settings: &Settings) {
+        let today = chrono::Local::now().date_naive();
+        let date = if today.year() == self.year {
+            today
+        } else {
+            NaiveDate::from_ymd_opt(self.year, 12, 31).unwrap()
+        };
+        let leave_year = Entitlement::new(settings).year(&self.taken, date, today);
+        self.holidays_left = leave_year.left();
+        self.leave_year = Some(leave_year);
+    }
+
+    pub fn update_days_off(&mut self, old_status: DayStatus, day: &FlexDay, settings: &Settings) {
         let new_status = day.status;
         if old_status != new_status {
-            match old_status {
-                DayStatus::Worked => match new_status {
-                    DayStatus::Holiday => self.holidays_left -= 1.0,
-                    DayStatus::Half => self.holidays_left -= 0.5,
-                    DayStatus::Sick => self.add_sick_day(day),
-                    DayStatus::Weekend | DayStatus::Worked => {}
-                },
-                DayStatus::Holiday => match new_status {
-                    DayStatus::Worked | DayStatus::Weekend => self.holidays_left += 1.0,
-                    DayStatus::Half => self.holidays_left += 0.5,
-                    DayStatus::Sick => {
-                        self.holidays_left += 1.0;
-                        self.add_sick_day(day);
-                    }
-                    DayStatus::Holiday => {}
-                },
-                DayStatus::Half => match new_status {
-                    DayStatus::Worked | DayStatus::Weekend => self.holidays_left += 0.5,
-                    DayStatus::Holiday => self.holidays_left -= 0.5,
-                    DayStatus::Sick => {
-                        self.holidays_left += 0.5;
-                        self.add_sick_day(day);
-                    }
-                    DayStatus::Half => {}
-                },
-                DayStatus::Sick => {
-                    self.remove_sick_day(day);
-                    match new_status {
-                        DayStatus::Half => self.holidays_left -= 0.5,
-                        DayStatus::Holiday => self.holidays_left -= 1.0,
-                        DayStatus::Sick => {}
-                        DayStatus::Worked | DayStatus::Weekend => {}
-                    }
-                }
-                DayStatus::Weekend => {}
+            let (old, new) = (
+                day_type(settings, old_status),
+                day_type(settings, new_status),
+            );
+            let date = day.date.expect("day off should have date");
+            match new.cost(Allowance::Holidays) {
+                cost if cost > 0.0 => self.taken.insert(date, cost),
+                _ => self.taken.remove(&date),
+            };
+            self.refresh(settings);
+            if old.allowance == Allowance::Sick {
+                self.remove_sick_day(day);
+            }
+            if new.allowance == Allowance::Sick {
+                self.add_sick_day(day);
             }
         }
     }
 
-    fn add_sick_day(&mut self, d: FlexDay) {
+    fn add_sick_day(&mut self, d: &FlexDay) {
         let date = d.date.expect("sick day should have date");
         if let Err(insert_idx) = self.sick_days.binary_search(&date) {
             self.sick_days.insert(insert_idx, date);
@@ -141,7 +135,7 @@ impl DaysOff {
         self.roll_sick_days();
     }
 
-    fn remove_sick_day(&mut self, d: FlexDay) {
+    fn remove_sick_day(&mut self, d: &FlexDay) {
         let date = d.date.expect("sick day should have date");
         if let Ok(index) = self.sick_days.binary_search(&date) {
             self.sick_days.remove(index);
@@ -150,8 +144,8 @@ impl DaysOff {
     }
 
     pub fn roll_sick_days(&mut self) {
-        let today = chrono::Local::today().naive_local();
-        let limit = NaiveDate::from_ymd(today.year() - 1, today.month(), 1);
+        let today = chrono::Local::now().date_naive();
+        let limit = NaiveDate::from_ymd_opt(today.year() - 1, today.month(), 1).unwrap();
         self.sick_days.retain(|&date| date > limit);
     }
 
@@ -163,55 +157,104 @@ impl DaysOff {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::savable::{fixture, load_file};
+
+    #[test]
+    fn fixture_test() {
+        let path = fixture("v0/2017_daysoff.json", "daysoff");
+        let daysoff = load_file::<DaysOff>(&path).unwrap().unwrap();
+        assert_eq!(daysoff.year, 2017);
+        assert_eq!(daysoff.holidays_left, 26.0);
+
+        let path = fixture("v0/sickdays.json", "sickdays");
+        let sick_days = load_file::<SickDays>(&path).unwrap().unwrap();
+        assert_eq!(
+            sick_days,
+            vec![NaiveDate::from_ymd_opt(2017, 5, 2).unwrap()]
+        );
+        let upgraded = std::fs::read_to_string(&path).unwrap();
+        assert!(upgraded.starts_with("{\n  \"version\": 1,\n  \"data\": ["));
+        assert_eq!(SickDays::from_json(&upgraded).unwrap(), sick_days);
+    }
 
     #[test]
     fn load_save_load() {
+        crate::datadir::test_data_dir("load_save_load");
         let s = Settings::default();
         let mut d1 = DaysOff::new(2017, &s);
         assert_eq!(d1.year, 2017);
         assert_eq!(d1.sick_days_taken() as i32, 0);
         assert_eq!(d1.holidays_left as i32, 26);
         d1.holidays_left = 15.0;
-        d1.sick_days.push(NaiveDate::from_ymd(2017, 06, 27));
+        d1.sick_days
+            .push(NaiveDate::from_ymd_opt(2017, 6, 27).unwrap());
         d1.save().unwrap();
-        let d2 = DaysOff::load(2017, &s);
+        let d2 = DaysOff::load(2017, &s).unwrap();
         assert_eq!(d2.year, 2017);
-        assert_eq!(d2.holidays_left as i32, 15);
+        // computed again from the days taken, not read back
+        assert_eq!(d2.holidays_left as i32, 26);
         assert_eq!(d2.sick_days_taken() as i32, 1);
     }
 
+    #[test]
+    fn update_days_off_test() {
+        let mut s = Settings::default();
+        let mut off = DaysOff::new(2017, &s);
+        let mut day = FlexDay::new(NaiveDate::from_ymd_opt(2017, 6, 27).unwrap(), &s);
+        day.status = DayStatus::Holiday;
+        off.update_days_off(DayStatus::Worked, &day, &s);
+        assert_eq!(off.holidays_left, 25.0);
+        day.status = DayStatus::HalfAm;
+        off.update_days_off(DayStatus::Holiday, &day, &s);
+        assert_eq!(off.holidays_left, 25.5);
+        day.status = DayStatus::PublicHoliday;
+        off.update_days_off(DayStatus::HalfAm, &day, &s);
+        assert_eq!(off.holidays_left, 26.0);
+
+        s.public_holidays.count_against_allowance = true;
+        off.update_days_off(DayStatus::Worked, &day, &s);
+        assert_eq!(off.holidays_left, 25.0);
+        day.status = DayStatus::Worked;
+        off.update_days_off(DayStatus::PublicHoliday, &day, &s);
+        assert_eq!(off.holidays_left, 26.0);
+    }
+
     #[test]
     fn sick_day_test() {
+        crate::datadir::test_data_dir("sick_day_test");
         let s = Settings::default();
         let mut d1 = DaysOff::new(2017, &s);
         assert_eq!(d1.sick_days_taken() as i32, 0);
-        let today = chrono::Local::today().naive_local();
+        let today = chrono::Local::now().date_naive();
         let day = FlexDay::new(today, &s);
-        d1.add_sick_day(day);
+        d1.add_sick_day(&day);
         assert_eq!(d1.sick_days_taken() as i32, 1);
 
         // adding the same day is not authorised
-        d1.add_sick_day(day);
+        d1.add_sick_day(&day);
         assert_eq!(d1.sick_days_taken() as i32, 1);
 
         d1.save().unwrap();
-        let d2 = DaysOff::load(2017, &s);
+        let d2 = DaysOff::load(2017, &s).unwrap();
         assert_eq!(d2.sick_days_taken() as i32, 1);
 
         // sick days are in a stand alone file but managed by DaysOff struct
-        let mut d2 = DaysOff::load(2018, &s);
+        let mut d2 = DaysOff::load(2018, &s).unwrap();
         assert_eq!(d2.sick_days_taken() as i32, 1);
 
         // adding a day more than 12 months old should be removed by roll_sick_days
-        let limit = NaiveDate::from_ymd(today.year() - 1, today.month(), 1).pred();
+        let limit = NaiveDate::from_ymd_opt(today.year() - 1, today.month(), 1)
+            .unwrap()
+            .pred_opt()
+            .unwrap();
         let day = FlexDay::new(limit, &s);
-        d2.add_sick_day(day);
+        d2.add_sick_day(&day);
         assert_eq!(d2.sick_days_taken() as i32, 1);
 
         let day = FlexDay::new(today, &s);
-        d2.remove_sick_day(day);
+        d2.remove_sick_day(&day);
         assert_eq!(d2.sick_days_taken() as i32, 0);
-        d2.remove_sick_day(day);
+        d2.remove_sick_day(&day);
         assert_eq!(d2.sick_days_taken() as i32, 0);
     }
 }
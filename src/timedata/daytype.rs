@@ -0,0 +1,259 @@
+use crate::settings::Settings;
+use crate::timedata::DayStatus;
+use serde_derive::{Deserialize, Serialize};
+
+/// Time credited for a day
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Credit {
+    // `holiday_duration` of the settings
+    FullDay,
+    HalfDay,
+    None,
+    // the work sessions, minus the pause
+    Actual,
+    // the work sessions plus half the scheduled day, the morning or the afternoon taken off
+    MorningOff,
+    AfternoonOff,
+}
+
+impl Credit {
+    /// true if the start, end and pause of the day are counted
+    pub fn is_timed(self) -> bool {
+        match self {
+            Credit::Actual | Credit::MorningOff | Credit::AfternoonOff => true,
+            Credit::FullDay | Credit::HalfDay | Credit::None => false,
+        }
+    }
+}
+
+/// Allowance used up by a day
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Allowance {
+    #[default]
+    None,
+    // holidays left in the year
+    Holidays,
+    // sick days of the last 12 months
+    Sick,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Color {
+    #[default]
+    Normal,
+    Dim,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+/// Kind of day, shown by its code letter in the week
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DayType {
+    pub code: char,
+    pub name: String,
+    pub credit: Credit,
+    #[serde(default)]
+    pub allowance: Allowance,
+    // days of the allowance used, e.g. 0.5 for half a day off
+    #[serde(default = "one_day")]
+    pub allowance_days: f32,
+    #[serde(default)]
+    pub color: Color,
+}
+
+fn one_day() -> f32 {
+    1.0
+}
+
+impl DayType {
+    pub fn new(code: char, name: &str, credit: Credit, color: Color) -> DayType {
+        DayType {
+            code,
+            name: name.to_string(),
+            credit,
+            allowance: Allowance::None,
+            allowance_days: one_day(),
+            color,
+        }
+    }
+
+    fn drawing(mut self, allowance: Allowance, days: f32) -> DayType {
+        self.allowance = allowance;
+        self.allowance_days = days;
+        self
+    }
+
+    /// days of `allowance` used by a day of this type
+    pub fn cost(&self, allowance: Allowance) -> f32 {
+        if self.allowance == allowance {
+            self.allowance_days
+        } else {
+            0.0
+        }
+    }
+
+    // type of a day saved with a code no longer defined
+    fn unknown(code: char) -> DayType {
+        DayType::new(code, "Unknown", Credit::None, Color::Red)
+    }
+}
+
+// built-in codes in scrolling order, replaced by the types of the settings with the same code
+const BUILT_IN: [char; 11] = ['N', 'H', 'a', 'p', 'S', 'P', 'T', 'B', 'L', 'O', 'W'];
+
+fn built_in(code: char, settings: &Settings) -> Option<DayType> {
+    let t = match code {
+        'N' => DayType::new('N', "Worked", Credit::Actual, Color::Normal),
+        'H' => DayType::new('H', "Holiday", Credit::FullDay, Color::Dim)
+            .drawing(Allowance::Holidays, 1.0),
+        'a' => DayType::new('a', "Morning off", Credit::MorningOff, Color::Dim)
+            .drawing(Allowance::Holidays, 0.5),
+        'p' => DayType::new('p', "Afternoon off", Credit::AfternoonOff, Color::Dim)
+            .drawing(Allowance::Holidays, 0.5),
+        'S' => DayType::new('S', "Sick", Credit::FullDay, Color::Dim).drawing(Allowance::Sick, 1.0),
+        'P' => {
+            let public_holiday = DayType::new('P', "Public holiday", Credit::FullDay, Color::Dim);
+            if settings.public_holidays.count_against_allowance {
+                public_holiday.drawing(Allowance::Holidays, 1.0)
+            } else {
+                public_holiday
+            }
+        }
+        'T' => DayType::new('T', "Training", Credit::FullDay, Color::Cyan),
+        'B' => DayType::new('B', "Business trip", Credit::Actual, Color::Blue),
+        'L' => DayType::new('L', "Parental leave", Credit::FullDay, Color::Magenta),
+        // time off in lieu, taken from the flexi account
+        'O' => DayType::new('O', "TOIL", Credit::None, Color::Yellow),
+        'W' => DayType::new('W', "Weekend", Credit::None, Color::Dim),
+        _ => return None,
+    };
+    Some(t)
+}
+
+/// Every day type, in the order the status is scrolled through
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayTypes {
+    types: Vec<DayType>,
+}
+
+impl DayTypes {
+    /// built-in types, replaced or completed by the ones of `settings`
+    pub fn new(settings: &Settings) -> DayTypes {
+        let mut types: Vec<DayType> = BUILT_IN
+            .iter()
+            .filter_map(|&code| built_in(code, settings))
+            .collect();
+        for t in &settings.day_types {
+            match types.iter_mut().find(|known| known.code == t.code) {
+                Some(known) => *known = t.clone(),
+                None => types.push(t.clone()),
+            }
+        }
+        DayTypes { types }
+    }
+
+    pub fn get(&self, status: DayStatus) -> DayType {
+        let code = status.code();
+        self.types
+            .iter()
+            .find(|t| t.code == code)
+            .cloned()
+            .unwrap_or_else(|| DayType::unknown(code))
+    }
+
+    /// statuses a working day can be given, in scrolling order
+    pub fn statuses(&self) -> Vec<DayStatus> {
+        self.types
+            .iter()
+            .map(|t| DayStatus::from_code(t.code))
+            .filter(|&s| s != DayStatus::Weekend)
+            .collect()
+    }
+
+    /// statuses using up `allowance`
+    pub fn drawing_from(&self, allowance: Allowance) -> Vec<DayStatus> {
+        self.types
+            .iter()
+            .filter(|t| t.allowance == allowance)
+            .map(|t| DayStatus::from_code(t.code))
+            .collect()
+    }
+}
+
+/// type of `status` in `settings`, without building all of them
+pub fn day_type(settings: &Settings, status: DayStatus) -> DayType {
+    let code = status.code();
+    match settings.day_types.iter().find(|t| t.code == code) {
+        Some(t) => t.clone(),
+        None => built_in(code, settings).unwrap_or_else(|| DayType::unknown(code)),
+    }
+}
+
+/// time credited for `status` in `settings`, cheap enough for every day of every redraw
+pub fn day_credit(settings: &Settings, status: DayStatus) -> Credit {
+    let code = status.code();
+    match settings.day_types.iter().find(|t| t.code == code) {
+        Some(t) => t.credit,
+        None => built_in(code, settings).map_or(Credit::None, |t| t.credit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_types_test() {
+        let mut settings = Settings {
+            day_types: vec![
+                DayType::new('T', "Course", Credit::HalfDay, Color::Green),
+                DayType::new('C', "Compassionate leave", Credit::FullDay, Color::Dim)
+                    .drawing(Allowance::Holidays, 1.0),
+            ],
+            ..Settings::default()
+        };
+        let types = DayTypes::new(&settings);
+        assert_eq!(types.get(DayStatus::Custom('T')).name, "Course");
+        assert_eq!(types.get(DayStatus::Sick).allowance, Allowance::Sick);
+        assert_eq!(types.get(DayStatus::Custom('Z')).name, "Unknown");
+        let statuses = types.statuses();
+        assert_eq!(statuses[0], DayStatus::Worked);
+        assert_eq!(statuses.last(), Some(&DayStatus::Custom('C')));
+        assert!(!statuses.contains(&DayStatus::Weekend));
+        assert_eq!(
+            types.drawing_from(Allowance::Holidays),
+            vec![
+                DayStatus::Holiday,
+                DayStatus::HalfAm,
+                DayStatus::HalfPm,
+                DayStatus::Custom('C')
+            ]
+        );
+
+        settings.public_holidays.count_against_allowance = true;
+        let types = DayTypes::new(&settings);
+        assert_eq!(
+            types
+                .get(DayStatus::PublicHoliday)
+                .cost(Allowance::Holidays),
+            1.0
+        );
+        // a single type is the same as the one of all the types
+        assert_eq!(
+            day_type(&settings, DayStatus::PublicHoliday),
+            types.get(DayStatus::PublicHoliday)
+        );
+        assert_eq!(day_type(&settings, DayStatus::Custom('T')).name, "Course");
+        assert_eq!(day_type(&settings, DayStatus::Custom('Z')).name, "Unknown");
+        assert_eq!(
+            day_credit(&settings, DayStatus::Custom('T')),
+            Credit::HalfDay
+        );
+        assert_eq!(day_credit(&settings, DayStatus::HalfAm), Credit::MorningOff);
+        assert_eq!(day_credit(&settings, DayStatus::Custom('Z')), Credit::None);
+    }
+}
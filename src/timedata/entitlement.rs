@@ -0,0 +1,230 @@
+use crate::error::FlexiError;
+use crate::settings::{EntitlementSettings, Settings};
+use crate::storage::storage;
+use crate::timedata::{grid_month_for, Allowance, DayStatus, DayTypes, HolidayCalendar};
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Days drawing on the holidays allowance, with the part of a day they use
+pub type HolidaysTaken = BTreeMap<NaiveDate, f32>;
+
+/// Holidays of one leave year
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeaveYear {
+    pub start: NaiveDate,
+    // first day of the next leave year
+    pub end: NaiveDate,
+    // pro rata the year work started
+    pub allowance: f32,
+    // left from the previous leave year
+    pub carried: f32,
+    // carried days not taken before they expired
+    pub expired: f32,
+    pub taken: f32,
+}
+
+impl LeaveYear {
+    pub fn left(&self) -> f32 {
+        self.allowance + self.carried - self.expired - self.taken
+    }
+
+    /// e.g. "2019", or "2019-20" for a leave year starting after January
+    pub fn label(&self) -> String {
+        if self.start.month() == 1 {
+            self.start.year().to_string()
+        } else {
+            format!("{}-{:02}", self.start.year(), self.end.year() % 100)
+        }
+    }
+}
+
+/// Holidays allowance computed from the rules of the settings and the days taken
+pub struct Entitlement {
+    rules: EntitlementSettings,
+    per_year: f32,
+}
+
+impl Entitlement {
+    pub fn new(settings: &Settings) -> Entitlement {
+        Entitlement {
+            rules: settings.entitlement.clone(),
+            per_year: settings.holidays_per_year,
+        }
+    }
+
+    /// days of the saved months drawing on the holidays. Public holidays counted against
+    /// the allowance are added for the months not created yet, from `today` on
+    pub fn load_taken(settings: &Settings, today: NaiveDate) -> Result<HolidaysTaken, FlexiError> {
+        let types = DayTypes::new(settings);
+        let mut taken = HolidaysTaken::new();
+        for status in types.drawing_from(Allowance::Holidays) {
+            let cost = types.get(status).cost(Allowance::Holidays);
+            for date in storage().find_days(status)? {
+                taken.insert(date, cost);
+            }
+        }
+        let public = types
+            .get(DayStatus::PublicHoliday)
+            .cost(Allowance::Holidays);
+        if public > 0.0 {
+            let saved: BTreeSet<(i32, u32)> = storage().saved_months()?.into_iter().collect();
+            let calendar = HolidayCalendar::load(settings)?;
+            for year in today.year()..=today.year() + 1 {
+                for date in calendar.days_in(year).keys() {
+                    if *date >= today
+                        && settings.is_working_day(date)
+                        && !saved.contains(&grid_month_for(*date))
+                    {
+                        taken.insert(*date, public);
+                    }
+                }
+            }
+        }
+        Ok(taken)
+    }
+
+    /// first day of the leave year holding `date`
+    pub fn year_start(&self, date: NaiveDate) -> NaiveDate {
+        let month = self.rules.year_start_month;
+        let year = if date.month() >= month {
+            date.year()
+        } else {
+            date.year() - 1
+        };
+        NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+    }
+
+    /// leave year holding `date`, as it stands on `today`
+    pub fn year(&self, taken: &HolidaysTaken, date: NaiveDate, today: NaiveDate) -> LeaveYear {
+        let wanted = self.year_start(date);
+        // the days left are carried from the first year counted
+        let first = self
+            .rules
+            .start
+            .or_else(|| taken.keys().next().cloned())
+            .map_or(wanted, |first| self.year_start(first).min(wanted));
+        let mut start = first;
+        let mut carried = self.rules.opening_balance;
+        loop {
+            let year = self.one_year(taken, start, carried, today);
+            if start == wanted {
+                return year;
+            }
+            carried = year.left().max(0.0).min(self.rules.carry_over_cap);
+            start = year.end;
+        }
+    }
+
+    fn one_year(
+        &self,
+        taken: &HolidaysTaken,
+        start: NaiveDate,
+        carried: f32,
+        today: NaiveDate,
+    ) -> LeaveYear {
+        let end = NaiveDate::from_ymd_opt(start.year() + 1, start.month(), 1).unwrap();
+        let allowance = match self.rules.start {
+            Some(joined) if joined >= end => 0.0,
+            Some(joined) if joined > start => {
+                let share = (end - joined).num_days() as f32 / (end - start).num_days() as f32;
+                // to the nearest half day
+                (self.per_year * share * 2.0).round() / 2.0
+            }
+            _ => self.per_year,
+        };
+        let taken_until =
+            |until: NaiveDate| -> f32 { taken.range(start..until).map(|(_, c)| c).sum() };
+        let expired = match self.rules.carry_over_expiry {
+            Some(expiry) if carried > 0.0 => {
+                let mut day =
+                    NaiveDate::from_ymd_opt(start.year(), expiry.month, expiry.day).unwrap();
+                if day < start {
+                    day = NaiveDate::from_ymd_opt(start.year() + 1, expiry.month, expiry.day)
+                        .unwrap();
+                }
+                if today > day {
+                    // days taken before the expiry use the carried ones first
+                    (carried - taken_until(day.succ_opt().unwrap())).max(0.0)
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        };
+        LeaveYear {
+            start,
+            end,
+            allowance,
+            carried,
+            expired,
+            taken: taken_until(end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::MonthDay;
+
+    fn taken(days: &[(i32, u32, u32, f32)]) -> HolidaysTaken {
+        days.iter()
+            .map(|&(y, m, d, cost)| (NaiveDate::from_ymd_opt(y, m, d).unwrap(), cost))
+            .collect()
+    }
+
+    #[test]
+    fn calendar_year_test() {
+        let settings = Settings::default();
+        let entitlement = Entitlement::new(&settings);
+        let taken = taken(&[(2018, 12, 24, 1.0), (2019, 3, 1, 1.0), (2019, 3, 4, 0.5)]);
+        let today = NaiveDate::from_ymd_opt(2019, 5, 14).unwrap();
+        let year = entitlement.year(&taken, today, today);
+        assert_eq!(year.label(), "2019");
+        assert_eq!(year.taken, 1.5);
+        // nothing carried over by default
+        assert_eq!(year.carried, 0.0);
+        assert_eq!(year.left(), 24.5);
+    }
+
+    #[test]
+    fn leave_year_test() {
+        let settings = Settings {
+            holidays_per_year: 25.0,
+            entitlement: EntitlementSettings {
+                year_start_month: 4,
+                carry_over_cap: 5.0,
+                carry_over_expiry: Some(MonthDay { month: 6, day: 30 }),
+                start: Some(NaiveDate::from_ymd_opt(2018, 10, 1).unwrap()),
+                opening_balance: 0.0,
+            },
+            ..Settings::default()
+        };
+        let entitlement = Entitlement::new(&settings);
+        let taken = taken(&[
+            (2018, 12, 24, 1.0),
+            (2019, 3, 29, 1.0),
+            // in the 2019-20 leave year, one day before the carried days expire
+            (2019, 4, 1, 1.0),
+            (2019, 8, 5, 1.0),
+        ]);
+        let date = NaiveDate::from_ymd_opt(2019, 3, 1).unwrap();
+        let first = entitlement.year(&taken, date, date);
+        assert_eq!(first.label(), "2018-19");
+        // joined half way through the leave year
+        assert_eq!(first.allowance, 12.5);
+        assert_eq!(first.left(), 10.5);
+
+        let may = NaiveDate::from_ymd_opt(2019, 5, 14).unwrap();
+        let second = entitlement.year(&taken, may, may);
+        assert_eq!((second.allowance, second.carried), (25.0, 5.0));
+        assert_eq!(second.expired, 0.0);
+        // days booked later in the year are taken off too
+        assert_eq!(second.left(), 28.0);
+
+        let september = NaiveDate::from_ymd_opt(2019, 9, 2).unwrap();
+        let second = entitlement.year(&taken, september, september);
+        assert_eq!(second.expired, 4.0);
+        assert_eq!(second.left(), 24.0);
+    }
+}
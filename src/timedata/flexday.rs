@@ -1,36 +1,178 @@
 use crate::settings::Settings;
+use crate::timedata::{day_credit, Credit, ProjectReport};
 use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Timelike, Weekday};
 use serde_derive::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::default::Default;
-use std::fmt::{Display, Formatter, Result};
 
-pub static mut HOLIDAY_DURATION: i64 = 0;
-
-#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+// saved by name, or by code for the types defined in the settings
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(into = "String", try_from = "String")]
 pub enum DayStatus {
+    #[default]
     Worked,
     Holiday,
-    Half,
+    // half a day of holiday, taken in the morning or in the afternoon
+    HalfAm,
+    HalfPm,
     Weekend,
     Sick,
+    PublicHoliday,
+    // one of `Settings::day_types`, by code
+    Custom(char),
+}
+
+impl DayStatus {
+    pub fn code(self) -> char {
+        match self {
+            DayStatus::Worked => 'N',
+            DayStatus::Holiday => 'H',
+            DayStatus::HalfAm => 'a',
+            DayStatus::HalfPm => 'p',
+            DayStatus::Weekend => 'W',
+            DayStatus::Sick => 'S',
+            DayStatus::PublicHoliday => 'P',
+            DayStatus::Custom(code) => code,
+        }
+    }
+
+    pub fn from_code(code: char) -> DayStatus {
+        match code {
+            'N' => DayStatus::Worked,
+            'H' => DayStatus::Holiday,
+            'a' => DayStatus::HalfAm,
+            'p' => DayStatus::HalfPm,
+            'W' => DayStatus::Weekend,
+            'S' => DayStatus::Sick,
+            'P' => DayStatus::PublicHoliday,
+            code => DayStatus::Custom(code),
+        }
+    }
+
+    /// true if the start, end and pause of the day are counted
+    pub fn is_timed(self, settings: &Settings) -> bool {
+        day_credit(settings, self).is_timed()
+    }
 }
 
-impl Default for DayStatus {
-    fn default() -> DayStatus {
-        DayStatus::Worked
+impl From<DayStatus> for String {
+    fn from(status: DayStatus) -> String {
+        match status {
+            DayStatus::Custom(code) => code.to_string(),
+            _ => format!("{:?}", status),
+        }
     }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+impl TryFrom<String> for DayStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<DayStatus, String> {
+        let mut chars = s.chars();
+        match (s.as_str(), chars.next(), chars.next()) {
+            ("Worked", _, _) => Ok(DayStatus::Worked),
+            ("Holiday", _, _) => Ok(DayStatus::Holiday),
+            ("HalfAm", _, _) => Ok(DayStatus::HalfAm),
+            ("HalfPm", _, _) => Ok(DayStatus::HalfPm),
+            // saved before the halves were told apart, months are upgraded by their start
+            ("Half", _, _) => Ok(DayStatus::HalfPm),
+            ("Weekend", _, _) => Ok(DayStatus::Weekend),
+            ("Sick", _, _) => Ok(DayStatus::Sick),
+            ("PublicHoliday", _, _) => Ok(DayStatus::PublicHoliday),
+            (_, Some(code), None) => Ok(DayStatus::from_code(code)),
+            _ => Err(format!("unknown day status `{}`", s)),
+        }
+    }
+}
+
+/// One clock-in/clock-out interval of a day
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    // one of `Settings::projects`, the time is billed to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+impl Session {
+    pub fn new(start: NaiveTime, end: NaiveTime) -> Session {
+        Session {
+            start,
+            end,
+            project: None,
+        }
+    }
+
+    pub fn total_minutes(&self) -> i64 {
+        self.end.signed_duration_since(self.start).num_minutes()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(from = "FlexDayJson")]
 pub struct FlexDay {
     pub date: Option<NaiveDate>,
     weekday: Option<Weekday>,
-    pub start: NaiveTime,
-    pub end: NaiveTime,
+    // ordered list of work intervals, never empty
+    pub sessions: Vec<Session>,
     // TODO switch to Duration when chrono supports Serialize/Deserialize
     pub pause: i64,
     pub status: DayStatus,
+    // free text reason, e.g. "left early, dentist"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    // clocked or typed, false while left from the schedule
+    pub start_recorded: bool,
+    pub end_recorded: bool,
+}
+
+// on disk representation, accepts the old single `start`/`end` format
+#[derive(Deserialize)]
+struct FlexDayJson {
+    date: Option<NaiveDate>,
+    weekday: Option<Weekday>,
+    #[serde(default)]
+    sessions: Vec<Session>,
+    start: Option<NaiveTime>,
+    end: Option<NaiveTime>,
+    pause: i64,
+    status: DayStatus,
+    #[serde(default)]
+    note: Option<String>,
+    // days saved before they were tracked count as recorded
+    #[serde(default = "recorded")]
+    start_recorded: bool,
+    #[serde(default = "recorded")]
+    end_recorded: bool,
+}
+
+fn recorded() -> bool {
+    true
+}
+
+impl From<FlexDayJson> for FlexDay {
+    fn from(json: FlexDayJson) -> FlexDay {
+        let mut sessions = json.sessions;
+        if sessions.is_empty() {
+            let default = FlexDay::default_session();
+            sessions.push(Session::new(
+                json.start.unwrap_or(default.start),
+                json.end.unwrap_or(default.end),
+            ));
+        }
+        FlexDay {
+            date: json.date,
+            weekday: json.weekday,
+            sessions,
+            pause: json.pause,
+            status: json.status,
+            note: json.note,
+            start_recorded: json.start_recorded,
+            end_recorded: json.end_recorded,
+        }
+    }
 }
 
 impl Default for FlexDay {
@@ -38,124 +180,12 @@ impl Default for FlexDay {
         FlexDay {
             date: None,
             weekday: None,
-            start: NaiveTime::from_hms(9, 0, 0),
-            end: NaiveTime::from_hms(17, 0, 0),
+            sessions: vec![FlexDay::default_session()],
             pause: Duration::minutes(30).num_minutes(),
             status: Default::default(),
-        }
-    }
-}
-
-impl Display for FlexDay {
-    fn fmt(&self, f: &mut Formatter) -> Result {
-        match self.status {
-            DayStatus::Worked | DayStatus::Half => {
-                let pause = Duration::minutes(self.pause);
-                let total = Duration::minutes(self.total_minutes());
-                if total.num_minutes() >= 0 {
-                    write!(
-                        f,
-                        "{}   {} {:02}/{:02}   {:02}:{:02} -> {:02}:{:02} - {:02}:{:02} = \
-                         {:02}:{:02}",
-                        if self.status == DayStatus::Worked {
-                            "N"
-                        } else {
-                            "h"
-                        },
-                        match self.weekday {
-                            Some(wd) => super::weekday_to_string(wd),
-                            None => "???".to_string(),
-                        },
-                        match self.date {
-                            Some(date) => date.day(),
-                            None => 0u32,
-                        },
-                        match self.date {
-                            Some(date) => date.month(),
-                            None => 0u32,
-                        },
-                        self.start.hour(),
-                        self.start.minute(),
-                        self.end.hour(),
-                        self.end.minute(),
-                        pause.num_hours(),
-                        pause.num_minutes() - (pause.num_hours() * 60),
-                        total.num_hours().abs(),
-                        total.num_minutes().abs() - (total.num_hours().abs() * 60)
-                    )
-                } else {
-                    write!(
-                        f,
-                        "{}   {} {:02}/{:02}   {:02}:{:02} -> {:02}:{:02} - {:02}:{:02} = ??:??",
-                        if self.status == DayStatus::Worked {
-                            "N"
-                        } else {
-                            "h"
-                        },
-                        match self.weekday {
-                            Some(wd) => super::weekday_to_string(wd),
-                            None => "???".to_string(),
-                        },
-                        match self.date {
-                            Some(date) => date.day(),
-                            None => 0u32,
-                        },
-                        match self.date {
-                            Some(date) => date.month(),
-                            None => 0u32,
-                        },
-                        self.start.hour(),
-                        self.start.minute(),
-                        self.end.hour(),
-                        self.end.minute(),
-                        pause.num_hours(),
-                        pause.num_minutes() - (pause.num_hours() * 60)
-                    )
-                }
-            }
-            DayStatus::Weekend => write!(
-                f,
-                "{}   {} {:02}/{:02}   --:-- -> --:-- - --:-- = --:--",
-                "W",
-                match self.weekday {
-                    Some(wd) => super::weekday_to_string(wd),
-                    None => "???".to_string(),
-                },
-                match self.date {
-                    Some(date) => date.day(),
-                    None => 0u32,
-                },
-                match self.date {
-                    Some(date) => date.month(),
-                    None => 0u32,
-                }
-            ),
-            DayStatus::Holiday | DayStatus::Sick => {
-                let total = Duration::minutes(self.total_minutes());
-                write!(
-                    f,
-                    "{}   {} {:02}/{:02}   --:-- -> --:-- - --:-- = {:02}:{:02}",
-                    if self.status == DayStatus::Holiday {
-                        "H"
-                    } else {
-                        "S"
-                    },
-                    match self.weekday {
-                        Some(wd) => super::weekday_to_string(wd),
-                        None => "???".to_string(),
-                    },
-                    match self.date {
-                        Some(date) => date.day(),
-                        None => 0u32,
-                    },
-                    match self.date {
-                        Some(date) => date.month(),
-                        None => 0u32,
-                    },
-                    total.num_hours(),
-                    total.num_minutes() - (total.num_hours() * 60)
-                )
-            }
+            note: None,
+            start_recorded: false,
+            end_recorded: false,
         }
     }
 }
@@ -187,32 +217,248 @@ impl FlexDay {
         FlexDay {
             date: Some(date),
             weekday: Some(date.weekday()),
-            start: default.start,
-            end: default.end,
+            sessions: vec![Session::new(default.start, default.end)],
             pause: default.pause,
-            status: FlexDay::day_status_for(date.weekday()),
+            status: if settings.is_working_day(&date) {
+                DayStatus::Worked
+            } else {
+                DayStatus::Weekend
+            },
+            note: None,
+            start_recorded: false,
+            end_recorded: false,
+        }
+    }
+
+    /// put back the working window of the schedule, shrunk to the worked half on half days
+    pub fn reset_to_schedule(&mut self, settings: &Settings) {
+        let date = self.date.expect("should have date");
+        let default = settings.get_default_day_settings_for_status(&date, self.status);
+        let project = self.sessions.first().and_then(|s| s.project.clone());
+        self.sessions = vec![Session {
+            project,
+            ..Session::new(default.start, default.end)
+        }];
+        self.pause = default.pause;
+        self.start_recorded = false;
+        self.end_recorded = false;
+    }
+
+    /// true unless the day is worked with its start or end left from the schedule
+    pub fn is_clocked(&self, settings: &Settings) -> bool {
+        !self.status.is_timed(settings) || (self.start_recorded && self.end_recorded)
+    }
+
+    /// flag the start and the end as recorded if they were changed from `old`'s
+    pub fn record_changes(&mut self, old: &FlexDay) {
+        self.start_recorded |= self.start() != old.start();
+        self.end_recorded |= self.end() != old.end();
+    }
+
+    /// replace the note, a blank one removes it
+    pub fn set_note(&mut self, text: &str) {
+        let text = text.trim();
+        self.note = if text.is_empty() {
+            None
+        } else {
+            Some(text.to_string())
+        };
+    }
+
+    /// true if the note contains `text`, ignoring case
+    pub fn note_matches(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.note
+            .as_ref()
+            .is_some_and(|note| note.to_lowercase().contains(&text))
+    }
+
+    fn default_session() -> Session {
+        Session::new(
+            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        )
+    }
+
+    /// start of the first session
+    pub fn start(&self) -> NaiveTime {
+        self.sessions[0].start
+    }
+
+    /// end of the last session
+    pub fn end(&self) -> NaiveTime {
+        self.sessions[self.sessions.len() - 1].end
+    }
+
+    /// clock in at `time`: open a new session if the last one is already closed, otherwise
+    /// move the start of the last session. The session of the schedule, never clocked,
+    /// is moved rather than followed by a new one
+    pub fn clock_in(&mut self, time: NaiveTime) {
+        let last = self.sessions.len() - 1;
+        if last == 0 && !self.start_recorded && !self.end_recorded {
+            let session = &mut self.sessions[0];
+            session.start = time;
+            if time > session.end {
+                session.end = time;
+                self.end_recorded = false;
+            }
+            self.start_recorded = true;
+        } else if time > self.sessions[last].end {
+            self.sessions.push(Session::new(time, time));
+            // the day ends with the new session, still to be clocked out
+            self.end_recorded = false;
+        } else {
+            self.sessions[last].start = time;
+            self.start_recorded |= last == 0;
+        }
+    }
+
+    /// clock out at `time`: close the last session
+    pub fn clock_out(&mut self, time: NaiveTime) {
+        let last = self.sessions.len() - 1;
+        self.sessions[last].end = time;
+        self.end_recorded = true;
+    }
+
+    /// add a session after the last one, return its index
+    pub fn add_session(&mut self) -> usize {
+        let end = self.end();
+        self.sessions.push(Session::new(end, end));
+        self.sessions.len() - 1
+    }
+
+    /// remove a session, the last remaining one is kept
+    pub fn remove_session(&mut self, idx: usize) {
+        if self.sessions.len() > 1 && idx < self.sessions.len() {
+            self.sessions.remove(idx);
+        }
+    }
+
+    // ' ' for a single session, number of sessions otherwise
+    fn sessions_marker(&self) -> String {
+        match self.sessions.len() {
+            1 => " ".to_string(),
+            n if n < 10 => n.to_string(),
+            _ => "+".to_string(),
+        }
+    }
+
+    /// day line from the start of the first session to the end of the last one
+    pub fn line(&self, settings: &Settings) -> String {
+        self.row(self.start(), self.end(), &self.sessions_marker(), settings)
+    }
+
+    /// day line showing only the given session, with its number as marker
+    pub fn session_to_string(&self, idx: usize, settings: &Settings) -> String {
+        let s = &self.sessions[idx];
+        let marker = if self.sessions.len() > 1 && idx < 9 {
+            (idx + 1).to_string()
+        } else {
+            self.sessions_marker()
+        };
+        self.row(s.start, s.end, &marker, settings)
+    }
+
+    fn row(&self, start: NaiveTime, end: NaiveTime, marker: &str, settings: &Settings) -> String {
+        let weekday = match self.weekday {
+            Some(wd) => super::weekday_to_string(wd),
+            None => "???".to_string(),
+        };
+        let (day, month) = match self.date {
+            Some(date) => (date.day(), date.month()),
+            None => (0u32, 0u32),
+        };
+        let code = self.status.code();
+        match day_credit(settings, self.status) {
+            Credit::Actual | Credit::MorningOff | Credit::AfternoonOff => {
+                let pause = Duration::minutes(self.pause);
+                let total = Duration::minutes(self.total_minutes(settings));
+                format!(
+                    "{} {} {} {:02}/{:02}   {:02}:{:02} -> {:02}:{:02} - {:02}:{:02} = {}",
+                    code,
+                    marker,
+                    weekday,
+                    day,
+                    month,
+                    start.hour(),
+                    start.minute(),
+                    end.hour(),
+                    end.minute(),
+                    pause.num_hours(),
+                    pause.num_minutes() - (pause.num_hours() * 60),
+                    if total.num_minutes() >= 0 {
+                        format!(
+                            "{:02}:{:02}",
+                            total.num_hours(),
+                            total.num_minutes() - (total.num_hours() * 60)
+                        )
+                    } else {
+                        "??:??".to_string()
+                    }
+                )
+            }
+            Credit::None => format!(
+                "{}   {} {:02}/{:02}   --:-- -> --:-- - --:-- = --:--",
+                code, weekday, day, month
+            ),
+            Credit::FullDay | Credit::HalfDay => {
+                let total = Duration::minutes(self.total_minutes(settings));
+                format!(
+                    "{}   {} {:02}/{:02}   --:-- -> --:-- - --:-- = {:02}:{:02}",
+                    code,
+                    weekday,
+                    day,
+                    month,
+                    total.num_hours(),
+                    total.num_minutes() - (total.num_hours() * 60)
+                )
+            }
         }
     }
 
     pub fn status_str(&self) -> String {
-        match self.status {
-            DayStatus::Worked => "N",
-            DayStatus::Holiday => "H",
-            DayStatus::Half => "h",
-            DayStatus::Sick => "S",
-            DayStatus::Weekend => "W",
+        self.status.code().to_string()
+    }
+
+    /// minutes between clock-in and clock-out, none for the days without times
+    pub fn worked_minutes(&self, settings: &Settings) -> i64 {
+        if !self.status.is_timed(settings) {
+            return 0;
         }
-        .to_string()
+        self.sessions
+            .iter()
+            .fold(0, |acc, s| acc + s.total_minutes())
+            - self.pause
     }
 
-    pub fn total_minutes(&self) -> i64 {
-        match self.status {
-            DayStatus::Worked | DayStatus::Half => {
-                self.end.signed_duration_since(self.start).num_minutes() - self.pause
+    pub fn total_minutes(&self, settings: &Settings) -> i64 {
+        match day_credit(settings, self.status) {
+            Credit::Actual => self.worked_minutes(settings),
+            Credit::MorningOff | Credit::AfternoonOff => {
+                self.worked_minutes(settings)
+                    + self.date.map_or(0, |date| settings.half_day_minutes(&date))
             }
-            DayStatus::Weekend => 0,
-            DayStatus::Holiday | DayStatus::Sick => unsafe { HOLIDAY_DURATION },
+            Credit::None => 0,
+            Credit::FullDay => settings.holiday_duration,
+            Credit::HalfDay => settings.holiday_duration / 2,
+        }
+    }
+
+    /// worked minutes of each project. The pause is taken off the longest session, where
+    /// lunch usually is
+    pub fn projects(&self, settings: &Settings) -> ProjectReport {
+        let mut report = ProjectReport::default();
+        if !self.status.is_timed(settings) {
+            return report;
+        }
+        let longest = (0..self.sessions.len())
+            .max_by_key(|&idx| (self.sessions[idx].total_minutes(), -(idx as i64)))
+            .unwrap_or(0);
+        for (idx, s) in self.sessions.iter().enumerate() {
+            let pause = if idx == longest { self.pause } else { 0 };
+            report.add(s.project.as_deref(), s.total_minutes() - pause);
         }
+        report
     }
 
     fn day_status_for(wd: Weekday) -> DayStatus {
@@ -238,7 +484,168 @@ mod tests {
 
     #[test]
     fn total_minutes_test() {
+        let settings = Settings::default();
         let d: FlexDay = Default::default();
-        assert_eq!(d.total_minutes(), 8 * 60 - 30);
+        assert_eq!(d.total_minutes(&settings), 8 * 60 - 30);
+    }
+
+    #[test]
+    fn day_status_test() {
+        let settings = Settings::default();
+        let json = |s: DayStatus| serde_json::to_string(&s).unwrap();
+        assert_eq!(json(DayStatus::PublicHoliday), "\"PublicHoliday\"");
+        assert_eq!(json(DayStatus::Custom('T')), "\"T\"");
+        let status: DayStatus = serde_json::from_str("\"T\"").unwrap();
+        assert_eq!(status, DayStatus::Custom('T'));
+        let status: DayStatus = serde_json::from_str("\"H\"").unwrap();
+        assert_eq!(status, DayStatus::Holiday);
+        assert!(serde_json::from_str::<DayStatus>("\"Training\"").is_err());
+
+        // the credit of the built-in types
+        let mut d = FlexDay {
+            status: DayStatus::Holiday,
+            ..Default::default()
+        };
+        let full_day = d.total_minutes(&settings);
+        d.status = DayStatus::Custom('T');
+        assert_eq!(d.total_minutes(&settings), full_day);
+        d.status = DayStatus::Custom('O');
+        assert_eq!(d.total_minutes(&settings), 0);
+    }
+
+    #[test]
+    fn half_day_test() {
+        let settings = Settings::default();
+        let mut d = FlexDay::new(NaiveDate::from_ymd_opt(2017, 5, 5).unwrap(), &settings);
+        d.status = DayStatus::HalfAm;
+        d.reset_to_schedule(&settings);
+        assert_eq!(d.start(), NaiveTime::from_hms_opt(13, 15, 0).unwrap());
+        assert_eq!(d.pause, 0);
+        // the afternoon worked and the morning credited make the day of the schedule
+        assert_eq!(d.total_minutes(&settings), 7 * 60 + 10);
+        assert!(d
+            .line(&settings)
+            .starts_with("a   Fri 05/05   13:15 -> 16:50"));
+
+        d.status = DayStatus::Worked;
+        d.reset_to_schedule(&settings);
+        assert_eq!(d.start(), NaiveTime::from_hms_opt(9, 10, 0).unwrap());
+        let status: DayStatus = serde_json::from_str("\"Half\"").unwrap();
+        assert_eq!(status, DayStatus::HalfPm);
+    }
+
+    #[test]
+    fn sessions_total_minutes_test() {
+        let settings = Settings::default();
+        let mut d: FlexDay = Default::default();
+        d.clock_out(NaiveTime::from_hms_opt(11, 0, 0).unwrap());
+        d.clock_in(NaiveTime::from_hms_opt(13, 0, 0).unwrap());
+        assert_eq!(d.sessions.len(), 2);
+        d.clock_out(NaiveTime::from_hms_opt(17, 0, 0).unwrap());
+        assert_eq!(d.total_minutes(&settings), 2 * 60 + 4 * 60 - 30);
+        assert_eq!(d.start(), NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+        assert_eq!(d.end(), NaiveTime::from_hms_opt(17, 0, 0).unwrap());
+
+        // clocking in again before the end of the last session moves its start
+        d.clock_in(NaiveTime::from_hms_opt(12, 30, 0).unwrap());
+        assert_eq!(d.sessions.len(), 2);
+        assert_eq!(
+            d.sessions[1].start,
+            NaiveTime::from_hms_opt(12, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn load_single_interval_test() {
+        let settings = Settings::default();
+        let json = r#"{
+  "date": "2017-05-02",
+  "weekday": "Tue",
+  "start": "08:30:00",
+  "end": "17:00:00",
+  "pause": 30,
+  "status": "Worked"
+}"#;
+        let d: FlexDay = serde_json::from_str(json).expect("should load old format");
+        assert_eq!(
+            d.sessions,
+            vec![Session::new(
+                NaiveTime::from_hms_opt(8, 30, 0).unwrap(),
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap()
+            )]
+        );
+        assert_eq!(d.total_minutes(&settings), 8 * 60);
+        // saved before the start and end were tracked
+        assert!(d.is_clocked(&settings));
+    }
+
+    #[test]
+    fn clocked_test() {
+        let settings = Settings::default();
+        let mut d: FlexDay = Default::default();
+        assert!(!d.is_clocked(&settings));
+        d.clock_in(NaiveTime::from_hms_opt(8, 45, 0).unwrap());
+        d.clock_out(NaiveTime::from_hms_opt(12, 0, 0).unwrap());
+        assert!(d.is_clocked(&settings));
+        // back from lunch, the end is the start of the new session until clocked out
+        d.clock_in(NaiveTime::from_hms_opt(13, 0, 0).unwrap());
+        assert!(!d.is_clocked(&settings));
+
+        let old = d.clone();
+        d.sessions[1].end = NaiveTime::from_hms_opt(17, 30, 0).unwrap();
+        d.record_changes(&old);
+        assert!(d.is_clocked(&settings));
+        d.status = DayStatus::Holiday;
+        d.start_recorded = false;
+        assert!(d.is_clocked(&settings));
+    }
+
+    #[test]
+    fn clock_in_after_schedule_test() {
+        // nothing worked during the default 9-17 session of the schedule
+        let settings = Settings::default();
+        let mut d = FlexDay::new(NaiveDate::from_ymd_opt(2019, 5, 14).unwrap(), &settings);
+        d.clock_in(NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+        assert_eq!(d.sessions.len(), 1);
+        assert_eq!(d.start(), NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+        assert!(!d.is_clocked(&settings));
+        d.clock_out(NaiveTime::from_hms_opt(21, 0, 0).unwrap());
+        assert!(d.is_clocked(&settings));
+        assert_eq!(d.worked_minutes(&settings), 3 * 60 - d.pause);
+    }
+
+    #[test]
+    fn note_test() {
+        let mut d: FlexDay = Default::default();
+        d.set_note("  Left early, dentist ");
+        assert_eq!(d.note, Some("Left early, dentist".to_string()));
+        assert!(d.note_matches("DENTIST"));
+        assert!(!d.note_matches("on-call"));
+        let json = serde_json::to_string(&d).unwrap();
+        assert_eq!(serde_json::from_str::<FlexDay>(&json).unwrap(), d);
+
+        d.set_note(" ");
+        assert_eq!(d.note, None);
+        assert!(!serde_json::to_string(&d).unwrap().contains("note"));
+    }
+
+    #[test]
+    fn projects_test() {
+        let settings = Settings::default();
+        let mut d: FlexDay = Default::default();
+        d.clock_out(NaiveTime::from_hms_opt(10, 0, 0).unwrap());
+        let idx = d.add_session();
+        d.clock_out(NaiveTime::from_hms_opt(17, 0, 0).unwrap());
+        d.sessions[idx].project = Some("acme".to_string());
+        let report = d.projects(&settings);
+        // the pause is taken off the longest session
+        assert_eq!(
+            report.projects(),
+            vec![(Some("acme"), 7 * 60 - 30), (None, 60)]
+        );
+        assert_eq!(report.total(), d.total_minutes(&settings));
+
+        d.status = DayStatus::Holiday;
+        assert_eq!(d.projects(&settings).total(), 0);
     }
 }
@@ -1,12 +1,13 @@
-use crate::savable::Savable;
+use crate::error::FlexiError;
+use crate::savable::{unchanged, Migration, Savable};
 use crate::settings::Settings;
-use crate::timedata::{DayStatus, FlexDay, FlexWeek, NaiveDateIter, SickDays};
+use crate::storage::storage;
+use crate::timedata::{
+    DayStatus, FlexDay, FlexWeek, HolidayCalendar, NaiveDateIter, ProjectReport,
+};
 use chrono::{Datelike, NaiveDate, Weekday};
 use serde_derive::{Deserialize, Serialize};
-use std::error::Error;
-use std::fmt::{Display, Formatter, Result};
-use std::fs::File;
-use std::io::prelude::*;
+use serde_json::Value;
 
 #[derive(Serialize, Deserialize, Default, PartialEq, Debug, Clone)]
 pub struct FlexMonth {
@@ -14,18 +15,13 @@ pub struct FlexMonth {
     pub year: i32,
     pub month: u32,
     pub one_week_goal: i64,
+    // goal of each week, from the schedule in force on its Monday. Empty for months created
+    // before schedule history, `one_week_goal` applies to every week then.
+    #[serde(default)]
+    pub week_goals: Vec<i64>,
     pub balance: i64, // TODO switch i64 to Duration when chrono supports Serialize/Deserialize
 }
 
-impl Display for FlexMonth {
-    fn fmt(&self, f: &mut Formatter) -> Result {
-        for w in &self.weeks {
-            writeln!(f, "{}", w).expect("Failed to write FlexMonth to Display");
-        }
-        write!(f, "")
-    }
-}
-
 pub fn next_month(year: i32, month: u32) -> (i32, u32) {
     if month == 12 {
         (year + 1, 1)
@@ -45,20 +41,20 @@ pub fn prev_month(year: i32, month: u32) -> (i32, u32) {
 fn find_next_monday(day: NaiveDate) -> NaiveDate {
     match day.weekday() {
         Weekday::Mon => day,
-        _ => find_next_monday(day.succ()),
+        _ => find_next_monday(day.succ_opt().unwrap()),
     }
 }
 
 fn find_prec_monday(day: NaiveDate) -> NaiveDate {
     match day.weekday() {
         Weekday::Mon => day,
-        _ => find_prec_monday(day.pred()),
+        _ => find_prec_monday(day.pred_opt().unwrap()),
     }
 }
 
 pub fn find_first_monday_of_grid(year: i32, month: u32) -> NaiveDate {
     //println!("find_first_monday_of_grid: year={}, month={}", year, month);
-    let first_day = NaiveDate::from_ymd(year, month, 1);
+    let first_day = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
     match first_day.weekday() {
         Weekday::Mon => first_day,
         Weekday::Sat | Weekday::Sun => find_next_monday(first_day),
@@ -68,15 +64,55 @@ pub fn find_first_monday_of_grid(year: i32, month: u32) -> NaiveDate {
 
 pub fn find_last_sunday_for(year: i32, month: u32) -> NaiveDate {
     let (y, m) = next_month(year, month);
-    let first_day_next_month = NaiveDate::from_ymd(y, m, 1);
+    let first_day_next_month = NaiveDate::from_ymd_opt(y, m, 1).unwrap();
     match first_day_next_month.weekday() {
         Weekday::Sun => first_day_next_month,
         _ => find_first_monday_of_grid(first_day_next_month.year(), first_day_next_month.month())
-            .pred(),
+            .pred_opt()
+            .unwrap(),
     }
 }
 
-impl<'a> Savable<'a, FlexMonth> for FlexMonth {}
+/// (year, month) of the month whose grid holds `day`
+pub fn grid_month_for(day: NaiveDate) -> (i32, u32) {
+    let (y, m) = next_month(day.year(), day.month());
+    if day >= find_first_monday_of_grid(y, m) {
+        (y, m)
+    } else if day < find_first_monday_of_grid(day.year(), day.month()) {
+        prev_month(day.year(), day.month())
+    } else {
+        (day.year(), day.month())
+    }
+}
+
+impl Savable for FlexMonth {
+    // version 2 added day notes, 3 the project of sessions, 4 morning and afternoon half days,
+    // 5 whether the start and end of days were recorded
+    const MIGRATIONS: &'static [Migration] =
+        &[unchanged, unchanged, unchanged, split_half_days, unchanged];
+}
+
+// half days starting in the afternoon had their morning off
+fn split_half_days(mut value: Value) -> std::result::Result<Value, String> {
+    let weeks = match value.get_mut("weeks").and_then(Value::as_array_mut) {
+        Some(weeks) => weeks,
+        None => return Err("expected weeks".to_string()),
+    };
+    let days = weeks
+        .iter_mut()
+        .filter_map(|week| week.get_mut("days").and_then(Value::as_array_mut))
+        .flatten();
+    for day in days.filter(|day| day["status"] == "Half") {
+        let start = day
+            .pointer("/sessions/0/start")
+            .or_else(|| day.get("start"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let status = if start >= "12:00" { "HalfAm" } else { "HalfPm" };
+        day["status"] = Value::from(status);
+    }
+    Ok(value)
+}
 
 impl FlexMonth {
     pub fn new(year: i32, month: u32, settings: &Settings) -> FlexMonth {
@@ -84,130 +120,156 @@ impl FlexMonth {
         let last_sunday = find_last_sunday_for(year, month);
         let range = NaiveDateIter::new(first_day, last_sunday);
         let mut weeks: Vec<FlexWeek> = Vec::new();
-        let mut week: [FlexDay; 7] = [FlexDay::default(); 7];
+        let mut week: [FlexDay; 7] = Default::default();
         let mut count = 0;
         for d in range {
             week[count % 7] = FlexDay::new(d, settings);
             count += 1;
             if count % 7 == 0 {
-                weeks.push(FlexWeek::new(week));
+                weeks.push(FlexWeek::new(week.clone()));
             }
         }
-        let balance = weeks.iter().fold(0, |acc, &w| acc + w.total_minutes())
-            - settings.week_goal * (weeks.len() as i64);
-        FlexMonth {
-            weeks: weeks,
-            year: year,
-            month: month,
-            one_week_goal: settings.week_goal,
-            balance: balance,
-        }
-    }
-
-    fn filename(year: i32, month: u32) -> String {
-        format!("./data/{}_{:02}.json", year, month)
-    }
-
-    pub fn save(&self) {
-        let mut file = match File::create(FlexMonth::filename(self.year, self.month)) {
-            Err(why) => panic!("couldn't create file: {}", why.description()),
-            Ok(file) => file,
+        let week_goals: Vec<i64> = weeks
+            .iter()
+            .map(|w| settings.week_goal_for(&w.days[0].date.expect("week should have dates")))
+            .collect();
+        let mut flex_month = FlexMonth {
+            weeks,
+            year,
+            month,
+            one_week_goal: settings.week_goal_for(&first_day),
+            week_goals,
+            balance: 0,
         };
+        flex_month.update_balance(settings);
+        flex_month
+    }
 
-        file.write_all(self.to_json().as_bytes())
-            .expect("Unable to write data");
-        file.write_all("\n".as_bytes()).expect("Unable to write");
+    pub fn save(&self) -> std::result::Result<(), FlexiError> {
+        storage().save_month(self)
     }
 
     /// return FlexMonth and if it was loaded from json or not
-    pub fn load_with_flag(year: i32, month: u32, settings: &Settings) -> (FlexMonth, bool) {
-        match File::open(FlexMonth::filename(year, month)) {
-            Err(_) => (FlexMonth::new(year, month, &settings), false),
-            Ok(mut file) => {
-                let mut json = String::new();
-                file.read_to_string(&mut json).expect(&format!(
-                    "Failed to read file: {}",
-                    FlexMonth::filename(year, month)
-                ));
-                (
-                    FlexMonth::from_json(&json).expect(&format!(
-                        "Failed to deserialized {}",
-                        FlexMonth::filename(year, month)
-                    )),
-                    true,
-                )
-            }
+    pub fn load_with_flag(
+        year: i32,
+        month: u32,
+        settings: &Settings,
+    ) -> std::result::Result<(FlexMonth, bool), FlexiError> {
+        match storage().load_month(year, month)? {
+            Some(m) => Ok((m, true)),
+            None => Ok((FlexMonth::new(year, month, settings), false)),
         }
     }
 
-    pub fn load(year: i32, month: u32, settings: &Settings) -> FlexMonth {
-        let (month, from_json) = FlexMonth::load_with_flag(year, month, &settings);
-        // generate Xmas holidays if needed
+    pub fn load(
+        year: i32,
+        month: u32,
+        settings: &Settings,
+    ) -> std::result::Result<FlexMonth, FlexiError> {
+        let (mut month, from_json) = FlexMonth::load_with_flag(year, month, settings)?;
         if !from_json {
-            // newly created month
-            match month.month {
-                1 => {
-                    let mut january = month;
-                    // auto set holiday as we always have 2 days of holidays in january
-                    let mut week_to_edit = january.weeks[0].clone();
-                    week_to_edit[0].status = DayStatus::Holiday;
-                    week_to_edit[1].status = DayStatus::Holiday;
-                    january.weeks[0] = week_to_edit;
-                    january.save();
-                    january
-                }
-                12 => {
-                    let mut december = month;
-                    // auto set holiday as we always have 5 days of holidays in december
-                    let nb_weeks = december.weeks.len();
-                    let mut week_to_edit = december.weeks[nb_weeks - 1].clone();
-                    for day in week_to_edit.days.iter_mut() {
-                        if day.status == DayStatus::Worked {
-                            day.status = DayStatus::Holiday;
-                        }
-                    }
-                    december.weeks[nb_weeks - 1] = week_to_edit;
-                    december.save();
-                    december
-                }
-                _ => month,
+            // newly created month, pre-fill public holidays and closures
+            let calendar = HolidayCalendar::load(settings)?;
+            if month.apply_calendar(&calendar, settings) > 0 {
+                month.save()?;
             }
-        } else {
-            month
         }
+        Ok(month)
     }
 
-    pub fn load_with_file(path: String) -> FlexMonth {
-        let mut file = File::open(&path).unwrap();
-        let mut json = String::new();
-        file.read_to_string(&mut json)
-            .expect(&format!("Failed to read file {}", path));
-        FlexMonth::from_json(&json).expect(&format!("Failed to deserialized  {}", path))
+    /// every day of the grid must have a date
+    pub fn validate(&self, path: &str) -> std::result::Result<(), FlexiError> {
+        if self.weeks.is_empty() {
+            return Err(FlexiError::Validation(format!("{} has no week", path)));
+        }
+        if self
+            .weeks
+            .iter()
+            .any(|w| w.days.iter().any(|d| d.date.is_none()))
+        {
+            return Err(FlexiError::Validation(format!(
+                "{} has a day without date",
+                path
+            )));
+        }
+        Ok(())
+    }
+
+    /// set working days found in `calendar` as public holidays, return how many were set
+    pub fn apply_calendar(&mut self, calendar: &HolidayCalendar, settings: &Settings) -> usize {
+        let mut days_off = calendar.days_in(self.year);
+        // the grid can start in previous month or end in next one
+        days_off.append(&mut calendar.days_in(self.year - 1));
+        days_off.append(&mut calendar.days_in(self.year + 1));
+        let mut count = 0;
+        for w in &mut self.weeks {
+            for day in w.days.iter_mut() {
+                let is_off = match day.date {
+                    Some(date) => days_off.contains_key(&date),
+                    None => false,
+                };
+                if is_off && day.status == DayStatus::Worked {
+                    day.status = DayStatus::PublicHoliday;
+                    count += 1;
+                }
+            }
+        }
+        self.update_balance(settings);
+        count
     }
 
     pub fn get_week_with_day(&self, d: NaiveDate) -> Option<(&FlexDay, &FlexWeek, i32)> {
-        let mut week_number = 1;
-        for w in &self.weeks {
-            if let Some(day) = w.days.iter().find(|&&day| {
+        for (idx, w) in self.weeks.iter().enumerate() {
+            if let Some(day) = w.days.iter().find(|day| {
                 if let Some(date) = day.date {
                     date == d
                 } else {
                     false
                 }
             }) {
-                return Some((&day, &w, week_number));
+                return Some((day, w, idx as i32 + 1));
             }
-            week_number += 1;
         }
         None
     }
 
-    pub fn total_minute(&self) -> i64 {
-        self.weeks.iter().fold(0, |acc, &w| acc + w.total_minutes())
+    pub fn total_minute(&self, settings: &Settings) -> i64 {
+        self.weeks
+            .iter()
+            .fold(0, |acc, w| acc + w.total_minutes(settings))
     }
 
-    pub fn update_balance(&mut self) {
-        self.balance = self.total_minute() - self.one_week_goal * (self.weeks.len() as i64);
+    pub fn projects(&self, settings: &Settings) -> ProjectReport {
+        let mut report = ProjectReport::default();
+        for w in &self.weeks {
+            report.merge(&w.projects(settings));
+        }
+        report
+    }
+
+    /// goal of the week at `idx` in `weeks`
+    pub fn week_goal(&self, idx: usize) -> i64 {
+        match self.week_goals.get(idx) {
+            Some(&goal) => goal,
+            None => self.one_week_goal,
+        }
+    }
+
+    /// goal of the week holding `d`
+    pub fn week_goal_for_day(&self, d: NaiveDate) -> i64 {
+        match self.get_week_with_day(d) {
+            Some((_, _, week_number)) => self.week_goal(week_number as usize - 1),
+            None => self.one_week_goal,
+        }
+    }
+
+    /// goal of the whole month
+    pub fn total_goal(&self) -> i64 {
+        (0..self.weeks.len()).fold(0, |acc, idx| acc + self.week_goal(idx))
+    }
+
+    pub fn update_balance(&mut self, settings: &Settings) {
+        self.balance = self.total_minute(settings) - self.total_goal();
     }
 
     pub fn update_day(&mut self, d: FlexDay) -> Option<FlexWeek> {
@@ -222,14 +284,14 @@ impl FlexMonth {
         None
     }
 
-    pub fn get_sick_days(&self) -> SickDays {
+    /// dates of the days with `status`
+    pub fn get_days_with(&self, status: DayStatus) -> Vec<NaiveDate> {
         self.weeks
             .iter()
             .flat_map(|w| {
                 w.days
-                    .to_vec()
-                    .into_iter()
-                    .filter(|d| d.status == DayStatus::Sick)
+                    .iter()
+                    .filter(move |d| d.status == status)
                     .map(|d| d.date.unwrap())
             })
             .collect()
@@ -239,149 +301,233 @@ impl FlexMonth {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::timedata;
+    use crate::savable::{backup_filename, fixture, load_file};
+    use crate::settings::ScheduleVersion;
+
+    #[test]
+    fn month_fixture_test() {
+        let path = fixture("v0/2017_05.json", "month");
+        let month = load_file::<FlexMonth>(&path).unwrap().unwrap();
+        assert_eq!((month.year, month.month), (2017, 5));
+        assert_eq!(month.weeks.len(), 4);
+        assert_eq!(
+            month.get_days_with(DayStatus::Sick),
+            vec![NaiveDate::from_ymd_opt(2017, 5, 2).unwrap()]
+        );
+        // days saved before version 5 count as clocked
+        let settings = Settings::default();
+        assert!(month
+            .weeks
+            .iter()
+            .flat_map(|w| &w.days)
+            .all(|d| d.is_clocked(&settings)));
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("{\n  \"version\": 5,\n  \"weeks\": ["));
+        assert!(std::path::Path::new(&backup_filename(&path, 0)).exists());
+        assert_eq!(load_file::<FlexMonth>(&path).unwrap().unwrap(), month);
+    }
+
+    #[test]
+    fn split_half_days_test() {
+        let month = serde_json::json!({ "weeks": [{ "days": [
+            { "status": "Half", "sessions": [{ "start": "13:15:00", "end": "16:50:00" }] },
+            { "status": "Half", "start": "09:10:00", "end": "12:45:00" },
+            { "status": "Worked", "start": "13:15:00", "end": "16:50:00" }
+        ]}]});
+        let month = split_half_days(month).unwrap();
+        let days = &month["weeks"][0]["days"];
+        assert_eq!(days[0]["status"], "HalfAm");
+        assert_eq!(days[1]["status"], "HalfPm");
+        assert_eq!(days[2]["status"], "Worked");
+    }
 
     #[test]
     fn get_week_with_day_test() {
         let settings: Settings = Default::default();
-        let m = FlexMonth::new(2017, 05, &settings);
+        let m = FlexMonth::new(2017, 5, &settings);
         let w = m
-            .get_week_with_day(NaiveDate::from_ymd(2017, 05, 10))
+            .get_week_with_day(NaiveDate::from_ymd_opt(2017, 5, 10).unwrap())
             .unwrap();
         assert_eq!(w.1.days[0].date.unwrap().day(), 8);
     }
 
     #[test]
     fn find_next_monday_test() {
-        let mut day = NaiveDate::from_ymd(2017, 04, 01);
+        let mut day = NaiveDate::from_ymd_opt(2017, 4, 1).unwrap();
         let mut first_monday = find_next_monday(day);
-        assert_eq!(first_monday, NaiveDate::from_ymd(2017, 04, 03));
+        assert_eq!(first_monday, NaiveDate::from_ymd_opt(2017, 4, 3).unwrap());
 
-        day = NaiveDate::from_ymd(2017, 05, 01);
+        day = NaiveDate::from_ymd_opt(2017, 5, 1).unwrap();
         first_monday = find_next_monday(day);
-        assert_eq!(first_monday, NaiveDate::from_ymd(2017, 05, 01));
+        assert_eq!(first_monday, NaiveDate::from_ymd_opt(2017, 5, 1).unwrap());
 
-        day = NaiveDate::from_ymd(2017, 03, 01);
+        day = NaiveDate::from_ymd_opt(2017, 3, 1).unwrap();
         first_monday = find_next_monday(day);
-        assert_eq!(first_monday, NaiveDate::from_ymd(2017, 03, 06));
+        assert_eq!(first_monday, NaiveDate::from_ymd_opt(2017, 3, 6).unwrap());
 
-        day = NaiveDate::from_ymd(2016, 12, 01);
+        day = NaiveDate::from_ymd_opt(2016, 12, 1).unwrap();
         first_monday = find_next_monday(day);
-        assert_eq!(first_monday, NaiveDate::from_ymd(2016, 12, 05));
+        assert_eq!(first_monday, NaiveDate::from_ymd_opt(2016, 12, 5).unwrap());
     }
 
     #[test]
     fn find_prec_monday_test() {
-        let mut day = NaiveDate::from_ymd(2017, 04, 01);
+        let mut day = NaiveDate::from_ymd_opt(2017, 4, 1).unwrap();
         let mut monday = find_prec_monday(day);
-        assert_eq!(monday, NaiveDate::from_ymd(2017, 03, 27));
+        assert_eq!(monday, NaiveDate::from_ymd_opt(2017, 3, 27).unwrap());
 
-        day = NaiveDate::from_ymd(2017, 05, 01);
+        day = NaiveDate::from_ymd_opt(2017, 5, 1).unwrap();
         monday = find_prec_monday(day);
-        assert_eq!(monday, NaiveDate::from_ymd(2017, 05, 01));
+        assert_eq!(monday, NaiveDate::from_ymd_opt(2017, 5, 1).unwrap());
 
-        day = NaiveDate::from_ymd(2017, 03, 01);
+        day = NaiveDate::from_ymd_opt(2017, 3, 1).unwrap();
         monday = find_prec_monday(day);
-        assert_eq!(monday, NaiveDate::from_ymd(2017, 02, 27));
+        assert_eq!(monday, NaiveDate::from_ymd_opt(2017, 2, 27).unwrap());
 
-        day = NaiveDate::from_ymd(2016, 12, 01);
+        day = NaiveDate::from_ymd_opt(2016, 12, 1).unwrap();
         monday = find_prec_monday(day);
-        assert_eq!(monday, NaiveDate::from_ymd(2016, 11, 28));
+        assert_eq!(monday, NaiveDate::from_ymd_opt(2016, 11, 28).unwrap());
     }
 
     #[test]
     fn find_last_sunday_test() {
-        let mut sunday = find_last_sunday_for(2017, 04);
-        assert_eq!(sunday, NaiveDate::from_ymd(2017, 04, 30));
+        let mut sunday = find_last_sunday_for(2017, 4);
+        assert_eq!(sunday, NaiveDate::from_ymd_opt(2017, 4, 30).unwrap());
 
-        sunday = find_last_sunday_for(2017, 05);
-        assert_eq!(sunday, NaiveDate::from_ymd(2017, 05, 28));
+        sunday = find_last_sunday_for(2017, 5);
+        assert_eq!(sunday, NaiveDate::from_ymd_opt(2017, 5, 28).unwrap());
 
-        sunday = find_last_sunday_for(2017, 03);
-        assert_eq!(sunday, NaiveDate::from_ymd(2017, 04, 02));
+        sunday = find_last_sunday_for(2017, 3);
+        assert_eq!(sunday, NaiveDate::from_ymd_opt(2017, 4, 2).unwrap());
 
         sunday = find_last_sunday_for(2016, 12);
-        assert_eq!(sunday, NaiveDate::from_ymd(2017, 01, 01));
+        assert_eq!(sunday, NaiveDate::from_ymd_opt(2017, 1, 1).unwrap());
+    }
+
+    #[test]
+    fn grid_month_for_test() {
+        // 1st of April 2017 is a Saturday, shown in March's grid
+        assert_eq!(
+            grid_month_for(NaiveDate::from_ymd_opt(2017, 4, 1).unwrap()),
+            (2017, 3)
+        );
+        assert_eq!(
+            grid_month_for(NaiveDate::from_ymd_opt(2017, 4, 3).unwrap()),
+            (2017, 4)
+        );
+        // Monday 30th of January 2017 starts February's grid
+        assert_eq!(
+            grid_month_for(NaiveDate::from_ymd_opt(2017, 1, 30).unwrap()),
+            (2017, 2)
+        );
+        assert_eq!(
+            grid_month_for(NaiveDate::from_ymd_opt(2017, 1, 1).unwrap()),
+            (2016, 12)
+        );
     }
 
     #[test]
     fn create_month_test() {
         let settings: Settings = Default::default();
-        let mut month = FlexMonth::new(2017, 05, &settings);
+        let mut month = FlexMonth::new(2017, 5, &settings);
 
         assert_eq!(month.weeks.len(), 4);
         assert_eq!(
             month.weeks[0].days[0].date,
-            Some(NaiveDate::from_ymd(2017, 05, 01))
+            Some(NaiveDate::from_ymd_opt(2017, 5, 1).unwrap())
         );
         assert_eq!(
             month.weeks[3].days[6].date,
-            Some(NaiveDate::from_ymd(2017, 05, 28))
+            Some(NaiveDate::from_ymd_opt(2017, 5, 28).unwrap())
         );
 
-        month = FlexMonth::new(2017, 02, &settings);
+        month = FlexMonth::new(2017, 2, &settings);
         assert_eq!(month.weeks.len(), 4);
         assert_eq!(
             month.weeks[0].days[0].date,
-            Some(NaiveDate::from_ymd(2017, 01, 30))
+            Some(NaiveDate::from_ymd_opt(2017, 1, 30).unwrap())
         );
         assert_eq!(
             month.weeks[3].days[6].date,
-            Some(NaiveDate::from_ymd(2017, 02, 26))
+            Some(NaiveDate::from_ymd_opt(2017, 2, 26).unwrap())
         );
 
-        month = FlexMonth::new(2017, 04, &settings);
+        month = FlexMonth::new(2017, 4, &settings);
         assert_eq!(month.weeks.len(), 4);
         assert_eq!(
             month.weeks[0].days[0].date,
-            Some(NaiveDate::from_ymd(2017, 04, 03))
+            Some(NaiveDate::from_ymd_opt(2017, 4, 3).unwrap())
         );
         assert_eq!(
             month.weeks[3].days[6].date,
-            Some(NaiveDate::from_ymd(2017, 04, 30))
+            Some(NaiveDate::from_ymd_opt(2017, 4, 30).unwrap())
         );
 
-        month = FlexMonth::new(2017, 01, &settings);
+        month = FlexMonth::new(2017, 1, &settings);
         assert_eq!(month.weeks.len(), 4);
         assert_eq!(
             month.weeks[0].days[0].date,
-            Some(NaiveDate::from_ymd(2017, 01, 02))
+            Some(NaiveDate::from_ymd_opt(2017, 1, 2).unwrap())
         );
         assert_eq!(
             month.weeks[3].days[6].date,
-            Some(NaiveDate::from_ymd(2017, 01, 29))
+            Some(NaiveDate::from_ymd_opt(2017, 1, 29).unwrap())
         );
 
         month = FlexMonth::new(2016, 11, &settings);
         assert_eq!(month.weeks.len(), 4);
         assert_eq!(
             month.weeks[0].days[0].date,
-            Some(NaiveDate::from_ymd(2016, 10, 31))
+            Some(NaiveDate::from_ymd_opt(2016, 10, 31).unwrap())
         );
         assert_eq!(
             month.weeks[3].days[6].date,
-            Some(NaiveDate::from_ymd(2016, 11, 27))
+            Some(NaiveDate::from_ymd_opt(2016, 11, 27).unwrap())
         );
 
         month = FlexMonth::new(2016, 12, &settings);
         assert_eq!(month.weeks.len(), 5);
         assert_eq!(
             month.weeks[0].days[0].date,
-            Some(NaiveDate::from_ymd(2016, 11, 28))
+            Some(NaiveDate::from_ymd_opt(2016, 11, 28).unwrap())
         );
         assert_eq!(
             month.weeks[4].days[6].date,
-            Some(NaiveDate::from_ymd(2017, 01, 01))
+            Some(NaiveDate::from_ymd_opt(2017, 1, 1).unwrap())
         );
     }
 
     #[test]
     fn save_load_test() {
-        timedata::create_data_dir();
+        crate::datadir::test_data_dir("save_load_test");
         let settings: Settings = Default::default();
-        let m = FlexMonth::new(2017, 05, &settings);
-        m.save();
-        let loaded = FlexMonth::load(2017, 05, &settings);
+        let m = FlexMonth::new(2017, 5, &settings);
+        m.save().unwrap();
+        let loaded = FlexMonth::load(2017, 5, &settings).unwrap();
         assert_eq!(m, loaded);
     }
+
+    #[test]
+    fn week_goals_test() {
+        let mut settings: Settings = Default::default();
+        settings.schedule_history.push(ScheduleVersion {
+            from: NaiveDate::from_ymd_opt(2017, 5, 15).unwrap(),
+            week_sched: settings.week_sched.clone(),
+            week_goal: 30 * 60,
+        });
+        let mut m = FlexMonth::new(2017, 5, &settings);
+        assert_eq!(m.week_goal(0), 37 * 60);
+        assert_eq!(m.week_goal(2), 30 * 60);
+        assert_eq!(
+            m.week_goal_for_day(NaiveDate::from_ymd_opt(2017, 5, 10).unwrap()),
+            37 * 60
+        );
+        assert_eq!(m.total_goal(), 2 * 37 * 60 + 2 * 30 * 60);
+
+        // months saved before schedule history use the same goal for every week
+        m.week_goals.clear();
+        m.update_balance(&settings);
+        assert_eq!(m.total_goal(), 4 * m.one_week_goal);
+    }
 }
@@ -1,48 +1,38 @@
-use crate::timedata::DayStatus;
+use crate::settings::Settings;
 use crate::timedata::FlexDay;
+use crate::timedata::ProjectReport;
 use chrono::Weekday;
 use serde_derive::{Deserialize, Serialize};
 use std::default::Default;
-use std::fmt::{Display, Formatter, Result};
 use std::iter::Iterator;
 use std::ops::{Index, IndexMut};
 
-#[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct FlexWeek {
     pub days: [FlexDay; 7],
 }
 
-impl Display for FlexWeek {
-    fn fmt(&self, f: &mut Formatter) -> Result {
-        for d in &self.days {
-            if d.status != DayStatus::Weekend {
-                writeln!(f, "{}", d).expect("Failed to write FlexWeek to Display");
-            }
-        }
-        let hours = self.total_minutes();
-        writeln!(
-            f,
-            "{:->40} {:02}:{:02}",
-            " Total =",
-            hours / 60,
-            hours - (hours / 60) * 60
-        )
-    }
-}
-
 impl FlexWeek {
     pub fn new(days: [FlexDay; 7]) -> FlexWeek {
-        FlexWeek { days: days }
+        FlexWeek { days }
     }
 
-    pub fn total_minutes(&self) -> i64 {
+    pub fn total_minutes(&self, settings: &Settings) -> i64 {
         self.days
             .iter()
-            .fold(0, |acc, &day| acc + day.total_minutes())
+            .fold(0, |acc, day| acc + day.total_minutes(settings))
+    }
+
+    pub fn projects(&self, settings: &Settings) -> ProjectReport {
+        let mut report = ProjectReport::default();
+        for d in &self.days {
+            report.merge(&d.projects(settings));
+        }
+        report
     }
 
-    pub fn total_str(&self) -> String {
-        let hours = self.total_minutes();
+    pub fn total_str(&self, settings: &Settings) -> String {
+        let hours = self.total_minutes(settings);
         format!("{:02}:{:02}", hours / 60, hours - (hours / 60) * 60)
     }
 }
@@ -50,7 +40,7 @@ impl FlexWeek {
 impl Default for FlexWeek {
     fn default() -> FlexWeek {
         let mut w = FlexWeek {
-            days: [Default::default(); 7],
+            days: Default::default(),
         };
         let mut wd = Weekday::Mon;
         for day in &mut (w.days) {
@@ -69,7 +59,7 @@ impl Index<u32> for FlexWeek {
 }
 
 impl IndexMut<u32> for FlexWeek {
-    fn index_mut<'a>(&'a mut self, idx: u32) -> &'a mut FlexDay {
+    fn index_mut(&mut self, idx: u32) -> &mut FlexDay {
         &mut self.days[idx as usize]
     }
 }
@@ -80,12 +70,13 @@ mod tests {
 
     #[test]
     fn total_minutes_test() {
+        let settings = Settings::default();
         let mut w = FlexWeek {
-            days: [Default::default(); 7],
+            days: Default::default(),
         };
-        assert_eq!(w.total_minutes(), (8 * 60 - 30) * 7);
+        assert_eq!(w.total_minutes(&settings), (8 * 60 - 30) * 7);
 
         w = Default::default();
-        assert_eq!(w.total_minutes(), (8 * 60 - 30) * 5);
+        assert_eq!(w.total_minutes(&settings), (8 * 60 - 30) * 5);
     }
 }
@@ -0,0 +1,185 @@
+use crate::error::FlexiError;
+use crate::storage::storage;
+use crate::timedata::FlexDay;
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Edit,
+    Undo,
+    Redo,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Edit => write!(f, "edit"),
+            Action::Undo => write!(f, "undo"),
+            Action::Redo => write!(f, "redo"),
+        }
+    }
+}
+
+/// One change of a day, `old` and `new` are the whole day before and after it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub at: NaiveDateTime,
+    pub action: Action,
+    pub old: FlexDay,
+    pub new: FlexDay,
+}
+
+impl JournalEntry {
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.new.date
+    }
+}
+
+/// Every change of the days, only ever appended to. Undo and redo walk back and forth
+/// through the edits and are recorded as well
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+    // edits that can be undone, the last one is undone first
+    undo: Vec<usize>,
+    // undone edits, the last one is redone first
+    redo: Vec<usize>,
+}
+
+impl Journal {
+    pub fn new(entries: Vec<JournalEntry>) -> Journal {
+        let mut journal = Journal {
+            entries: Vec::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        };
+        for entry in entries {
+            journal.push(entry);
+        }
+        journal
+    }
+
+    pub fn load() -> Result<Journal, FlexiError> {
+        Ok(Journal::new(storage().load_journal()?))
+    }
+
+    fn push(&mut self, entry: JournalEntry) {
+        let idx = self.entries.len();
+        match entry.action {
+            Action::Edit => {
+                self.undo.push(idx);
+                self.redo.clear();
+            }
+            Action::Undo => {
+                if let Some(edit) = self.undo.pop() {
+                    self.redo.push(edit);
+                }
+            }
+            Action::Redo => {
+                if let Some(edit) = self.redo.pop() {
+                    self.undo.push(edit);
+                }
+            }
+        }
+        self.entries.push(entry);
+    }
+
+    /// the day as it was before the last edit still applied
+    pub fn to_undo(&self) -> Option<&FlexDay> {
+        self.undo.last().map(|&idx| &self.entries[idx].old)
+    }
+
+    /// the day as it was after the last undone edit
+    pub fn to_redo(&self) -> Option<&FlexDay> {
+        self.redo.last().map(|&idx| &self.entries[idx].new)
+    }
+
+    /// save the change of `old` into `new`, an edit changing nothing is not recorded
+    pub fn record(
+        &mut self,
+        action: Action,
+        old: FlexDay,
+        new: FlexDay,
+        at: NaiveDateTime,
+    ) -> Result<(), FlexiError> {
+        if action == Action::Edit && old == new {
+            return Ok(());
+        }
+        let entry = JournalEntry {
+            // to the second, the journal is read by people too
+            at: at.with_nanosecond(0).unwrap_or(at),
+            action,
+            old,
+            new,
+        };
+        storage().append_journal(&entry)?;
+        self.push(entry);
+        Ok(())
+    }
+
+    /// changes of the day at `date`, newest first
+    pub fn history(&self, date: NaiveDate) -> Vec<&JournalEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|e| e.date() == Some(date))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+    use crate::timedata::DayStatus;
+
+    fn entry(action: Action, old: &FlexDay, new: &FlexDay) -> JournalEntry {
+        JournalEntry {
+            at: NaiveDate::from_ymd_opt(2019, 5, 14)
+                .unwrap()
+                .and_hms_opt(9, 0, 0)
+                .unwrap(),
+            action,
+            old: old.clone(),
+            new: new.clone(),
+        }
+    }
+
+    #[test]
+    fn undo_redo_test() {
+        let settings = Settings::default();
+        let worked = FlexDay::new(NaiveDate::from_ymd_opt(2019, 5, 14).unwrap(), &settings);
+        let mut sick = worked.clone();
+        sick.status = DayStatus::Sick;
+        let mut holiday = worked.clone();
+        holiday.status = DayStatus::Holiday;
+
+        let mut journal = Journal::new(vec![
+            entry(Action::Edit, &worked, &sick),
+            entry(Action::Edit, &sick, &holiday),
+            entry(Action::Undo, &holiday, &sick),
+        ]);
+        assert_eq!(journal.to_undo(), Some(&worked));
+        assert_eq!(journal.to_redo(), Some(&holiday));
+        journal.push(entry(Action::Undo, &sick, &worked));
+        assert_eq!(journal.to_undo(), None);
+        assert_eq!(journal.to_redo(), Some(&sick));
+        journal.push(entry(Action::Redo, &worked, &sick));
+        assert_eq!(journal.to_undo(), Some(&worked));
+
+        // a new edit drops what was undone
+        journal.push(entry(Action::Edit, &sick, &holiday));
+        assert_eq!(journal.to_redo(), None);
+        assert_eq!(journal.to_undo(), Some(&sick));
+        assert_eq!(
+            journal
+                .history(NaiveDate::from_ymd_opt(2019, 5, 14).unwrap())
+                .len(),
+            6
+        );
+        assert!(journal
+            .history(NaiveDate::from_ymd_opt(2019, 5, 15).unwrap())
+            .is_empty());
+    }
+}
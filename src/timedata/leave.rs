@@ -0,0 +1,72 @@
+use crate::settings::Settings;
+use crate::timedata::FlexMonth;
+use chrono::{Duration, NaiveDate, NaiveTime};
+
+/// Times today's work can end at to reach each goal, None past midnight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeaveTimes {
+    // hours of the schedule for today
+    pub day: Option<NaiveTime>,
+    // no deficit left in the week
+    pub week: Option<NaiveTime>,
+    // flexi account back to zero
+    pub account: Option<NaiveTime>,
+}
+
+impl LeaveTimes {
+    /// None if `today` is not in `month` or is not worked. `account` is the flexi account
+    /// with today ending as saved
+    pub fn new(
+        month: &FlexMonth,
+        today: NaiveDate,
+        account: i64,
+        settings: &Settings,
+    ) -> Option<LeaveTimes> {
+        let (d, week, _) = month.get_week_with_day(today)?;
+        if !d.status.is_timed(settings) {
+            return None;
+        }
+        let total = d.total_minutes(settings);
+        // every goal is reached by moving the end by the minutes missing
+        let end = d.end();
+        let leave = |missing: i64| match end.overflowing_add_signed(Duration::minutes(missing)) {
+            (time, 0) => Some(time),
+            _ => None,
+        };
+        Some(LeaveTimes {
+            day: leave(settings.target_minutes_for(&today) - total),
+            week: leave(month.week_goal_for_day(today) - week.total_minutes(settings)),
+            account: leave(-account),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leave_times_test() {
+        let settings = Settings::default();
+        let month = FlexMonth::new(2017, 5, &settings);
+        let friday = NaiveDate::from_ymd_opt(2017, 5, 5).unwrap();
+        let leave = LeaveTimes::new(&month, friday, -30, &settings).unwrap();
+        assert_eq!(leave.day, Some(NaiveTime::from_hms_opt(16, 50, 0).unwrap()));
+        // 37:10 scheduled in the week for a goal of 37:00
+        assert_eq!(
+            leave.week,
+            Some(NaiveTime::from_hms_opt(16, 40, 0).unwrap())
+        );
+        assert_eq!(
+            leave.account,
+            Some(NaiveTime::from_hms_opt(17, 20, 0).unwrap())
+        );
+
+        let leave = LeaveTimes::new(&month, friday, -10 * 60, &settings).unwrap();
+        assert_eq!(leave.account, None);
+        assert_eq!(
+            LeaveTimes::new(&month, friday.succ_opt().unwrap(), 0, &settings),
+            None
+        );
+    }
+}
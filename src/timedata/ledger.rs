@@ -0,0 +1,90 @@
+use crate::error::FlexiError;
+use crate::settings::Settings;
+use crate::storage::storage;
+use crate::timedata::FlexMonth;
+use chrono::Datelike;
+use std::collections::BTreeMap;
+
+/// Running flexi account: balance of every saved month since `settings.ledger.start`, on
+/// top of the opening balance
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ledger {
+    opening_balance: i64,
+    start: Option<(i32, u32)>,
+    // balance of each saved month, keyed by (year, month)
+    balances: BTreeMap<(i32, u32), i64>,
+}
+
+impl Ledger {
+    pub fn new(settings: &Settings) -> Ledger {
+        Ledger {
+            opening_balance: settings.ledger.opening_balance,
+            start: settings.ledger.start.map(|d| (d.year(), d.month())),
+            balances: BTreeMap::new(),
+        }
+    }
+
+    /// build the ledger from the saved months
+    pub fn load(settings: &Settings) -> Result<Ledger, FlexiError> {
+        let mut ledger = Ledger::new(settings);
+        ledger.balances.extend(storage().month_balances()?);
+        Ok(ledger)
+    }
+
+    /// record the current balance of `month`, to be called each time it is saved
+    pub fn update(&mut self, month: &FlexMonth) {
+        self.balances
+            .insert((month.year, month.month), month.balance);
+    }
+
+    fn counts(&self, key: (i32, u32)) -> bool {
+        match self.start {
+            Some(start) => key >= start,
+            None => true,
+        }
+    }
+
+    /// account balance at the end of the given month
+    pub fn total_until(&self, year: i32, month: u32) -> i64 {
+        self.opening_balance
+            + self
+                .balances
+                .range(..=(year, month))
+                .filter(|(&key, _)| self.counts(key))
+                .fold(0, |acc, (_, &balance)| acc + balance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn month_with_balance(year: i32, month: u32, balance: i64) -> FlexMonth {
+        let mut m = FlexMonth::new(year, month, &Settings::default());
+        m.balance = balance;
+        m
+    }
+
+    #[test]
+    fn running_balance_test() {
+        let mut settings = Settings::default();
+        settings.ledger.opening_balance = 60;
+        settings.ledger.start = Some(NaiveDate::from_ymd_opt(2018, 12, 15).unwrap());
+        let mut ledger = Ledger::new(&settings);
+        ledger.update(&month_with_balance(2018, 11, 1000));
+        ledger.update(&month_with_balance(2018, 12, 30));
+        ledger.update(&month_with_balance(2019, 1, -10));
+        ledger.update(&month_with_balance(2019, 2, 5));
+
+        // November is before the start of the account
+        assert_eq!(ledger.total_until(2018, 11), 60);
+        assert_eq!(ledger.total_until(2018, 12), 90);
+        assert_eq!(ledger.total_until(2019, 1), 80);
+        assert_eq!(ledger.total_until(2019, 2), 85);
+
+        // editing a past month changes the following totals
+        ledger.update(&month_with_balance(2018, 12, -30));
+        assert_eq!(ledger.total_until(2019, 2), 25);
+    }
+}
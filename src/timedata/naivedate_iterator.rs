@@ -22,7 +22,7 @@ impl Iterator for NaiveDateIter {
             None
         } else {
             let res = self.start;
-            self.start = self.start.succ();
+            self.start = self.start.succ_opt().unwrap();
             Some(res)
         }
     }
@@ -30,12 +30,8 @@ impl Iterator for NaiveDateIter {
 
 #[test]
 fn naivedate_iter_test() {
-    let first_monday = NaiveDate::from_ymd(2017, 04, 01);
-    let last_sunday = NaiveDate::from_ymd(2017, 04, 30);
-    let mut itr = NaiveDateIter::new(first_monday, last_sunday);
-    let mut count = 0;
-    while let Some(_) = itr.next() {
-        count += 1;
-    }
-    assert_eq!(count, 30);
+    let first_monday = NaiveDate::from_ymd_opt(2017, 4, 1).unwrap();
+    let last_sunday = NaiveDate::from_ymd_opt(2017, 4, 30).unwrap();
+    let itr = NaiveDateIter::new(first_monday, last_sunday);
+    assert_eq!(itr.count(), 30);
 }
@@ -0,0 +1,103 @@
+use crate::error::FlexiError;
+use crate::settings::Settings;
+use crate::storage::storage;
+use crate::timedata::minutes_to_string;
+use std::collections::BTreeMap;
+
+/// Worked minutes by project, the time of untagged sessions is under None
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectReport {
+    minutes: BTreeMap<Option<String>, i64>,
+}
+
+impl ProjectReport {
+    pub fn add(&mut self, project: Option<&str>, minutes: i64) {
+        if minutes != 0 {
+            *self
+                .minutes
+                .entry(project.map(|p| p.to_string()))
+                .or_insert(0) += minutes;
+        }
+    }
+
+    pub fn merge(&mut self, other: &ProjectReport) {
+        for (project, minutes) in &other.minutes {
+            self.add(project.as_deref(), *minutes);
+        }
+    }
+
+    pub fn total(&self) -> i64 {
+        self.minutes.values().sum()
+    }
+
+    /// every project with its minutes, most worked first
+    pub fn projects(&self) -> Vec<(Option<&str>, i64)> {
+        let mut projects: Vec<(Option<&str>, i64)> = self
+            .minutes
+            .iter()
+            .map(|(p, m)| (p.as_deref(), *m))
+            .collect();
+        projects.sort_by_key(|&(_, minutes)| -minutes);
+        projects
+    }
+
+    /// report of the saved months of `year`
+    pub fn year(year: i32, settings: &Settings) -> Result<ProjectReport, FlexiError> {
+        let mut report = ProjectReport::default();
+        for (y, m) in storage().saved_months()? {
+            if y != year {
+                continue;
+            }
+            if let Some(month) = storage().load_month(y, m)? {
+                report.merge(&month.projects(settings));
+            }
+        }
+        Ok(report)
+    }
+
+    /// one line per project: name, hours and share of the total
+    pub fn lines(&self) -> Vec<String> {
+        let total = self.total();
+        let mut lines: Vec<String> = self
+            .projects()
+            .into_iter()
+            .map(|(project, minutes)| {
+                let share = if total > 0 { minutes * 100 / total } else { 0 };
+                format!(
+                    "{:<20} {:>7} {:>4}%",
+                    project.unwrap_or("(no project)"),
+                    minutes_to_string(minutes),
+                    share
+                )
+            })
+            .collect();
+        lines.push(format!("{:<20} {:>7}", "Total", minutes_to_string(total)));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_test() {
+        let mut report = ProjectReport::default();
+        report.add(Some("acme"), 90);
+        report.add(None, 30);
+        report.add(Some("zen"), 0);
+        let mut other = ProjectReport::default();
+        other.add(Some("acme"), 120);
+        report.merge(&other);
+        assert_eq!(report.total(), 240);
+        assert_eq!(report.projects(), vec![(Some("acme"), 210), (None, 30)]);
+        assert_eq!(
+            report.lines(),
+            vec![
+                "acme                   03:30   87%",
+                "(no project)           00:30   12%",
+                "Total                  04:00",
+            ]
+        );
+    }
+}